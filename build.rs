@@ -0,0 +1,55 @@
+//! Compila el runtime de `src/runtime` como biblioteca estática para que el
+//! compilador la enlace con los ejecutables que genera. Se invoca `rustc`
+//! directamente porque Cargo no entrega la biblioteca estática de una
+//! dependencia.
+
+use std::env;
+use std::path::PathBuf;
+use std::process::Command;
+
+fn main() {
+    let out_dir = PathBuf::from(env::var_os("OUT_DIR").expect("Cargo define OUT_DIR"));
+    let rustc = env::var_os("RUSTC").unwrap_or_else(|| "rustc".into());
+    let target = env::var("TARGET").expect("Cargo define TARGET");
+    let output = Command::new(rustc)
+        .args(["--crate-name", "hulk_runtime", "--crate-type", "staticlib"])
+        .args([
+            "--edition",
+            "2021",
+            "-C",
+            "opt-level=2",
+            "--target",
+            &target,
+        ])
+        // Las bibliotecas del sistema que pide la estándar de Rust en este
+        // target, para pasárselas al enlazador junto con el runtime
+        .args(["--print", "native-static-libs"])
+        .arg("--out-dir")
+        .arg(&out_dir)
+        .arg("src/runtime/src/lib.rs")
+        .output()
+        .expect("no se pudo ejecutar rustc para compilar el runtime");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        output.status.success(),
+        "no se pudo compilar el runtime:\n{}",
+        stderr
+    );
+    let native_libs = stderr
+        .lines()
+        .find_map(|line| line.split_once("native-static-libs:"))
+        .map(|(_, libs)| libs.trim())
+        .expect("rustc no informó las bibliotecas nativas del runtime");
+
+    let library = if target.contains("windows") {
+        "hulk_runtime.lib"
+    } else {
+        "libhulk_runtime.a"
+    };
+    println!("cargo:rerun-if-changed=src/runtime/src");
+    println!(
+        "cargo:rustc-env=HULK_RUNTIME_LIBRARY={}",
+        out_dir.join(library).display()
+    );
+    println!("cargo:rustc-env=HULK_RUNTIME_NATIVE_LIBS={}", native_libs);
+}
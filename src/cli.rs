@@ -0,0 +1,312 @@
+use std::path::PathBuf;
+
+/// Subcomando pedido en la línea de comandos.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Command {
+    /// Solo análisis léxico, sintáctico y semántico
+    Check,
+    /// Compila y escribe el artefacto pedido en disco
+    Build,
+    /// Compila y ejecuta el programa
+    Run,
+    /// Compila y escribe el artefacto pedido en la salida estándar (o en `-o`)
+    Emit,
+    /// Sesión interactiva sobre el intérprete
+    Repl,
+    /// Reescribe el script en el formato canónico
+    Fmt,
+}
+
+/// Artefacto que produce la compilación (`--emit`).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EmitKind {
+    Ast,
+    /// Listado del bytecode de la máquina virtual
+    Bytecode,
+    /// Volcado del HIR tipado que consumen los backends nativos
+    Hir,
+    /// Volcado del MIR: bloques básicos, `phi` e instrucciones
+    Mir,
+    Ir,
+    Asm,
+    Obj,
+    Exe,
+}
+
+impl EmitKind {
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "ast" => Ok(EmitKind::Ast),
+            "bytecode" => Ok(EmitKind::Bytecode),
+            "hir" => Ok(EmitKind::Hir),
+            "mir" => Ok(EmitKind::Mir),
+            "ir" | "llvm-ir" => Ok(EmitKind::Ir),
+            "asm" => Ok(EmitKind::Asm),
+            "obj" => Ok(EmitKind::Obj),
+            "exe" => Ok(EmitKind::Exe),
+            _ => Err(format!(
+                "Valor inválido para --emit: '{}' (se esperaba ast, bytecode, hir, mir, ir, asm, obj o exe)",
+                s
+            )),
+        }
+    }
+
+    /// Extensión del archivo de salida por defecto.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            EmitKind::Ast => "ast",
+            EmitKind::Bytecode => "bc",
+            EmitKind::Hir => "hir",
+            EmitKind::Mir => "mir",
+            EmitKind::Ir => "ll",
+            EmitKind::Asm => "s",
+            EmitKind::Obj => "o",
+            EmitKind::Exe => {
+                if cfg!(windows) {
+                    "exe"
+                } else {
+                    ""
+                }
+            }
+        }
+    }
+}
+
+/// Formato del volcado del AST (`--ast`).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AstFormat {
+    /// Árbol indentado para leerlo en la terminal
+    Text,
+    Json,
+    SExpr,
+}
+
+impl AstFormat {
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "text" => Ok(AstFormat::Text),
+            "json" => Ok(AstFormat::Json),
+            "sexpr" => Ok(AstFormat::SExpr),
+            _ => Err(format!(
+                "Valor inválido para --ast: '{}' (se esperaba text, json o sexpr)",
+                s
+            )),
+        }
+    }
+}
+
+/// Cómo ejecuta `hulk run` el programa.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Backend {
+    /// Compila a un ejecutable nativo con LLVM
+    Native,
+    /// Evalúa el AST con el intérprete, sin toolchain externo
+    Interpreter,
+    /// Compila a bytecode y lo ejecuta en la máquina virtual
+    Vm,
+}
+
+#[derive(Debug, Clone)]
+pub struct Options {
+    pub command: Command,
+    pub input: PathBuf,
+    pub output: Option<PathBuf>,
+    pub emit: EmitKind,
+    /// Formato de `--emit=ast`
+    pub ast_format: AstFormat,
+    pub opt_level: u8,
+    pub backend: Backend,
+    /// Con `fmt`, solo comprueba el formato sin reescribir el archivo
+    pub check: bool,
+    /// Imprime el código preprocesado y los pasos de la compilación
+    pub verbose: bool,
+    /// Trata las advertencias como errores
+    pub deny_warnings: bool,
+    /// Advierte cuando una declaración oculta a otra del mismo nombre
+    pub warn_shadowing: bool,
+    /// El ejecutable imprime al terminar las estadísticas del recolector
+    pub gc_stats: bool,
+    /// Argumentos para el programa en `hulk run`, después de `--`
+    pub program_args: Vec<String>,
+}
+
+impl Options {
+    /// Ruta de salida: la de `-o` o el nombre del script con la extensión del artefacto.
+    pub fn output_path(&self) -> PathBuf {
+        match &self.output {
+            Some(path) => path.clone(),
+            None => {
+                let extension = match (self.emit, self.ast_format) {
+                    (EmitKind::Ast, AstFormat::Json) => "json",
+                    (EmitKind::Ast, AstFormat::SExpr) => "sexp",
+                    _ => self.emit.extension(),
+                };
+                self.input.with_extension(extension)
+            }
+        }
+    }
+}
+
+pub fn usage() -> String {
+    "Uso: hulk <comando> <script.hulk> [opciones]
+     hulk repl
+
+Comandos:
+  check    Analiza el script y reporta errores sin generar código
+  build    Compila el script (por defecto a un ejecutable)
+  run      Compila el script y lo ejecuta; los argumentos tras `--` van al programa
+  emit     Compila el script y escribe el resultado (por defecto LLVM IR) en stdout
+  repl     Evalúa expresiones y declaraciones de forma interactiva
+  fmt      Reescribe el script con el formato canónico
+
+Opciones:
+  -o <ruta>               Archivo de salida
+  --emit=ast|bytecode|hir|mir|ir|asm|obj|exe
+                          Artefacto a generar
+  --ast=text|json|sexpr   Formato del AST (implica --emit=ast)
+  --opt-level=<0-3>       Nivel de optimización (también -O<n>)
+  --interp                Con `run`, interpreta el programa en lugar de compilarlo
+  --vm                    Con `run`, ejecuta el programa en la máquina virtual de bytecode
+  --check                 Con `fmt`, falla si el script no tiene el formato canónico
+  --deny-warnings         Falla si el análisis produce advertencias
+  --warn-shadowing        Advierte cuando una declaración oculta a otra del mismo nombre
+  --gc-stats              El ejecutable nativo imprime en stderr las estadísticas del recolector
+  -v, --verbose           Muestra el código preprocesado y los pasos ejecutados
+  -h, --help              Muestra esta ayuda"
+        .to_string()
+}
+
+fn parse_opt_level(value: &str) -> Result<u8, String> {
+    match value.parse::<u8>() {
+        Ok(level) if level <= 3 => Ok(level),
+        _ => Err(format!(
+            "Valor inválido para --opt-level: '{}' (se esperaba 0, 1, 2 o 3)",
+            value
+        )),
+    }
+}
+
+/// Interpreta los argumentos (sin el nombre del ejecutable).
+/// `Ok(None)` significa que se pidió la ayuda.
+pub fn parse_args(args: &[String]) -> Result<Option<Options>, String> {
+    let mut iter = args.iter();
+    let command = match iter.next().map(String::as_str) {
+        None | Some("-h") | Some("--help") | Some("help") => return Ok(None),
+        Some("check") => Command::Check,
+        Some("build") => Command::Build,
+        Some("run") => Command::Run,
+        Some("emit") => Command::Emit,
+        Some("repl") => Command::Repl,
+        Some("fmt") => Command::Fmt,
+        Some(other) => return Err(format!("Comando desconocido: '{}'", other)),
+    };
+
+    let mut input = None;
+    let mut output = None;
+    let mut emit = None;
+    let mut ast_format = None;
+    let mut opt_level = 0;
+    let mut backend = Backend::Native;
+    let mut check = false;
+    let mut verbose = false;
+    let mut deny_warnings = false;
+    let mut warn_shadowing = false;
+    let mut gc_stats = false;
+    let mut program_args = Vec::new();
+
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "-v" | "--verbose" => verbose = true,
+            "--deny-warnings" => deny_warnings = true,
+            "--warn-shadowing" => warn_shadowing = true,
+            "--gc-stats" => gc_stats = true,
+            "--interp" => backend = Backend::Interpreter,
+            "--vm" => backend = Backend::Vm,
+            "--check" => check = true,
+            "-o" => match iter.next() {
+                Some(path) => output = Some(PathBuf::from(path)),
+                None => return Err("Falta la ruta después de -o".to_string()),
+            },
+            "--emit" => match iter.next() {
+                Some(kind) => emit = Some(EmitKind::from_str(kind)?),
+                None => return Err("Falta el valor después de --emit".to_string()),
+            },
+            "--ast" => match iter.next() {
+                Some(format) => ast_format = Some(AstFormat::from_str(format)?),
+                None => return Err("Falta el valor después de --ast".to_string()),
+            },
+            "--opt-level" => match iter.next() {
+                Some(level) => opt_level = parse_opt_level(level)?,
+                None => return Err("Falta el valor después de --opt-level".to_string()),
+            },
+            "--" => {
+                program_args.extend(iter.by_ref().cloned());
+            }
+            _ => {
+                if let Some(kind) = arg.strip_prefix("--emit=") {
+                    emit = Some(EmitKind::from_str(kind)?);
+                } else if let Some(format) = arg.strip_prefix("--ast=") {
+                    ast_format = Some(AstFormat::from_str(format)?);
+                } else if let Some(level) = arg.strip_prefix("--opt-level=") {
+                    opt_level = parse_opt_level(level)?;
+                } else if let Some(level) = arg.strip_prefix("-O") {
+                    opt_level = parse_opt_level(level)?;
+                } else if arg.starts_with('-') {
+                    return Err(format!("Opción desconocida: '{}'", arg));
+                } else if input.is_none() {
+                    input = Some(PathBuf::from(arg));
+                } else {
+                    return Err(format!("Argumento inesperado: '{}'", arg));
+                }
+            }
+        }
+    }
+
+    let input = match (command, input) {
+        (Command::Repl, None) => PathBuf::new(),
+        (Command::Repl, Some(_)) => return Err("`hulk repl` no recibe un script".to_string()),
+        (_, Some(input)) => input,
+        (_, None) => return Err("Falta el script de entrada".to_string()),
+    };
+    let emit = match (command, emit, ast_format) {
+        (_, Some(kind), _) => kind,
+        (_, None, Some(_)) => EmitKind::Ast,
+        (Command::Emit, None, None) => EmitKind::Ir,
+        (_, None, None) => EmitKind::Exe,
+    };
+    if ast_format.is_some() && emit != EmitKind::Ast {
+        return Err("--ast solo se admite con --emit=ast".to_string());
+    }
+    if command == Command::Run && emit != EmitKind::Exe {
+        return Err("`hulk run` no admite --emit".to_string());
+    }
+    if backend != Backend::Native && command != Command::Run {
+        return Err("--interp y --vm solo se admiten con `hulk run`".to_string());
+    }
+    if gc_stats && backend != Backend::Native {
+        return Err("--gc-stats solo se admite con el backend nativo".to_string());
+    }
+    if check && command != Command::Fmt {
+        return Err("--check solo se admite con `hulk fmt`".to_string());
+    }
+    if !program_args.is_empty() && command != Command::Run {
+        return Err("Los argumentos tras `--` solo se admiten con `hulk run`".to_string());
+    }
+
+    Ok(Some(Options {
+        command,
+        input,
+        output,
+        emit,
+        ast_format: ast_format.unwrap_or(AstFormat::Text),
+        opt_level,
+        backend,
+        check,
+        verbose,
+        deny_warnings,
+        warn_shadowing,
+        gc_stats,
+        program_args,
+    }))
+}
@@ -0,0 +1,778 @@
+use crate::cli::{AstFormat, Backend, Command, EmitKind, Options};
+use crate::repl;
+use crate::toolchain::{NativeOutput, Toolchain};
+use parser::comments;
+use parser::visitor::ast_optimizer;
+use parser::visitor::ast_printer_visitor::AstPrinterVisitor;
+use parser::visitor::semantic_type_checker::SemanticTypeChecker;
+use parser::visitor::Visitable;
+use parser::visitor::{AstOptimizer, LLVMGenerator, Linter};
+use parser::visitor::{AstSerializer, BytecodeCompiler, CodeFormatter, Interpreter, RuntimeError};
+use parser::vm::Vm;
+use parser::{hir, mir, Diagnostic, Program, Severity};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+/// Programa ya parseado y chequeado, listo para los backends.
+pub struct Analysis {
+    /// Código preprocesado; las posiciones del AST se refieren a este texto
+    pub source: String,
+    /// Posición en el archivo original de cada byte de `source`
+    pub offsets: Vec<usize>,
+    pub program: Program,
+    pub checker: SemanticTypeChecker,
+}
+
+/// Preprocesa, parsea y chequea `source`, reportando los errores en stderr.
+/// Devuelve `None` si hubo algún error.
+pub fn analyze(source: &str, verbose: bool) -> Option<Analysis> {
+    let source = match comments::strip_comments(source) {
+        Ok((stripped, _)) => stripped,
+        Err(message) => {
+            eprintln!("Error: {}", message);
+            return None;
+        }
+    };
+    let preprocessed = ast_optimizer::preprocess_functions(&source);
+    if verbose {
+        println!(
+            "--- Código preprocesado ---\n{}\n---------------------------",
+            preprocessed
+        );
+    }
+
+    let program = match parser::parse_program(&preprocessed) {
+        Ok(program) => program,
+        Err(err) => {
+            if let Some(line) = err.line {
+                eprintln!("Error en la línea {}: {}", line, err.message);
+            } else {
+                eprintln!("Error: {}", err.message);
+            }
+            return None;
+        }
+    };
+
+    let mut checker = SemanticTypeChecker::new();
+    checker.check(&program);
+    if !checker.errors.is_empty() {
+        for diagnostic in checker.diagnostics() {
+            eprintln!("{}", diagnostic.with_line(&preprocessed));
+        }
+        return None;
+    }
+
+    Some(Analysis {
+        offsets: comments::unmap_offsets(&source, &preprocessed),
+        source: preprocessed,
+        program,
+        checker,
+    })
+}
+
+/// Reporta las advertencias del linter. Con `--deny-warnings` cualquier
+/// advertencia hace fallar la compilación y se devuelve `false`.
+fn lint(analysis: &Analysis, options: &Options) -> bool {
+    let warnings = Linter::lint(&analysis.program, options.warn_shadowing);
+    for warning in &warnings {
+        eprintln!("{}", warning.clone().with_line(&analysis.source));
+    }
+    if options.deny_warnings && !warnings.is_empty() {
+        eprintln!(
+            "{}",
+            Diagnostic::error(format!(
+                "La compilación falló por {} advertencia(s)",
+                warnings.len()
+            ))
+            .with_note("--deny-warnings trata las advertencias como errores")
+        );
+        return false;
+    }
+    true
+}
+
+/// Pliega constantes con `AstOptimizer`. Devuelve `None` si la optimización
+/// encontró algún error.
+fn optimize(analysis: Analysis) -> Option<Analysis> {
+    let mut optimizer = AstOptimizer::new();
+    let program = optimizer.optimize(analysis.program);
+    let mut failed = false;
+    for diagnostic in optimizer.diagnostics {
+        failed |= diagnostic.severity == Severity::Error;
+        eprintln!("{}", diagnostic.with_line(&analysis.source));
+    }
+    if failed {
+        return None;
+    }
+    // Las anotaciones del chequeo siguen valiendo: el optimizador conserva el
+    // `ExprId` de lo que pliega
+    Some(Analysis {
+        program,
+        ..analysis
+    })
+}
+
+fn write_output(path: &Path, contents: &str) -> Result<(), Diagnostic> {
+    if let Some(parent) = path.parent() {
+        if !parent.as_os_str().is_empty() {
+            fs::create_dir_all(parent).map_err(|e| {
+                Diagnostic::error(format!(
+                    "No se pudo crear el directorio '{}'",
+                    parent.display()
+                ))
+                .with_note(e.to_string())
+            })?;
+        }
+    }
+    fs::write(path, contents).map_err(|e| {
+        Diagnostic::error(format!("No se pudo escribir '{}'", path.display()))
+            .with_note(e.to_string())
+    })
+}
+
+/// Baja el programa chequeado al HIR, la entrada de los backends nativos.
+fn lower_hir(analysis: &Analysis) -> Result<hir::Program, Diagnostic> {
+    hir::lower(&analysis.program, &analysis.checker.expr_types)
+        .map_err(|diagnostic| diagnostic.with_line(&analysis.source))
+}
+
+/// Baja el HIR al MIR y lo verifica. Un MIR inválido es un error del
+/// compilador, no del programa.
+fn lower_mir(analysis: &Analysis) -> Result<mir::Module, Diagnostic> {
+    let module = mir::lower(&lower_hir(analysis)?);
+    mir::verify(&module).map_err(|errors| {
+        errors.into_iter().fold(
+            Diagnostic::error("Error interno: el MIR generado no es válido"),
+            Diagnostic::with_note,
+        )
+    })?;
+    Ok(module)
+}
+
+fn generate_ir(analysis: &Analysis, options: &Options) -> Result<String, Diagnostic> {
+    Ok(LLVMGenerator::generate(&lower_mir(analysis)?, options.gc_stats).to_ir_string())
+}
+
+/// Ruta temporal única para los archivos intermedios de este proceso.
+fn temp_path(name: &str) -> PathBuf {
+    env::temp_dir().join(format!("hulk-{}-{}", process::id(), name))
+}
+
+/// Compila el IR del programa a código nativo en `output`, pasando por un
+/// `.ll` temporal que se borra al terminar.
+fn compile_native(
+    analysis: &Analysis,
+    options: &Options,
+    kind: NativeOutput,
+    output: &Path,
+) -> Result<(), Diagnostic> {
+    let toolchain = Toolchain::detect()?;
+    if options.verbose {
+        println!("Usando {:?}", toolchain);
+    }
+    let ir_path = temp_path("program.ll");
+    write_output(&ir_path, &generate_ir(analysis, options)?)?;
+    if let Some(parent) = output.parent() {
+        if !parent.as_os_str().is_empty() {
+            let _ = fs::create_dir_all(parent);
+        }
+    }
+    let result = toolchain.compile(&ir_path, output, kind, options.opt_level);
+    let _ = fs::remove_file(&ir_path);
+    result
+}
+
+/// Genera el artefacto pedido en `options.emit`.
+fn emit(analysis: &Analysis, options: &Options) -> Result<(), Diagnostic> {
+    // `emit` sin -o escribe en stdout; `build` siempre escribe un archivo
+    let to_stdout = options.command == Command::Emit && options.output.is_none();
+
+    match options.emit {
+        EmitKind::Ast if options.ast_format == AstFormat::Text => {
+            if !to_stdout {
+                return Err(Diagnostic::error(
+                    "--emit=ast solo puede escribirse en la salida estándar",
+                ));
+            }
+            let mut printer = AstPrinterVisitor::new();
+            analysis.program.accept(&mut printer);
+            Ok(())
+        }
+        EmitKind::Ast => {
+            let mut tree =
+                AstSerializer::serialize(&analysis.program, Some(&analysis.checker.expr_types));
+            tree.remap_spans(&analysis.offsets);
+            let dump = match options.ast_format {
+                AstFormat::Json => tree.to_json(),
+                _ => tree.to_sexpr(),
+            };
+            if to_stdout {
+                print!("{}", dump);
+                Ok(())
+            } else {
+                write_output(&options.output_path(), &dump)
+            }
+        }
+        EmitKind::Bytecode => {
+            let listing =
+                BytecodeCompiler::compile(&analysis.program, analysis.checker.expr_types.clone())
+                    .disassemble();
+            if to_stdout {
+                print!("{}", listing);
+                Ok(())
+            } else {
+                write_output(&options.output_path(), &listing)
+            }
+        }
+        EmitKind::Hir => {
+            let dump = lower_hir(analysis)?.to_string();
+            if to_stdout {
+                print!("{}", dump);
+                Ok(())
+            } else {
+                write_output(&options.output_path(), &dump)
+            }
+        }
+        EmitKind::Mir => {
+            let dump = lower_mir(analysis)?.to_string();
+            if to_stdout {
+                print!("{}", dump);
+                Ok(())
+            } else {
+                write_output(&options.output_path(), &dump)
+            }
+        }
+        EmitKind::Ir => {
+            let ir = generate_ir(analysis, options)?;
+            if to_stdout {
+                print!("{}", ir);
+                Ok(())
+            } else {
+                let path = options.output_path();
+                write_output(&path, &ir)?;
+                if options.verbose {
+                    println!("LLVM IR escrito en {}", path.display());
+                }
+                Ok(())
+            }
+        }
+        EmitKind::Asm if to_stdout => {
+            let asm_path = temp_path("program.s");
+            compile_native(analysis, options, NativeOutput::Asm, &asm_path)?;
+            let asm = fs::read_to_string(&asm_path);
+            let _ = fs::remove_file(&asm_path);
+            print!(
+                "{}",
+                asm.map_err(
+                    |e| Diagnostic::error("No se pudo leer el ensamblador generado")
+                        .with_note(e.to_string())
+                )?
+            );
+            Ok(())
+        }
+        EmitKind::Obj | EmitKind::Exe if to_stdout => Err(Diagnostic::error(format!(
+            "--emit={} es binario y no puede escribirse en la salida estándar",
+            if options.emit == EmitKind::Obj {
+                "obj"
+            } else {
+                "exe"
+            }
+        ))
+        .with_note("indique el archivo de salida con -o")),
+        EmitKind::Asm | EmitKind::Obj | EmitKind::Exe => {
+            let kind = match options.emit {
+                EmitKind::Asm => NativeOutput::Asm,
+                EmitKind::Obj => NativeOutput::Obj,
+                _ => NativeOutput::Exe,
+            };
+            let path = options.output_path();
+            compile_native(analysis, options, kind, &path)?;
+            if options.verbose {
+                println!("Salida escrita en {}", path.display());
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Compila el programa a un ejecutable temporal, lo ejecuta con los
+/// argumentos de `--` y devuelve su código de salida.
+fn run_program(analysis: &Analysis, options: &Options) -> Result<i32, Diagnostic> {
+    let exe_path = temp_path("program").with_extension(EmitKind::Exe.extension());
+    compile_native(analysis, options, NativeOutput::Exe, &exe_path)?;
+    let status = process::Command::new(&exe_path)
+        .args(&options.program_args)
+        .status();
+    let _ = fs::remove_file(&exe_path);
+    match status {
+        Ok(status) => Ok(status.code().unwrap_or(1)),
+        Err(err) => Err(
+            Diagnostic::error("No se pudo ejecutar el programa compilado")
+                .with_note(err.to_string()),
+        ),
+    }
+}
+
+pub fn runtime_diagnostic(err: &RuntimeError, source: &str) -> Diagnostic {
+    Diagnostic::error(err.message.clone()).with_note(format!(
+        "en tiempo de ejecución, línea {}",
+        err.position.start_line(source)
+    ))
+}
+
+/// Informa un error de ejecución y devuelve el código de salida, el mismo
+/// que daría el ejecutable nativo.
+fn runtime_failure(err: &RuntimeError, source: &str) -> i32 {
+    eprintln!("{}", runtime_diagnostic(err, source));
+    err.exit_code()
+}
+
+/// Evalúa el programa con el intérprete y devuelve el código de salida.
+fn interpret_program(analysis: &Analysis) -> Result<i32, Diagnostic> {
+    let mut interpreter = Interpreter::new(analysis.checker.expr_types.clone());
+    match interpreter.run(&analysis.program) {
+        Ok(_) => Ok(0),
+        Err(err) => Ok(runtime_failure(&err, &analysis.source)),
+    }
+}
+
+/// Compila el programa a bytecode, lo ejecuta en la máquina virtual y
+/// devuelve el código de salida.
+fn run_vm(analysis: &Analysis, options: &Options) -> Result<i32, Diagnostic> {
+    let compiled =
+        BytecodeCompiler::compile(&analysis.program, analysis.checker.expr_types.clone());
+    if options.verbose {
+        print!("{}", compiled.disassemble());
+    }
+    let mut vm = Vm::new(compiled.globals);
+    match vm.run(compiled.script) {
+        Ok(_) => Ok(0),
+        Err(err) => Ok(runtime_failure(&err, &analysis.source)),
+    }
+}
+
+/// Código canónico de `source`, con sus comentarios.
+pub fn format_source(source: &str) -> Result<String, Diagnostic> {
+    let (stripped, mut comments) = comments::strip_comments(source).map_err(Diagnostic::error)?;
+    let preprocessed = ast_optimizer::preprocess_functions(&stripped);
+    // Las posiciones del AST cuentan las `@` del preprocesado; las de los
+    // comentarios se llevan a esas mismas coordenadas
+    let offsets = comments::map_offsets(&stripped, &preprocessed);
+    for comment in &mut comments {
+        comment.position.start = offsets[comment.position.start];
+        comment.position.end = offsets[comment.position.end];
+    }
+    let program = parser::parse_program(&preprocessed).map_err(|err| match err.line {
+        Some(line) => Diagnostic::error(format!("Error en la línea {}: {}", line, err.message)),
+        None => Diagnostic::error(err.message),
+    })?;
+    Ok(CodeFormatter::format(&program, comments))
+}
+
+/// `hulk fmt`: reescribe el script en forma canónica o, con `--check`,
+/// solo informa si ya lo está (código 1 si no).
+fn format_file(source: &str, options: &Options) -> Result<i32, Diagnostic> {
+    let formatted = format_source(source)?;
+    if options.check {
+        if formatted == source {
+            return Ok(0);
+        }
+        eprintln!(
+            "El archivo '{}' no tiene el formato canónico",
+            options.input.display()
+        );
+        return Ok(1);
+    }
+    let path = options
+        .output
+        .clone()
+        .unwrap_or_else(|| options.input.clone());
+    if options.output.is_some() || formatted != source {
+        write_output(&path, &formatted)?;
+    }
+    Ok(0)
+}
+
+/// Ejecuta el comando pedido y devuelve el código de salida del proceso.
+pub fn run(options: &Options) -> i32 {
+    if options.command == Command::Repl {
+        return repl::run();
+    }
+
+    let source = match fs::read_to_string(&options.input) {
+        Ok(source) => source,
+        Err(err) => {
+            eprintln!(
+                "No se pudo leer el archivo '{}': {}",
+                options.input.display(),
+                err
+            );
+            return 1;
+        }
+    };
+
+    if options.command == Command::Fmt {
+        return match format_file(&source, options) {
+            Ok(code) => code,
+            Err(diagnostic) => {
+                eprintln!("{}", diagnostic);
+                1
+            }
+        };
+    }
+
+    let analysis = match analyze(&source, options.verbose) {
+        Some(analysis) => analysis,
+        None => return 1,
+    };
+    if !lint(&analysis, options) {
+        return 1;
+    }
+    // El AST se emite tal como se escribió; el resto trabaja con el optimizado
+    let emits_ast = options.command != Command::Run && options.emit == EmitKind::Ast;
+    let analysis = if emits_ast {
+        analysis
+    } else {
+        match optimize(analysis) {
+            Some(analysis) => analysis,
+            None => return 1,
+        }
+    };
+
+    let result = match options.command {
+        Command::Check => Ok(0),
+        Command::Build | Command::Emit => emit(&analysis, options).map(|()| 0),
+        Command::Run => match options.backend {
+            Backend::Native => run_program(&analysis, options),
+            Backend::Interpreter => interpret_program(&analysis),
+            Backend::Vm => run_vm(&analysis, options),
+        },
+        Command::Repl | Command::Fmt => unreachable!("atendido antes del análisis"),
+    };
+
+    match result {
+        Ok(code) => code,
+        Err(diagnostic) => {
+            eprintln!("{}", diagnostic);
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parser::{Atom, Expression, Literal};
+    use std::cell::RefCell;
+    use std::io::{self, Write};
+    use std::rc::Rc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    const LOOPS: &str = "
+        let i = 0, total = 0 in {
+            while (i < 5) {
+                total := total + i * i;
+                i := i + 1;
+            }
+            print(total);
+            print(for (k in range(1, 4)) k * 10);
+            for (k in range(0, 10)) {
+                k := k + 2;
+                print(k);
+            }
+        };
+    ";
+
+    const CLOSURES: &str = "
+        function adder:(number)->number(n:number) { (x: number) => x + n };
+        function twice:number(f:(number)->number, x:number) => f(f(x));;
+        let add3 = adder(3), base = 10 in {
+            print(add3(4));
+            print(twice(add3, base));
+            let scale = (x: number) => x * base in print(twice(scale, 2));;
+            function fact:number(n:number) => if (n <= 1) 1 else n * fact(n - 1);;
+            print(fact(6));
+        };
+    ";
+
+    const DIVISION_BY_ZERO: &str = "let z = 0 in { print(1); print(5 / z); };";
+
+    const STRING_ORDER: &str = r#"
+        let a = "abc" in { print(a > "abb"); print(a <= "abd"); print(a < "ab"); print(a >= "abc"); };
+    "#;
+
+    /// Salida que se comparte con el backend para leerla al terminar.
+    #[derive(Clone, Default)]
+    struct Capture(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for Capture {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Capture {
+        fn text(&self) -> String {
+            String::from_utf8(self.0.borrow().clone()).unwrap()
+        }
+    }
+
+    fn analysis(source: &str) -> Analysis {
+        optimize(analyze(source, false).expect("el programa es válido"))
+            .expect("el programa se optimiza")
+    }
+
+    /// Programa parseado y chequeado aunque el chequeo encuentre errores,
+    /// para ver qué hacen los backends con lo que `hulk check` rechaza.
+    fn unchecked(source: &str) -> Analysis {
+        let source = ast_optimizer::preprocess_functions(source);
+        let program = parser::parse_program(&source).expect("el programa se parsea");
+        let mut checker = SemanticTypeChecker::new();
+        checker.check(&program);
+        Analysis {
+            offsets: Vec::new(),
+            source,
+            program,
+            checker,
+        }
+    }
+
+    /// Lo que imprime el intérprete y su código de salida.
+    fn interpret(source: &str) -> (String, i32) {
+        interpreter_output(&analysis(source))
+    }
+
+    fn interpreter_output(analysis: &Analysis) -> (String, i32) {
+        let output = Capture::default();
+        let mut interpreter = Interpreter::with_output(
+            analysis.checker.expr_types.clone(),
+            Box::new(output.clone()),
+        );
+        let code = match interpreter.run(&analysis.program) {
+            Ok(_) => 0,
+            Err(err) => err.exit_code(),
+        };
+        (output.text(), code)
+    }
+
+    /// Lo que imprime la máquina virtual y su código de salida.
+    fn run_bytecode(source: &str) -> (String, i32) {
+        vm_output(&analysis(source))
+    }
+
+    fn vm_output(analysis: &Analysis) -> (String, i32) {
+        let compiled =
+            BytecodeCompiler::compile(&analysis.program, analysis.checker.expr_types.clone());
+        let output = Capture::default();
+        let mut vm = Vm::with_output(compiled.globals, Box::new(output.clone()));
+        let code = match vm.run(compiled.script) {
+            Ok(_) => 0,
+            Err(err) => err.exit_code(),
+        };
+        (output.text(), code)
+    }
+
+    /// Lo que imprime el ejecutable nativo y su código de salida, o `None`
+    /// si no hay toolchain para compilarlo.
+    fn run_native(source: &str) -> Option<(String, i32)> {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        Toolchain::detect().ok()?;
+        let analysis = analysis(source);
+        let args = ["build".to_string(), "test.hulk".to_string()];
+        let options = crate::cli::parse_args(&args).unwrap().unwrap();
+        // Los tests corren en paralelo: cada uno necesita su ejecutable
+        let exe = temp_path(&format!("test-{}", NEXT.fetch_add(1, Ordering::Relaxed)))
+            .with_extension(EmitKind::Exe.extension());
+        compile_native(&analysis, &options, NativeOutput::Exe, &exe).expect("compila");
+        let output = process::Command::new(&exe).output();
+        let _ = fs::remove_file(&exe);
+        let output = output.expect("el ejecutable corre");
+        Some((
+            String::from_utf8(output.stdout).unwrap(),
+            output.status.code().unwrap_or(1),
+        ))
+    }
+
+    /// Salida del programa, después de comprobar que los tres backends
+    /// coinciden en ella.
+    fn run_everywhere(source: &str) -> (String, i32) {
+        let interpreted = interpret(source);
+        assert_eq!(run_bytecode(source), interpreted, "VM: {}", source);
+        if let Some(native) = run_native(source) {
+            assert_eq!(native, interpreted, "nativo: {}", source);
+        }
+        interpreted
+    }
+
+    /// Errores que reporta `hulk check`. Con alguno, `analyze` no deja
+    /// llegar el programa a ningún backend.
+    fn check_errors(source: &str) -> Vec<String> {
+        let errors = unchecked(source).checker.errors;
+        assert_eq!(analyze(source, false).is_none(), !errors.is_empty());
+        errors
+    }
+
+    #[test]
+    fn interprets_the_sample_script() {
+        assert_eq!(interpret(include_str!("../script.hulk")), ("3\n".into(), 0));
+    }
+
+    #[test]
+    fn interprets_loops() {
+        assert_eq!(interpret(LOOPS), ("30\n30\n2\n5\n8\n11\n".into(), 0));
+    }
+
+    #[test]
+    fn interprets_closures() {
+        assert_eq!(interpret(CLOSURES), ("7\n16\n200\n720\n".into(), 0));
+    }
+
+    #[test]
+    fn division_by_zero_exits_like_the_native_runtime() {
+        // 3 es el código de `DivisionByZero` en el runtime
+        assert_eq!(interpret(DIVISION_BY_ZERO), ("1\n".into(), 3));
+    }
+
+    #[test]
+    fn orders_strings_like_the_native_runtime() {
+        let expected = ("true\ntrue\nfalse\ntrue\n".to_string(), 0);
+        assert_eq!(interpret(STRING_ORDER), expected);
+        if let Some(native) = run_native(STRING_ORDER) {
+            assert_eq!(native, expected);
+        }
+    }
+
+    #[test]
+    fn vm_matches_the_interpreter() {
+        let fib = "
+            function fib:number(n:number) { if (n < 2) n else fib(n - 1) + fib(n - 2) };
+            print(fib(20));
+        ";
+        for source in [
+            include_str!("../script.hulk"),
+            fib,
+            LOOPS,
+            CLOSURES,
+            DIVISION_BY_ZERO,
+            STRING_ORDER,
+        ] {
+            assert_eq!(run_bytecode(source), interpret(source), "{}", source);
+        }
+
+        // El chequeo rechaza este for; sin él, ambos fallan al llegar al bucle
+        let rejected = unchecked("print(1); for (i in 5) print(i);");
+        assert_eq!(vm_output(&rejected), ("1\n".into(), 1));
+        assert_eq!(interpreter_output(&rejected), ("1\n".into(), 1));
+    }
+
+    #[test]
+    fn using_the_value_of_a_mixed_if_is_an_error() {
+        for source in [
+            r#"let c = 1 in { print(if (c < 2) 1 else "a"); };"#,
+            r#"let x = if (true) 1 else "a" in { print(x); };"#,
+        ] {
+            let errors = check_errors(source);
+            assert_eq!(errors.len(), 1, "{:?}", errors);
+            assert!(errors[0].contains("tipos distintos"), "{}", errors[0]);
+        }
+
+        let discarded =
+            r#"let c = 1 in { if (c < 2) { print(1); } else { print("a"); } print(c); };"#;
+        assert!(check_errors(discarded).is_empty());
+        assert_eq!(run_everywhere(discarded), ("1\n1\n".into(), 0));
+    }
+
+    #[test]
+    fn a_for_only_iterates_ranges() {
+        for source in [
+            "for (i in 5) print(i);",
+            "print(range(1, 3));",
+            r#"for (i in range(0, "a")) print(i);"#,
+        ] {
+            assert_eq!(check_errors(source).len(), 1, "{}", source);
+        }
+        assert_eq!(
+            run_everywhere("for (i in range(0, 3)) print(i);"),
+            ("0\n1\n2\n".into(), 0)
+        );
+    }
+
+    #[test]
+    fn an_assignment_keeps_the_type_of_the_variable() {
+        let errors = check_errors(r#"let x = 1 in { x := "hola"; print(x); };"#);
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert!(errors[0].contains("'String'"), "{}", errors[0]);
+
+        assert_eq!(
+            run_everywhere("let x = 1 in { x := x + 2; print(x); };"),
+            ("3\n".into(), 0)
+        );
+    }
+
+    #[test]
+    fn a_lambda_needs_a_body_with_a_known_type() {
+        for source in [
+            "let f = (x: number) => range(0, x) in { print(1); };",
+            "let f = (x: number) => { function g:number(y:number) => y;; } in { print(1); };",
+        ] {
+            assert_eq!(check_errors(source).len(), 1, "{}", source);
+        }
+
+        assert_eq!(
+            run_everywhere("let f = (x: number) => x * 2 in { print(f(4)); };"),
+            ("8\n".into(), 0)
+        );
+    }
+
+    #[test]
+    fn folds_string_comparisons() {
+        let source = r#"print("hola" == "hola"); print("hola" != "hola"); print("a" == "b");"#;
+        let folded: Vec<bool> = analysis(source)
+            .program
+            .expression_list
+            .expressions
+            .iter()
+            .map(|expr| match expr {
+                Expression::Print(value, _) => match &**value {
+                    Expression::Atom(atom) => match &**atom {
+                        Atom::BooleanLiteral(Literal::Bool(b, _)) => *b,
+                        other => panic!("no se plegó: {:?}", other),
+                    },
+                    other => panic!("no se plegó: {:?}", other),
+                },
+                other => panic!("se esperaba un print: {:?}", other),
+            })
+            .collect();
+        assert_eq!(folded, [true, false, false]);
+        assert_eq!(run_everywhere(source), ("true\nfalse\nfalse\n".into(), 0));
+    }
+
+    #[test]
+    fn formatting_twice_changes_nothing() {
+        let formatted = format_source(include_str!("../fixtures/comments.hulk")).unwrap();
+        assert_eq!(format_source(&formatted).unwrap(), formatted);
+        // Cada comentario sigue junto al código donde estaba
+        for line in [
+            "}; // fin fib\n\n/* bloque\n",
+            "    print(fib(x /* en medio */ + 1));\n",
+            "    if (x < 2)\n        print(1); // uno\n    elif (x < 5)\n",
+            "    print(fib(x /* al final */));\n",
+            "    print((x /* agrupado */));\n",
+            "    print(x); // final\n",
+        ] {
+            assert!(
+                formatted.contains(line),
+                "{:?} no está en\n{}",
+                line,
+                formatted
+            );
+        }
+    }
+}
@@ -0,0 +1,158 @@
+use crate::index::SymbolIndex;
+use lsp_types::{Diagnostic, DiagnosticSeverity, Position, Range};
+use parser::comments;
+use parser::tokens;
+use parser::visitor::ast_optimizer;
+use parser::visitor::semantic_type_checker::SemanticTypeChecker;
+use parser::visitor::{AstNode, AstOptimizer, AstSerializer, Linter, SymbolTable};
+use parser::{Program, Severity};
+
+/// Resultado de analizar un documento que llegó a parsear. Las posiciones de
+/// `tree` e `index` ya están traducidas al texto del editor.
+pub struct Analysis {
+    /// Tabla del checker al terminar: las funciones y los nombres globales
+    pub symbols: SymbolTable,
+    pub tree: AstNode,
+    pub index: SymbolIndex,
+}
+
+/// Documento abierto en el editor, con su último análisis.
+pub struct Document {
+    pub text: String,
+    /// Posición del primer byte de cada línea
+    line_starts: Vec<usize>,
+    pub analysis: Option<Analysis>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Document {
+    pub fn new(text: String) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        let mut document = Document {
+            text,
+            line_starts,
+            analysis: None,
+            diagnostics: Vec::new(),
+        };
+        document.analyze();
+        document
+    }
+
+    /// Corre el mismo análisis que `hulk check` y guarda sus errores como
+    /// diagnósticos del editor.
+    fn analyze(&mut self) {
+        let stripped = match comments::strip_comments(&self.text) {
+            Ok((stripped, _)) => stripped,
+            Err(message) => {
+                self.error(message, tokens::Position::new(0, 0));
+                return;
+            }
+        };
+        let preprocessed = ast_optimizer::preprocess_functions(&stripped);
+        let offsets = comments::unmap_offsets(&stripped, &preprocessed);
+        let original = |position: tokens::Position| {
+            tokens::Position::new(offsets[position.start], offsets[position.end])
+        };
+
+        let program = match parser::parse_program(&preprocessed) {
+            Ok(program) => program,
+            Err(err) => {
+                // Sin posición, el error se marca al principio de su línea
+                let position = match (err.position, err.line) {
+                    (Some(position), _) => original(position),
+                    (None, Some(line)) => {
+                        let start = self.line_starts.get(line - 1).copied().unwrap_or(0);
+                        tokens::Position::new(start, start)
+                    }
+                    (None, None) => tokens::Position::new(0, 0),
+                };
+                self.error(err.message, position);
+                return;
+            }
+        };
+
+        let mut checker = SemanticTypeChecker::new();
+        checker.check(&program);
+        for diagnostic in checker.diagnostics() {
+            let mut message = diagnostic.message;
+            // El editor no muestra notas: la declaración anterior va en el mensaje
+            for (note, position) in diagnostic.related {
+                let line = self.position(original(position).start).line + 1;
+                message.push_str(&format!("\n{} (línea {})", note, line));
+            }
+            let position = diagnostic.position.unwrap_or(tokens::Position::new(0, 0));
+            self.error(message, original(position));
+        }
+        // Los errores que detecta el optimizador, como la división por cero
+        let mut optimizer = AstOptimizer::new();
+        optimizer.optimize(Program::new(program.expression_list.clone()));
+        for diagnostic in optimizer.diagnostics {
+            let position = diagnostic.position.unwrap_or(tokens::Position::new(0, 0));
+            self.push(diagnostic.severity, diagnostic.message, original(position));
+        }
+        for warning in Linter::lint(&program, false) {
+            let position = warning.position.unwrap_or(tokens::Position::new(0, 0));
+            self.push(warning.severity, warning.message, original(position));
+        }
+
+        let mut tree = AstSerializer::serialize(&program, Some(&checker.expr_types));
+        tree.remap_spans(&offsets);
+        let index = SymbolIndex::build(&program, &checker.expr_types, &offsets);
+        self.analysis = Some(Analysis {
+            symbols: checker.symbol_table,
+            tree,
+            index,
+        });
+    }
+
+    fn error(&mut self, message: String, position: tokens::Position) {
+        self.push(Severity::Error, message, position);
+    }
+
+    fn push(&mut self, severity: Severity, message: String, position: tokens::Position) {
+        let severity = match severity {
+            Severity::Error => DiagnosticSeverity::ERROR,
+            Severity::Warning => DiagnosticSeverity::WARNING,
+        };
+        self.diagnostics.push(Diagnostic {
+            range: self.range(position),
+            severity: Some(severity),
+            source: Some("hulk".to_string()),
+            message,
+            ..Diagnostic::default()
+        });
+    }
+
+    /// Posición LSP (línea y columna en unidades UTF-16) de un byte del texto.
+    pub fn position(&self, offset: usize) -> Position {
+        let offset = offset.min(self.text.len());
+        let line = self.line_starts.partition_point(|&start| start <= offset) - 1;
+        let start = self.line_starts[line];
+        let character = self.text[start..offset]
+            .chars()
+            .map(char::len_utf16)
+            .sum::<usize>();
+        Position::new(line as u32, character as u32)
+    }
+
+    /// Byte del texto que corresponde a una posición LSP.
+    pub fn offset(&self, position: Position) -> usize {
+        let Some(&start) = self.line_starts.get(position.line as usize) else {
+            return self.text.len();
+        };
+        let mut units = 0;
+        for (i, c) in self.text[start..].char_indices() {
+            if units >= position.character as usize || c == '\n' {
+                return start + i;
+            }
+            units += c.len_utf16();
+        }
+        self.text.len()
+    }
+
+    pub fn range(&self, position: tokens::Position) -> Range {
+        Range::new(self.position(position.start), self.position(position.end))
+    }
+}
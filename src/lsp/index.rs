@@ -0,0 +1,317 @@
+use parser::functiondeclaration::{FunctionParams, Lambda};
+use parser::tokens::{Identifier, Position};
+use parser::visitor::semantic_type_checker::TypeAnnotations;
+use parser::visitor::symbol_table::SymbolInfo;
+use parser::visitor::visitor::walk_expression;
+use parser::visitor::{SymbolTable, Type, Visitor};
+use parser::{Assignment, Atom, Block, Expression, For, FunctionDef, LetIn, Program, Visitable};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DefinitionKind {
+    Function,
+    Parameter,
+    Variable,
+}
+
+/// Declaración de un nombre en el programa.
+#[derive(Debug, Clone)]
+pub struct Definition {
+    pub name: String,
+    pub kind: DefinitionKind,
+    /// Posición del nombre en la declaración
+    pub span: Position,
+    /// Toda la declaración; para las funciones incluye el cuerpo
+    pub full_span: Position,
+    pub info: SymbolInfo,
+}
+
+impl Definition {
+    /// Firma para mostrar en hover y completado.
+    pub fn signature(&self) -> String {
+        match &self.info {
+            SymbolInfo::Function {
+                return_type,
+                param_types,
+            } => {
+                let params = param_types
+                    .iter()
+                    .map(Type::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                format!("function {}({}): {}", self.name, params, return_type)
+            }
+            SymbolInfo::Variable { var_type } => match self.kind {
+                DefinitionKind::Parameter => format!("(parámetro) {}: {}", self.name, var_type),
+                _ => format!("{}: {}", self.name, var_type),
+            },
+        }
+    }
+}
+
+/// Uso de un nombre, con la declaración a la que se refiere si existe.
+#[derive(Debug, Clone)]
+pub struct Reference {
+    pub span: Position,
+    pub definition: Option<usize>,
+}
+
+/// Nombres visibles dentro de un fragmento del programa.
+#[derive(Debug, Clone)]
+pub struct Scope {
+    pub span: Position,
+    pub symbols: Vec<(String, SymbolInfo)>,
+}
+
+/// Declaraciones, usos y scopes de un programa chequeado, con las
+/// posiciones ya llevadas al texto original.
+#[derive(Debug, Default)]
+pub struct SymbolIndex {
+    pub definitions: Vec<Definition>,
+    pub references: Vec<Reference>,
+    pub scopes: Vec<Scope>,
+}
+
+impl SymbolIndex {
+    /// `offsets` lleva las posiciones del texto preprocesado al original.
+    pub fn build(program: &Program, expr_types: &TypeAnnotations, offsets: &[usize]) -> Self {
+        let mut builder = IndexBuilder {
+            index: SymbolIndex::default(),
+            table: SymbolTable::new(),
+            declared: vec![HashMap::new()],
+            expr_types,
+            offsets,
+        };
+        program.accept(&mut builder);
+        builder.index
+    }
+
+    /// Uso (o declaración) del nombre que contiene `offset`.
+    pub fn reference_at(&self, offset: usize) -> Option<&Reference> {
+        self.references
+            .iter()
+            .find(|r| r.span.start <= offset && offset <= r.span.end)
+    }
+
+    /// Nombres visibles en `offset` según el scope más interno que lo
+    /// contiene, o `None` si está fuera de todos.
+    pub fn scope_at(&self, offset: usize) -> Option<&Scope> {
+        self.scopes
+            .iter()
+            .filter(|s| s.span.start <= offset && offset <= s.span.end)
+            .min_by_key(|s| s.span.end - s.span.start)
+    }
+}
+
+/// Recorre el programa con las mismas reglas de alcance que
+/// `SemanticTypeChecker` y va llenando el índice.
+struct IndexBuilder<'a> {
+    index: SymbolIndex,
+    table: SymbolTable,
+    /// Declaración vigente de cada nombre, un mapa por scope abierto
+    declared: Vec<HashMap<String, usize>>,
+    expr_types: &'a TypeAnnotations,
+    offsets: &'a [usize],
+}
+
+impl IndexBuilder<'_> {
+    fn original(&self, position: Position) -> Position {
+        let translate = |offset: usize| self.offsets.get(offset).copied().unwrap_or(offset);
+        Position::new(translate(position.start), translate(position.end))
+    }
+
+    fn enter_scope(&mut self) {
+        self.table.enter_scope();
+        self.declared.push(HashMap::new());
+    }
+
+    fn exit_scope(&mut self) {
+        self.table.exit_scope();
+        self.declared.pop();
+    }
+
+    /// Guarda los nombres visibles ahora como los del fragmento `span`.
+    fn snapshot(&mut self, span: Position) {
+        let symbols = self
+            .table
+            .visible()
+            .into_iter()
+            .map(|(name, info)| (name.clone(), info.clone()))
+            .collect();
+        let span = self.original(span);
+        self.index.scopes.push(Scope { span, symbols });
+    }
+
+    fn declare(
+        &mut self,
+        name: &Identifier,
+        kind: DefinitionKind,
+        full_span: Position,
+        info: SymbolInfo,
+    ) {
+        let id = self.index.definitions.len();
+        let span = self.original(name.position);
+        self.index.definitions.push(Definition {
+            name: name.name.clone(),
+            kind,
+            span,
+            full_span: self.original(full_span),
+            info: info.clone(),
+        });
+        self.index.references.push(Reference {
+            span,
+            definition: Some(id),
+        });
+        self.table.insert(name.name.clone(), info);
+        self.declared
+            .last_mut()
+            .unwrap()
+            .insert(name.name.clone(), id);
+    }
+
+    fn lookup(&self, name: &str) -> Option<usize> {
+        self.declared
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name).copied())
+    }
+
+    fn reference(&mut self, name: &Identifier) {
+        let definition = self.lookup(&name.name);
+        let span = self.original(name.position);
+        self.index.references.push(Reference { span, definition });
+    }
+
+    fn type_of(&self, expr: &Expression) -> Type {
+        self.expr_types
+            .get(&expr.id())
+            .cloned()
+            .unwrap_or(Type::Unknown)
+    }
+
+    /// Los parámetros se ven solo dentro del cuerpo.
+    fn function_body(&mut self, params: &[FunctionParams], body: &Expression) {
+        self.enter_scope();
+        for param in params {
+            self.declare(
+                &param.name,
+                DefinitionKind::Parameter,
+                param.name.position,
+                SymbolInfo::Variable {
+                    var_type: param.signature.clone(),
+                },
+            );
+        }
+        self.snapshot(body.position());
+        self.visit_expression(body);
+        self.exit_scope();
+    }
+
+    fn function_info(def: &FunctionDef) -> SymbolInfo {
+        SymbolInfo::Function {
+            return_type: def.return_type.clone(),
+            param_types: def.params.iter().map(|p| p.signature.clone()).collect(),
+        }
+    }
+}
+
+impl Visitor for IndexBuilder<'_> {
+    fn visit_program(&mut self, program: &Program) {
+        // Como en el intérprete, las funciones se ven desde todo el programa
+        for expr in &program.expression_list.expressions {
+            if let Expression::FunctionDef(def) = expr {
+                let full_span = expr.position();
+                self.declare(
+                    &def.name,
+                    DefinitionKind::Function,
+                    full_span,
+                    Self::function_info(def),
+                );
+            }
+        }
+        self.visit_expression_list(&program.expression_list);
+    }
+
+    fn visit_expression(&mut self, expr: &Expression) {
+        match expr {
+            Expression::FunctionDef(def) if self.lookup(&def.name.name).is_none() => {
+                // Función declarada dentro de otra expresión
+                self.declare(
+                    &def.name,
+                    DefinitionKind::Function,
+                    expr.position(),
+                    Self::function_info(def),
+                );
+                self.visit_function_def(def);
+            }
+            _ => walk_expression(self, expr),
+        }
+    }
+
+    fn visit_letin(&mut self, letin: &LetIn) {
+        self.enter_scope();
+        for assign in &letin.bindings {
+            self.visit_assignment(assign);
+        }
+        self.snapshot(letin.body.position());
+        self.visit_expression(&letin.body);
+        self.exit_scope();
+    }
+
+    fn visit_assignment(&mut self, assign: &Assignment) {
+        // El valor se resuelve antes de declarar la variable, como en el checker
+        self.visit_expression(&assign.body);
+        if let Atom::Variable(identifier) = &assign.variable {
+            let full_span = Position::new(identifier.position.start, assign.body.position().end);
+            let var_type = self.type_of(&assign.body);
+            self.declare(
+                identifier,
+                DefinitionKind::Variable,
+                full_span,
+                SymbolInfo::Variable { var_type },
+            );
+        }
+    }
+
+    fn visit_block(&mut self, block: &Block) {
+        self.enter_scope();
+        self.snapshot(Position::new(
+            block.open_brace.position().start,
+            block.close_brace.position().end,
+        ));
+        self.visit_expression_list(&block.expression_list);
+        self.exit_scope();
+    }
+
+    fn visit_identifier(&mut self, identifier: &Identifier) {
+        self.reference(identifier);
+    }
+
+    fn visit_for(&mut self, forr: &For) {
+        self.visit_expression(&forr.iterable);
+        self.enter_scope();
+        if let Expression::Atom(atom) = &*forr.var {
+            if let Atom::Variable(identifier) = &**atom {
+                self.declare(
+                    identifier,
+                    DefinitionKind::Variable,
+                    identifier.position,
+                    SymbolInfo::Variable {
+                        var_type: Type::Number,
+                    },
+                );
+            }
+        }
+        self.snapshot(forr.body.position());
+        self.visit_expression(&forr.body);
+        self.exit_scope();
+    }
+
+    fn visit_function_def(&mut self, def: &FunctionDef) {
+        self.function_body(&def.params, &def.body);
+    }
+
+    fn visit_lambda(&mut self, lambda: &Lambda) {
+        self.function_body(&lambda.params, &lambda.body);
+    }
+}
@@ -0,0 +1,30 @@
+mod document;
+mod index;
+mod server;
+
+use lsp_server::Connection;
+use std::thread;
+
+/// Pila del hilo del servidor: el análisis recorre el AST recursivamente.
+const STACK_SIZE: usize = 512 * 1024 * 1024;
+
+/// Servidor de lenguaje de HULK. Habla LSP por la entrada y la salida
+/// estándar; los editores lo lanzan como un proceso hijo.
+fn main() {
+    let (connection, io_threads) = Connection::stdio();
+    let result = thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(move || server::run(&connection))
+        .map_err(|e| e.to_string())
+        .and_then(|handle| {
+            handle
+                .join()
+                .map_err(|_| "el servidor terminó de forma inesperada".to_string())?
+        });
+    if let Err(message) = result {
+        eprintln!("Error: {}", message);
+        std::process::exit(1);
+    }
+    // Los hilos de E/S terminan cuando el cliente cierra la conexión
+    let _ = io_threads.join();
+}
@@ -0,0 +1,299 @@
+use crate::document::Document;
+use crate::index::DefinitionKind;
+use lsp_server::{Connection, Message, Notification, Request, Response};
+use lsp_types::notification::{
+    DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument,
+    Notification as NotificationTrait, PublishDiagnostics,
+};
+use lsp_types::request::{
+    Completion, DocumentSymbolRequest, GotoDefinition, HoverRequest, Request as RequestTrait,
+};
+use lsp_types::{
+    CompletionItem, CompletionItemKind, CompletionParams, CompletionResponse, DocumentSymbol,
+    DocumentSymbolParams, DocumentSymbolResponse, GotoDefinitionParams, GotoDefinitionResponse,
+    Hover, HoverContents, HoverParams, HoverProviderCapability, Location, MarkupContent,
+    MarkupKind, OneOf, PublishDiagnosticsParams, ServerCapabilities, SymbolKind,
+    TextDocumentSyncCapability, TextDocumentSyncKind, Url,
+};
+use parser::visitor::symbol_table::SymbolInfo;
+use parser::visitor::{AstChild, AstNode};
+use std::collections::HashMap;
+use std::error::Error;
+
+type Result<T> = std::result::Result<T, Box<dyn Error + Send + Sync>>;
+
+/// Estado del servidor: los documentos abiertos, indexados por URI.
+struct Server<'a> {
+    connection: &'a Connection,
+    documents: HashMap<Url, Document>,
+}
+
+fn capabilities() -> ServerCapabilities {
+    ServerCapabilities {
+        // El cliente manda el texto completo en cada cambio
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        definition_provider: Some(OneOf::Left(true)),
+        document_symbol_provider: Some(OneOf::Left(true)),
+        completion_provider: Some(Default::default()),
+        ..ServerCapabilities::default()
+    }
+}
+
+/// Atiende la conexión hasta que el cliente pide cerrarla.
+pub fn run(connection: &Connection) -> std::result::Result<(), String> {
+    let capabilities = serde_json::to_value(capabilities()).map_err(|e| e.to_string())?;
+    connection
+        .initialize(capabilities)
+        .map_err(|e| e.to_string())?;
+    let mut server = Server {
+        connection,
+        documents: HashMap::new(),
+    };
+    server.main_loop().map_err(|e| e.to_string())
+}
+
+impl Server<'_> {
+    fn main_loop(&mut self) -> Result<()> {
+        for message in &self.connection.receiver {
+            match message {
+                Message::Request(request) => {
+                    if self.connection.handle_shutdown(&request)? {
+                        return Ok(());
+                    }
+                    self.handle_request(request)?;
+                }
+                Message::Notification(notification) => self.handle_notification(notification)?,
+                Message::Response(_) => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn respond(&self, response: Response) -> Result<()> {
+        self.connection.sender.send(Message::Response(response))?;
+        Ok(())
+    }
+
+    fn handle_request(&mut self, request: Request) -> Result<()> {
+        match request.method.as_str() {
+            HoverRequest::METHOD => {
+                let (id, params) = request.extract::<HoverParams>(HoverRequest::METHOD)?;
+                self.respond(Response::new_ok(id, self.hover(params)))
+            }
+            GotoDefinition::METHOD => {
+                let (id, params) =
+                    request.extract::<GotoDefinitionParams>(GotoDefinition::METHOD)?;
+                self.respond(Response::new_ok(id, self.definition(params)))
+            }
+            DocumentSymbolRequest::METHOD => {
+                let (id, params) =
+                    request.extract::<DocumentSymbolParams>(DocumentSymbolRequest::METHOD)?;
+                self.respond(Response::new_ok(id, self.document_symbols(params)))
+            }
+            Completion::METHOD => {
+                let (id, params) = request.extract::<CompletionParams>(Completion::METHOD)?;
+                self.respond(Response::new_ok(id, self.completion(params)))
+            }
+            _ => self.respond(Response::new_err(
+                request.id,
+                lsp_server::ErrorCode::MethodNotFound as i32,
+                format!("Método no soportado: {}", request.method),
+            )),
+        }
+    }
+
+    fn handle_notification(&mut self, notification: Notification) -> Result<()> {
+        match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let params = notification
+                    .extract::<lsp_types::DidOpenTextDocumentParams>(DidOpenTextDocument::METHOD)?;
+                let document = params.text_document;
+                self.update(document.uri, document.text, Some(document.version))
+            }
+            DidChangeTextDocument::METHOD => {
+                let params = notification.extract::<lsp_types::DidChangeTextDocumentParams>(
+                    DidChangeTextDocument::METHOD,
+                )?;
+                // Con sincronización completa, el último cambio trae todo el texto
+                match params.content_changes.into_iter().last() {
+                    Some(change) => self.update(
+                        params.text_document.uri,
+                        change.text,
+                        Some(params.text_document.version),
+                    ),
+                    None => Ok(()),
+                }
+            }
+            DidCloseTextDocument::METHOD => {
+                let params = notification.extract::<lsp_types::DidCloseTextDocumentParams>(
+                    DidCloseTextDocument::METHOD,
+                )?;
+                let uri = params.text_document.uri;
+                self.documents.remove(&uri);
+                // Al cerrar el archivo se borran sus diagnósticos del editor
+                self.publish(uri, Vec::new(), None)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Reanaliza el documento y publica sus diagnósticos.
+    fn update(&mut self, uri: Url, text: String, version: Option<i32>) -> Result<()> {
+        let document = Document::new(text);
+        let diagnostics = document.diagnostics.clone();
+        self.documents.insert(uri.clone(), document);
+        self.publish(uri, diagnostics, version)
+    }
+
+    fn publish(
+        &self,
+        uri: Url,
+        diagnostics: Vec<lsp_types::Diagnostic>,
+        version: Option<i32>,
+    ) -> Result<()> {
+        let params = PublishDiagnosticsParams {
+            uri,
+            diagnostics,
+            version,
+        };
+        let notification = Notification::new(PublishDiagnostics::METHOD.to_string(), params);
+        self.connection
+            .sender
+            .send(Message::Notification(notification))?;
+        Ok(())
+    }
+
+    fn hover(&self, params: HoverParams) -> Option<Hover> {
+        let position = params.text_document_position_params;
+        let document = self.documents.get(&position.text_document.uri)?;
+        let analysis = document.analysis.as_ref()?;
+        let offset = document.offset(position.position);
+
+        // Sobre un nombre se muestra su declaración; sobre otra expresión, su tipo
+        let (text, span) = match analysis.index.reference_at(offset) {
+            Some(reference) => {
+                let definition = &analysis.index.definitions[reference.definition?];
+                (definition.signature(), reference.span)
+            }
+            None => {
+                let node = typed_node_at(&analysis.tree, offset)?;
+                let ty = node.ty.as_ref()?;
+                (format!("{}: {}", node.kind, ty), node.span)
+            }
+        };
+        Some(Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: format!("```hulk\n{}\n```", text),
+            }),
+            range: Some(document.range(span)),
+        })
+    }
+
+    fn definition(&self, params: GotoDefinitionParams) -> Option<GotoDefinitionResponse> {
+        let position = params.text_document_position_params;
+        let uri = position.text_document.uri;
+        let document = self.documents.get(&uri)?;
+        let analysis = document.analysis.as_ref()?;
+        let offset = document.offset(position.position);
+        let reference = analysis.index.reference_at(offset)?;
+        let definition = &analysis.index.definitions[reference.definition?];
+        Some(GotoDefinitionResponse::Scalar(Location::new(
+            uri,
+            document.range(definition.span),
+        )))
+    }
+
+    fn document_symbols(&self, params: DocumentSymbolParams) -> Option<DocumentSymbolResponse> {
+        let document = self.documents.get(&params.text_document.uri)?;
+        let analysis = document.analysis.as_ref()?;
+        // HULK todavía no tiene declaraciones de tipos: los símbolos son las funciones
+        let symbols = analysis
+            .index
+            .definitions
+            .iter()
+            .filter(|definition| definition.kind == DefinitionKind::Function)
+            .map(|definition| {
+                #[allow(deprecated)]
+                DocumentSymbol {
+                    name: definition.name.clone(),
+                    detail: Some(definition.signature()),
+                    kind: SymbolKind::FUNCTION,
+                    tags: None,
+                    deprecated: None,
+                    range: document.range(definition.full_span),
+                    selection_range: document.range(definition.span),
+                    children: None,
+                }
+            })
+            .collect();
+        Some(DocumentSymbolResponse::Nested(symbols))
+    }
+
+    fn completion(&self, params: CompletionParams) -> Option<CompletionResponse> {
+        let position = params.text_document_position;
+        let document = self.documents.get(&position.text_document.uri)?;
+        let analysis = document.analysis.as_ref()?;
+        let offset = document.offset(position.position);
+
+        // Fuera de todo scope solo se ve lo global que dejó el checker
+        let symbols = match analysis.index.scope_at(offset) {
+            Some(scope) => scope.symbols.clone(),
+            None => analysis
+                .symbols
+                .visible()
+                .into_iter()
+                .map(|(name, info)| (name.clone(), info.clone()))
+                .collect(),
+        };
+        let items = symbols
+            .into_iter()
+            .map(|(name, info)| {
+                let (kind, detail) = match &info {
+                    SymbolInfo::Function {
+                        return_type,
+                        param_types,
+                    } => {
+                        let params = param_types
+                            .iter()
+                            .map(ToString::to_string)
+                            .collect::<Vec<_>>()
+                            .join(", ");
+                        (
+                            CompletionItemKind::FUNCTION,
+                            format!("({}): {}", params, return_type),
+                        )
+                    }
+                    SymbolInfo::Variable { var_type } => {
+                        (CompletionItemKind::VARIABLE, var_type.to_string())
+                    }
+                };
+                CompletionItem {
+                    label: name,
+                    kind: Some(kind),
+                    detail: Some(detail),
+                    ..CompletionItem::default()
+                }
+            })
+            .collect();
+        Some(CompletionResponse::Array(items))
+    }
+}
+
+/// Nodo anotado más interno cuya posición contiene `offset`.
+fn typed_node_at(node: &AstNode, offset: usize) -> Option<&AstNode> {
+    if offset < node.span.start || offset > node.span.end {
+        return None;
+    }
+    let children = node.children.iter().flat_map(|(_, child)| match child {
+        AstChild::Node(node) => std::slice::from_ref(node),
+        AstChild::List(nodes) => nodes.as_slice(),
+    });
+    for child in children {
+        if let Some(found) = typed_node_at(child, offset) {
+            return Some(found);
+        }
+    }
+    node.ty.as_ref().map(|_| node)
+}
@@ -5,6 +5,18 @@ use crate::Atom;
 use crate::BinOp;
 use crate::tokens;
 
+/// Identidad estable de una expresión: el fragmento del texto que ocupa.
+/// Cada expresión compuesta agrega al menos un token a los de sus hijas, así
+/// que dos expresiones de un mismo programa nunca comparten fragmento. A
+/// diferencia de la dirección del nodo, sobrevive a que el AST se mueva o se
+/// clone, y un nodo que pliega el optimizador conserva la de la expresión que
+/// reemplaza.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ExprId {
+    pub start: usize,
+    pub end: usize,
+}
+
 #[derive(Debug,Clone)]
 pub enum Expression {
     BinaryOp(BinaryOp),
@@ -19,7 +31,7 @@ pub enum Expression {
     Range(Box<Expression>, Box<Expression>),
     FunctionCall(functioncall::FunctionCall),
     FunctionDef(functiondeclaration::FunctionDef),
-    
+    Lambda(functiondeclaration::Lambda),
 }
 
 impl Expression {
@@ -62,7 +74,67 @@ impl Expression {
         Expression::Block(Box::new(block))
     }
 
+    /// Fragmento del texto parseado que ocupa la expresión. Las llamadas y los
+    /// rangos no guardan su paréntesis de cierre, así que terminan donde
+    /// termina su último argumento; las funciones empiezan en su nombre.
+    pub fn position(&self) -> tokens::Position {
+        let span =
+            |start: usize, last: &Expression| tokens::Position::new(start, last.position().end);
+        match self {
+            Expression::BinaryOp(binop) => span(binop.left.position().start, &binop.right),
+            Expression::Atom(atom) => match &**atom {
+                Atom::NumberLiteral(lit) | Atom::BooleanLiteral(lit) | Atom::StringLiteral(lit) => {
+                    lit.position()
+                }
+                Atom::Variable(identifier) => identifier.position,
+                Atom::Group(group) => tokens::Position::new(
+                    group.open_paren.position().start,
+                    group.close_paren.position().end,
+                ),
+            },
+            Expression::IfElse(ifelse) => {
+                let last = match (&ifelse.else_branch, ifelse.elif_branches.last()) {
+                    (Some(else_branch), _) => &**else_branch,
+                    (None, Some((_, _, branch))) => branch,
+                    (None, None) => &*ifelse.then_branch,
+                };
+                span(ifelse.if_kw.position().start, last)
+            }
+            Expression::LetIn(letin) => span(letin.let_token.position().start, &letin.body),
+            Expression::For(forr) => span(forr.for_token.position().start, &forr.body),
+            Expression::Print(_, position) => *position,
+            Expression::While(whilee) => span(whilee.while_token.position().start, &whilee.body),
+            Expression::Block(block) => tokens::Position::new(
+                block.open_brace.position().start,
+                block.close_brace.position().end,
+            ),
+            Expression::UnaryOp(unop) => {
+                let start = match unop.op {
+                    tokens::UnaryOp::Plus(p)
+                    | tokens::UnaryOp::Minus(p)
+                    | tokens::UnaryOp::Not(p) => p.start,
+                };
+                span(start, &unop.expr)
+            }
+            Expression::Range(start, end) => span(start.position().start, end),
+            Expression::FunctionCall(call) => match call.arguments.last() {
+                Some(last) => span(call.funct_name.position.start, last),
+                None => call.funct_name.position,
+            },
+            Expression::FunctionDef(def) => span(def.name.position.start, &def.body),
+            Expression::Lambda(lambda) => {
+                span(lambda.open_paren.position().start, &lambda.body)
+            }
+        }
+    }
 
+    pub fn id(&self) -> ExprId {
+        let position = self.position();
+        ExprId {
+            start: position.start,
+            end: position.end,
+        }
+    }
 }
 
 impl Visitable for Expression {
@@ -83,6 +155,7 @@ impl Visitable for Expression {
             }
             Expression::FunctionCall(call) => call.accept(visitor),
             Expression::FunctionDef(def) => def.accept(visitor),
+            Expression::Lambda(lambda) => lambda.accept(visitor),
         }
     }
 }
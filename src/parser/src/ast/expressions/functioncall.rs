@@ -1,16 +1,25 @@
 use super::super::{Visitable, Visitor};
 use crate::Expression;
-use crate::tokens::Identifier;
+use crate::tokens::{GroupingOperator, Identifier};
 
 #[derive(Debug,Clone)]
 pub struct FunctionCall {
     pub funct_name: Identifier,             
     pub arguments: Vec<Expression>,
+    pub close_paren: GroupingOperator,
 }
 
 impl FunctionCall {
-    pub fn new(funct_name: Identifier, arguments: Vec<Expression>) -> Self {
-        FunctionCall { funct_name, arguments }
+    pub fn new(
+        funct_name: Identifier,
+        arguments: Vec<Expression>,
+        close_paren: GroupingOperator,
+    ) -> Self {
+        FunctionCall {
+            funct_name,
+            arguments,
+            close_paren,
+        }
     }
 }
 
@@ -1,6 +1,6 @@
 use super::super::{Visitable, Visitor,Type};
 use crate::Expression;
-use crate::tokens::{Identifier };
+use crate::tokens::{GroupingOperator, Identifier};
 
 
 #[derive(Debug,Clone)]
@@ -43,4 +43,45 @@ impl Visitable for FunctionDef {
     fn accept<V: Visitor>(&self, visitor: &mut V) {
         visitor.visit_function_def(self);
     }
-}
\ No newline at end of file
+}
+
+/// Nombre de las funciones que salen de una lambda. No es un identificador
+/// válido, así que ninguna variable del programa puede taparlo ni nombrarlo.
+pub const LAMBDA_NAME: &str = "λ";
+
+/// Función anónima `(x: Number) => x * 2`, que vale lo que la clausura que crea.
+#[derive(Debug,Clone)]
+pub struct Lambda {
+    pub open_paren: GroupingOperator,
+    pub params: Vec<FunctionParams>,
+    pub body: Box<Expression>,
+}
+
+impl Lambda {
+    pub fn new(open_paren: GroupingOperator, params: Vec<FunctionParams>, body: Expression) -> Self {
+        Lambda {
+            open_paren,
+            params,
+            body: Box::new(body),
+        }
+    }
+
+    /// La lambda como una función anidada de nombre `LAMBDA_NAME`, para los
+    /// recorridos que ya saben compilar funciones. El tipo de retorno lo
+    /// decide quien llama, que es quien conoce el tipo del cuerpo.
+    pub fn as_function(&self, return_type: Type) -> FunctionDef {
+        let position = self.open_paren.position();
+        FunctionDef {
+            name: Identifier::new(position.start, position.end, LAMBDA_NAME),
+            params: self.params.clone(),
+            return_type,
+            body: self.body.clone(),
+        }
+    }
+}
+
+impl Visitable for Lambda {
+    fn accept<V: Visitor>(&self, visitor: &mut V) {
+        visitor.visit_lambda(self);
+    }
+}
@@ -1,5 +1,5 @@
 pub mod expressions;
-pub use expressions::Expression;
+pub use expressions::{ExprId, Expression};
 
 pub mod binoperation;
 pub use binoperation::BinaryOp;
@@ -24,4 +24,4 @@ pub use forr::For;
 pub mod functioncall;
 pub use functioncall::FunctionCall;
 pub mod functiondeclaration;
-pub use functiondeclaration::FunctionDef;
\ No newline at end of file
+pub use functiondeclaration::{FunctionDef, Lambda};
\ No newline at end of file
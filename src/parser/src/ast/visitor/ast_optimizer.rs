@@ -1,188 +1,345 @@
-use crate::ast;
+use crate::ast::Program;
+use crate::ast::atoms::atom::Atom;
+use crate::ast::atoms::group::Group;
 use crate::ast::expressions::binoperation::BinaryOp;
-use crate::ast::visitor::{Visitable, Visitor};
+use crate::ast::expressions::expressions::Expression;
+use crate::ast::expressions::ifelse::IfElse;
+use crate::ast::expressions::unaryoperation::UnaryOp;
+use crate::ast::visitor::folder::Folder;
+use crate::diagnostics::Diagnostic;
+use crate::tokens::{self, BinOp, Literal, Position};
 
-pub struct AstOptimizer;
+/// Transformación AST a AST que pliega las operaciones entre constantes y
+/// aplica identidades algebraicas (`x * 1`, `x + 0`, `if (true)`...).
+///
+/// Corre sobre programas ya chequeados: da por buenos los tipos de los
+/// operandos y no toca lo que en ejecución sería un error, salvo la división
+/// por un cero constante, que reporta en `diagnostics`.
+pub struct AstOptimizer {
+    pub diagnostics: Vec<Diagnostic>,
+}
 
 impl AstOptimizer {
     pub fn new() -> Self {
-        AstOptimizer
+        AstOptimizer {
+            diagnostics: Vec::new(),
+        }
     }
 
-    // fn merge_string_literals(&self, binop: &BinaryOp) -> Option<ast::Expression> {
-    //     if let BinOp::ConcatString(_) = binop.operator {
-    //         if let ast::Expression::Atom(left_atom) = &*binop.left {
-    //             if let ast::Expression::Atom(right_atom) = &*binop.right {
-    //                 if let ast::atoms::atom::Atom::StringLiteral(left_lit) = &**left_atom {
-    //                     if let ast::atoms::atom::Atom::StringLiteral(right_lit) = &**right_atom {
-    //                         // Merge the two string literals
-    //                         let merged_value = format!(
-    //                             "{}{}",
-    //                             left_lit.to_string(),
-    //                             right_lit.to_string()
-    //                         );
-    //                         return Some(ast::Expression::Atom(Box::new(
-    //                             ast::atoms::atom::Atom::new_string_literal(
-    //                                 left_lit.,
-    //                                 right_lit.position().end(),
-    //                                 &merged_value,
-    //                             ),
-    //                         )));
-    //                     }
-    //                 }
-    //             }
-    //         }
-    //     }
-    //     None
-    // }
-}
-
-impl Visitor for AstOptimizer {
-    fn visit_for(&mut self, _forr: &ast::forr::For) {
-        
-    }
-    fn visit_range(&mut self, _start: &ast::Expression, _end: &ast::Expression) {
-        
-    }
-    fn visit_function_call(&mut self, _call: &ast::expressions::functioncall::FunctionCall) {
-        
-    }
-    fn visit_function_def(&mut self, _def: &ast::expressions::functiondeclaration::FunctionDef) {
-        
+    pub fn optimize(&mut self, program: Program) -> Program {
+        self.fold_program(program)
     }
+}
 
-    fn visit_program(&mut self, program: &ast::Program) {
-        program.expression_list.accept(self);
+impl Folder for AstOptimizer {
+    fn fold_group(&mut self, group: Group) -> Expression {
+        let Group {
+            open_paren,
+            close_paren,
+            expression,
+        } = group;
+        match self.fold_expression(expression) {
+            // Los paréntesis alrededor de un átomo no aportan nada
+            inner @ Expression::Atom(_) => inner,
+            inner => Expression::new_atom(Atom::new_grouped_expression(Group::new(
+                open_paren,
+                inner,
+                close_paren,
+            ))),
+        }
     }
 
-    fn visit_expression_list(&mut self, expr_list: &ast::ExpressionList) {
-        for expr in &expr_list.expressions {
-            expr.accept(self);
+    fn fold_binary_op(&mut self, binop: BinaryOp) -> Expression {
+        let position = Position::new(binop.left.position().start, binop.right.position().end);
+        let BinaryOp {
+            left,
+            right,
+            operator,
+        } = binop;
+        // El lado izquierdo de `:=` es la variable asignada, no un valor
+        if let BinOp::Assign(_) = operator {
+            return Expression::new_binary_op(*left, self.fold_expression(*right), operator);
         }
-    }
+        let left = self.fold_expression(*left);
+        let right = self.fold_expression(*right);
 
-    fn visit_ifelse(&mut self, ifelse: &crate::ast::expressions::ifelse::IfElse) {
-        ifelse.condition.accept(self);
-        ifelse.then_branch.accept(self);
-        for (_, cond, expr) in &ifelse.elif_branches {
-            cond.accept(self);
-            expr.accept(self);
+        if let (BinOp::Div(_) | BinOp::Mod(_), Some(Literal::Number(0, _))) =
+            (&operator, literal(&right))
+        {
+            self.diagnostics.push(
+                Diagnostic::error(format!("División por cero en '{}'", operator)).at(position),
+            );
+            return Expression::new_binary_op(left, right, operator);
         }
-        if let Some(branch) = &ifelse.else_branch {
-            branch.accept(self);
+        if let (Some(l), Some(r)) = (literal(&left), literal(&right))
+            && let Some(folded) = fold_binary(&operator, l, r, position)
+        {
+            return folded;
         }
+        simplify(left, right, operator, position)
     }
 
-    fn visit_expression(&mut self, expr: &ast::Expression) {
-        expr.accept(self);
-    }
-
-    fn visit_atom(&mut self, _atom: &ast::atoms::atom::Atom) {
-        // Nothing to optimize in atoms
+    fn fold_unary_op(&mut self, unop: UnaryOp) -> Expression {
+        let start = match unop.op {
+            tokens::UnaryOp::Plus(p) | tokens::UnaryOp::Minus(p) | tokens::UnaryOp::Not(p) => {
+                p.start
+            }
+        };
+        let position = Position::new(start, unop.expr.position().end);
+        let operand = self.fold_expression(*unop.expr);
+        match (unop.op, literal(&operand)) {
+            (tokens::UnaryOp::Plus(_), Some(Literal::Number(..))) => operand,
+            (tokens::UnaryOp::Minus(_), Some(Literal::Number(n, _))) => {
+                number(n.wrapping_neg(), position)
+            }
+            (tokens::UnaryOp::Not(_), Some(Literal::Bool(b, _))) => boolean(!b, position),
+            (op, _) => Expression::new_unary_op(op, operand),
+        }
     }
 
-    fn visit_binary_op(&mut self, _binop: &BinaryOp) {
-        // Primero, recorre los hijos
-        // binop.left.accept(self);
-        // binop.right.accept(self);
-
-        // use crate::ast::atoms::atom::Atom::*;
-        // use crate::tokens::Literal;
-
-        // // Intenta reducir si ambos lados son literales
-        // if let (ast::Expression::Atom(left_atom), ast::Expression::Atom(rightAtom)) = (&*binop.left, &*binop.right) {
-        //     match (&**leftAtom, &**rightAtom, &binop.operator) {
-        //         // Comparaciones numéricas
-        //         (NumberLiteral(Literal::Number(l, _)), NumberLiteral(Literal::Number(r, _)), op) => {
-        //             let result = match op {
-        //                 BinOp::GreaterEqual(_) => l >= r,
-        //                 BinOp::Greater(_) => l > r,
-        //                 BinOp::LessEqual(_) => l <= r,
-        //                 BinOp::Less(_) => l < r,
-        //                 BinOp::EqualEqual(_) => l == r,
-        //                 BinOp::NotEqual(_) => l != r,
-        //                 _ => return,
-        //             };
-        //             // Aquí deberías reemplazar el nodo en el AST por un Atom::BooleanLiteral
-        //             // (esto requiere un AST mutable o una transformación, aquí solo es ejemplo)
-        //             // println!("Reducible: {} {:?} {} => {}", l, op, r, result);
-        //         }
-        //         // Operadores lógicos entre booleanos
-        //         (BooleanLiteral(Literal::Bool(l, _)), BooleanLiteral(Literal::Bool(r, _)), op) => {
-        //             let result = match op {
-        //                 BinOp::AndAnd(_) => *l && *r,
-        //                 BinOp::OrOr(_) => *l || *r,
-        //                 BinOp::EqualEqual(_) => l == r,
-        //                 BinOp::NotEqual(_) => l != r,
-        //                 _ => return,
-        //             };
-        //             // println!("Reducible: {} {:?} {} => {}", l, op, r, result);
-        //         }
-        //         _ => {}
-        //     }
-        }
-
-    fn visit_letin(&mut self, _letin: &ast::expressions::letin::LetIn) {
-        // for assign in &letin.assignments {
-        //     assign.accept(self);
-        // }
-        // letin.expression.accept(self);
-    }
+    /// Descarta las ramas cuya condición es una constante falsa y se queda
+    /// con la primera cuya condición es una constante verdadera.
+    fn fold_ifelse(&mut self, ifelse: IfElse) -> Expression {
+        let mut branches = vec![(ifelse.if_kw, *ifelse.condition, *ifelse.then_branch)];
+        branches.extend(ifelse.elif_branches);
+        let mut else_kw = ifelse.else_kw;
+        let mut else_branch = ifelse
+            .else_branch
+            .map(|branch| self.fold_expression(*branch));
 
-    fn visit_assignment(&mut self, _assign: &ast::expressions::letin::Assignment) {
-        // assign.expression.accept(self);
-    }
+        let mut kept = Vec::new();
+        let mut first_dropped = None;
+        for (keyword, condition, branch) in branches {
+            let condition = self.fold_expression(condition);
+            match literal(&condition) {
+                Some(Literal::Bool(false, _)) => {
+                    if first_dropped.is_none() {
+                        first_dropped = Some((keyword, condition, branch));
+                    }
+                }
+                Some(Literal::Bool(true, _)) => {
+                    let branch = self.fold_expression(branch);
+                    if kept.is_empty() {
+                        return branch;
+                    }
+                    // Las ramas siguientes ya no pueden ejecutarse
+                    else_kw = else_kw.or(Some(keyword));
+                    else_branch = Some(branch);
+                    break;
+                }
+                _ => kept.push((keyword, condition, self.fold_expression(branch))),
+            }
+        }
 
-    fn visit_block(&mut self, _block: &ast::expressions::block::Block) {
-        // for expr in &block.expressions.expressions {
-        //     expr.accept(self);
-        // }
+        if kept.is_empty() {
+            if let Some(branch) = else_branch {
+                return branch;
+            }
+            // Sin else, el if vale el valor por defecto de su tipo: se conserva
+            // la primera rama para que el tipo no cambie
+            kept.extend(first_dropped);
+        }
+        let mut kept = kept.into_iter();
+        let (if_kw, condition, then_branch) = kept.next().unwrap();
+        Expression::new_ifelse(IfElse::new(
+            if_kw,
+            condition,
+            then_branch,
+            kept.collect(),
+            else_kw,
+            else_branch,
+        ))
     }
+}
 
-    fn visit_literal(&mut self, _literal: &crate::tokens::Literal) {}
+fn number(n: i32, position: Position) -> Expression {
+    Expression::new_atom(Atom::NumberLiteral(Literal::Number(n, position)))
+}
 
-    fn visit_identifier(&mut self, _identifier: &crate::tokens::Identifier) {}
+fn boolean(b: bool, position: Position) -> Expression {
+    Expression::new_atom(Atom::BooleanLiteral(Literal::Bool(b, position)))
+}
 
-    fn visit_print(&mut self, expr: &ast::Expression) {
-        expr.accept(self);
+fn literal(expr: &Expression) -> Option<&Literal> {
+    match expr {
+        Expression::Atom(atom) => match &**atom {
+            Atom::NumberLiteral(lit) | Atom::BooleanLiteral(lit) | Atom::StringLiteral(lit) => {
+                Some(lit)
+            }
+            _ => None,
+        },
+        _ => None,
     }
+}
 
-    fn visit_group(&mut self, _group: &ast::atoms::group::Group) {}
+/// Resultado de aplicar `op` a dos literales, con la misma aritmética de 32
+/// bits que los backends. `None` si la operación fallaría en ejecución.
+fn fold_binary(
+    op: &BinOp,
+    left: &Literal,
+    right: &Literal,
+    position: Position,
+) -> Option<Expression> {
+    use Literal::{Bool, Number, Str};
+    let folded = match (left, right) {
+        (Number(l, _), Number(r, _)) => {
+            let (l, r) = (*l, *r);
+            match op {
+                BinOp::Plus(_) => number(l.wrapping_add(r), position),
+                BinOp::Minus(_) => number(l.wrapping_sub(r), position),
+                BinOp::Mul(_) => number(l.wrapping_mul(r), position),
+                BinOp::Div(_) => number(l.wrapping_div(r), position),
+                BinOp::Mod(_) => number(l.wrapping_rem(r), position),
+                BinOp::Pow(_) if r >= 0 => number(l.wrapping_pow(r as u32), position),
+                BinOp::EqualEqual(_) => boolean(l == r, position),
+                BinOp::NotEqual(_) => boolean(l != r, position),
+                BinOp::Less(_) => boolean(l < r, position),
+                BinOp::LessEqual(_) => boolean(l <= r, position),
+                BinOp::Greater(_) => boolean(l > r, position),
+                BinOp::GreaterEqual(_) => boolean(l >= r, position),
+                BinOp::ConcatString(_) => string(format!("{}{}", l, r), position),
+                _ => return None,
+            }
+        }
+        (Bool(l, _), Bool(r, _)) => match op {
+            BinOp::AndAnd(_) => boolean(*l && *r, position),
+            BinOp::OrOr(_) => boolean(*l || *r, position),
+            BinOp::EqualEqual(_) => boolean(l == r, position),
+            BinOp::NotEqual(_) => boolean(l != r, position),
+            _ => return None,
+        },
+        (Str(l, _), Str(r, _)) => match op {
+            BinOp::EqualEqual(_) => boolean(l == r, position),
+            BinOp::NotEqual(_) => boolean(l != r, position),
+            BinOp::ConcatString(_) => string(format!("{}{}", l, r), position),
+            _ => return None,
+        },
+        (Str(..) | Number(..), Str(..) | Number(..)) => match op {
+            BinOp::ConcatString(_) => string(format!("{}{}", left, right), position),
+            _ => return None,
+        },
+        _ => return None,
+    };
+    Some(folded)
+}
 
-    fn visit_unary_op(&mut self, _unary_op: &ast::expressions::unaryoperation::UnaryOp) {}
+fn string(s: String, position: Position) -> Expression {
+    Expression::new_atom(Atom::StringLiteral(Literal::Str(s, position)))
+}
 
-    fn visit_while(&mut self, _whilee: &ast::whilee::While) {}
+/// Identidades con un operando constante. Un operando que se descarta tiene
+/// que ser puro: los backends evalúan siempre los dos lados.
+fn simplify(left: Expression, right: Expression, op: BinOp, position: Position) -> Expression {
+    let is = |expr: &Expression, value: i32| matches!(literal(expr), Some(Literal::Number(n, _)) if *n == value);
+    let is_bool = |expr: &Expression, value: bool| matches!(literal(expr), Some(Literal::Bool(b, _)) if *b == value);
+    match op {
+        BinOp::Plus(_) if is(&right, 0) => left,
+        BinOp::Plus(_) if is(&left, 0) => right,
+        BinOp::Minus(_) if is(&right, 0) => left,
+        BinOp::Mul(_) | BinOp::Div(_) | BinOp::Pow(_) if is(&right, 1) => left,
+        BinOp::Mul(_) if is(&left, 1) => right,
+        BinOp::Mul(_) if (is(&right, 0) && is_pure(&left)) || (is(&left, 0) && is_pure(&right)) => {
+            number(0, position)
+        }
+        BinOp::Pow(_) if is(&right, 0) && is_pure(&left) => number(1, position),
+        BinOp::AndAnd(_) if is_bool(&left, true) => right,
+        BinOp::AndAnd(_) if is_bool(&right, true) => left,
+        BinOp::OrOr(_) if is_bool(&left, false) => right,
+        BinOp::OrOr(_) if is_bool(&right, false) => left,
+        BinOp::AndAnd(_)
+            if (is_bool(&left, false) && is_pure(&right))
+                || (is_bool(&right, false) && is_pure(&left)) =>
+        {
+            boolean(false, position)
+        }
+        BinOp::OrOr(_)
+            if (is_bool(&left, true) && is_pure(&right))
+                || (is_bool(&right, true) && is_pure(&left)) =>
+        {
+            boolean(true, position)
+        }
+        op => Expression::new_binary_op(left, right, op),
+    }
+}
 
+/// `true` si evaluar `expr` no tiene efectos visibles: no imprime, no asigna,
+/// no llama funciones, no puede fallar y siempre termina.
+fn is_pure(expr: &Expression) -> bool {
+    match expr {
+        Expression::Atom(atom) => match &**atom {
+            Atom::Group(group) => is_pure(&group.expression),
+            _ => true,
+        },
+        Expression::BinaryOp(binop) => {
+            let may_fail = match binop.operator {
+                // Solo un divisor constante distinto de cero asegura que no
+                // haya error de división por cero
+                BinOp::Div(_) | BinOp::Mod(_) => {
+                    !matches!(literal(&binop.right), Some(Literal::Number(n, _)) if *n != 0)
+                }
+                _ => false,
+            };
+            !may_fail
+                && !matches!(binop.operator, BinOp::Assign(_))
+                && is_pure(&binop.left)
+                && is_pure(&binop.right)
+        }
+        Expression::UnaryOp(unop) => is_pure(&unop.expr),
+        Expression::IfElse(ifelse) => {
+            is_pure(&ifelse.condition)
+                && is_pure(&ifelse.then_branch)
+                && ifelse
+                    .elif_branches
+                    .iter()
+                    .all(|(_, condition, branch)| is_pure(condition) && is_pure(branch))
+                && ifelse.else_branch.as_deref().is_none_or(is_pure)
+        }
+        Expression::LetIn(letin) => {
+            letin.bindings.iter().all(|assign| is_pure(&assign.body)) && is_pure(&letin.body)
+        }
+        Expression::Block(block) => block.expression_list.expressions.iter().all(is_pure),
+        // Crear la clausura no ejecuta el cuerpo
+        Expression::Lambda(_) => true,
+        _ => false,
+    }
 }
+
 // ...existing code...
 
-use std::collections::HashSet;
+/// Palabras reservadas que pueden ir seguidas de `(` sin ser una llamada.
+const KEYWORDS: &[&str] = &[
+    "print", "range", "if", "elif", "else", "while", "for", "in", "let", "function", "true",
+    "false",
+];
 
 /// Preprocesador: marca las llamadas a función con '@' antes del parseo.
-/// - Busca todas las declaraciones de función y las guarda.
-/// - Reemplaza cada llamada a función `foo(` por `@foo(` en el código fuente.
+///
+/// Es una llamada todo identificador seguido de `(` que no sea una palabra
+/// reservada, el nombre de una declaración (`function foo(`) ni un tipo
+/// (`: number(`, o el resultado de un tipo función: `-> number(`). Así se marcan también las llamadas a variables que
+/// guardan funciones. El contenido de los strings no se toca.
 pub fn preprocess_functions(source: &str) -> String {
-    let mut function_names = HashSet::new();
     let mut output = String::new();
-
-    // 1. Encuentra todas las declaraciones de función
-    for line in source.lines() {
-        let trimmed = line.trim_start();
-        if let Some(rest) = trimmed.strip_prefix("function ") {
-            if let Some(name) = rest.split(':').next() {
-                let name = name.trim();
-                if !name.is_empty() {
-                    function_names.insert(name.to_string());
-                }
-            }
-        }
-    }
-
-    // 2. Recorre el código y reemplaza llamadas a función
     let chars: Vec<char> = source.chars().collect();
     let mut i = 0;
     while i < chars.len() {
-        if chars[i].is_alphabetic() || chars[i] == '_' {
+        if chars[i] == '"' {
+            // Copia el string completo, con sus escapes
+            output.push('"');
+            i += 1;
+            while i < chars.len() && chars[i] != '"' {
+                if chars[i] == '\\' && i + 1 < chars.len() {
+                    output.push(chars[i]);
+                    i += 1;
+                }
+                output.push(chars[i]);
+                i += 1;
+            }
+            if i < chars.len() {
+                output.push('"');
+                i += 1;
+            }
+        } else if chars[i].is_alphabetic() || chars[i] == '_' {
             let start = i;
             while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
                 i += 1;
@@ -197,15 +354,17 @@ pub fn preprocess_functions(source: &str) -> String {
                 j += 1;
             }
 
-            let is_function_call = j < chars.len() && chars[j] == '(' && function_names.contains(&ident);
             let prev = output.trim_end();
-            let is_definition = prev.ends_with("function");
-            if is_function_call && !is_definition {
+            let is_function_call = j < chars.len()
+                && chars[j] == '('
+                && !KEYWORDS.contains(&ident.as_str())
+                && !prev.ends_with("function")
+                && !prev.ends_with(':')
+                && !prev.ends_with("->");
+            if is_function_call {
                 output.push('@');
-                output.push_str(&ident);
-            } else {
-                output.push_str(&ident);
             }
+            output.push_str(&ident);
             output.push_str(&spaces);
             i = j;
         } else {
@@ -215,4 +374,4 @@ pub fn preprocess_functions(source: &str) -> String {
     }
 
     output
-}
\ No newline at end of file
+}
@@ -58,6 +58,7 @@ impl Visitor for AstPrinterVisitor {
             Expression::Range(start, end) => self.visit_range(start, end),
             Expression::FunctionCall(call) => call.accept(self),      
             Expression::FunctionDef(def) => def.accept(self),  
+            Expression::Lambda(lambda) => lambda.accept(self),
         }
     }
 
@@ -97,6 +98,21 @@ fn visit_function_def(&mut self, def: &ast::expressions::functiondeclaration::Fu
     def.body.accept(self);
     self.indent -= 2;
 }
+
+fn visit_lambda(&mut self, lambda: &ast::expressions::functiondeclaration::Lambda) {
+    println!("{}Lambda", self.pad());
+    self.indent += 1;
+    println!("{}Params:", self.pad());
+    self.indent += 1;
+    for param in &lambda.params {
+        println!("{}{}", self.pad(), param.name);
+    }
+    self.indent -= 1;
+    println!("{}Body:", self.pad());
+    self.indent += 1;
+    lambda.body.accept(self);
+    self.indent -= 2;
+}
     fn visit_atom(&mut self, atom: &ast::atoms::atom::Atom) {
         use crate::ast::atoms::atom::Atom::*;
         match atom {
@@ -0,0 +1,469 @@
+use super::semantic_type_checker::TypeAnnotations;
+use super::types::Type;
+use crate::ast::atoms::atom::Atom;
+use crate::ast::expressions::binoperation::BinaryOp;
+use crate::ast::expressions::expressions::Expression;
+use crate::ast::expressions::functiondeclaration::{FunctionDef, FunctionParams, Lambda};
+use crate::ast::visitor::visitor::Visitor;
+use crate::ast::{ExpressionList, Program};
+use crate::tokens::{Identifier, Literal, Position};
+use crate::{Visitable, forr, whilee};
+use std::fmt::{self, Write};
+
+/// Valor de un atributo de `AstNode`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AstValue {
+    Number(i32),
+    Boolean(bool),
+    Text(String),
+}
+
+impl fmt::Display for AstValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AstValue::Number(n) => write!(f, "{}", n),
+            AstValue::Boolean(b) => write!(f, "{}", b),
+            AstValue::Text(text) => write!(f, "{}", json_string(text)),
+        }
+    }
+}
+
+/// Hijo de un `AstNode`: un nodo o una lista de nodos.
+#[derive(Debug, Clone)]
+pub enum AstChild {
+    Node(AstNode),
+    List(Vec<AstNode>),
+}
+
+/// Vista genérica de un nodo del AST, pensada para volcarla como JSON o
+/// S-expresión y consumirla desde herramientas externas.
+#[derive(Debug, Clone)]
+pub struct AstNode {
+    pub kind: &'static str,
+    pub span: Position,
+    /// Tipo que le asignó el chequeo semántico, si es una expresión anotada
+    pub ty: Option<Type>,
+    /// Datos propios del nodo: operador, nombre, valor literal...
+    pub attributes: Vec<(&'static str, AstValue)>,
+    pub children: Vec<(&'static str, AstChild)>,
+}
+
+impl AstNode {
+    pub fn new(kind: &'static str, span: Position) -> Self {
+        AstNode {
+            kind,
+            span,
+            ty: None,
+            attributes: Vec::new(),
+            children: Vec::new(),
+        }
+    }
+
+    fn attribute(mut self, name: &'static str, value: AstValue) -> Self {
+        self.attributes.push((name, value));
+        self
+    }
+
+    fn child(mut self, name: &'static str, node: AstNode) -> Self {
+        self.children.push((name, AstChild::Node(node)));
+        self
+    }
+
+    fn list(mut self, name: &'static str, nodes: Vec<AstNode>) -> Self {
+        self.children.push((name, AstChild::List(nodes)));
+        self
+    }
+
+    /// Traduce todas las posiciones con `offsets`, por ejemplo para llevarlas
+    /// del texto preprocesado al archivo original.
+    pub fn remap_spans(&mut self, offsets: &[usize]) {
+        let translate = |offset: usize| offsets.get(offset).copied().unwrap_or(offset);
+        self.span = Position::new(translate(self.span.start), translate(self.span.end));
+        for (_, child) in &mut self.children {
+            match child {
+                AstChild::Node(node) => node.remap_spans(offsets),
+                AstChild::List(nodes) => {
+                    for node in nodes {
+                        node.remap_spans(offsets);
+                    }
+                }
+            }
+        }
+    }
+
+    /// JSON con sangría de dos espacios. Cada nodo es un objeto con `kind`,
+    /// `span` (`start` y `end` en bytes), `type` si está anotado, y luego sus
+    /// atributos e hijos con su propio nombre.
+    pub fn to_json(&self) -> String {
+        let mut out = String::new();
+        self.write_json(&mut out, 0);
+        out.push('\n');
+        out
+    }
+
+    fn write_json(&self, out: &mut String, indent: usize) {
+        let pad = "  ".repeat(indent + 1);
+        out.push_str("{\n");
+        let _ = write!(out, "{}\"kind\": {}", pad, json_string(self.kind));
+        let _ = write!(
+            out,
+            ",\n{}\"span\": {{ \"start\": {}, \"end\": {} }}",
+            pad, self.span.start, self.span.end
+        );
+        if let Some(ty) = &self.ty {
+            let _ = write!(out, ",\n{}\"type\": {}", pad, json_string(&ty.to_string()));
+        }
+        for (name, value) in &self.attributes {
+            let _ = write!(out, ",\n{}{}: {}", pad, json_string(name), value);
+        }
+        for (name, child) in &self.children {
+            let _ = write!(out, ",\n{}{}: ", pad, json_string(name));
+            match child {
+                AstChild::Node(node) => node.write_json(out, indent + 1),
+                AstChild::List(nodes) if nodes.is_empty() => out.push_str("[]"),
+                AstChild::List(nodes) => {
+                    out.push_str("[\n");
+                    for (i, node) in nodes.iter().enumerate() {
+                        if i > 0 {
+                            out.push_str(",\n");
+                        }
+                        out.push_str(&"  ".repeat(indent + 2));
+                        node.write_json(out, indent + 2);
+                    }
+                    let _ = write!(out, "\n{}]", pad);
+                }
+            }
+        }
+        let _ = write!(out, "\n{}}}", "  ".repeat(indent));
+    }
+
+    /// S-expresión con un nodo por línea:
+    /// `(Kind start end :type T :atributo valor :hijo (...))`.
+    pub fn to_sexpr(&self) -> String {
+        let mut out = String::new();
+        self.write_sexpr(&mut out, 0);
+        out.push('\n');
+        out
+    }
+
+    fn write_sexpr(&self, out: &mut String, indent: usize) {
+        let _ = write!(out, "({} {} {}", self.kind, self.span.start, self.span.end);
+        if let Some(ty) = &self.ty {
+            let _ = write!(out, " :type {}", ty);
+        }
+        for (name, value) in &self.attributes {
+            let _ = write!(out, " :{} {}", name, value);
+        }
+        let pad = "  ".repeat(indent + 1);
+        for (name, child) in &self.children {
+            let _ = write!(out, "\n{}:{} ", pad, name);
+            match child {
+                AstChild::Node(node) => node.write_sexpr(out, indent + 1),
+                AstChild::List(nodes) => {
+                    out.push('(');
+                    for (i, node) in nodes.iter().enumerate() {
+                        if i > 0 {
+                            let _ = write!(out, "\n{} ", pad);
+                        }
+                        node.write_sexpr(out, indent + 1);
+                    }
+                    out.push(')');
+                }
+            }
+        }
+        out.push(')');
+    }
+}
+
+/// Cadena entre comillas con los escapes de JSON, que también valen para
+/// las S-expresiones.
+fn json_string(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Construye el `AstNode` de un programa. Cada `visit_*` deja su nodo en la
+/// pila y quien lo visitó lo recoge.
+pub struct AstSerializer<'a> {
+    expr_types: Option<&'a TypeAnnotations>,
+    nodes: Vec<AstNode>,
+}
+
+impl<'a> AstSerializer<'a> {
+    /// `expr_types` son las anotaciones del chequeo semántico; sin ellas los
+    /// nodos salen sin tipo.
+    pub fn new(expr_types: Option<&'a TypeAnnotations>) -> Self {
+        AstSerializer {
+            expr_types,
+            nodes: Vec::new(),
+        }
+    }
+
+    pub fn serialize(program: &Program, expr_types: Option<&'a TypeAnnotations>) -> AstNode {
+        let mut serializer = AstSerializer::new(expr_types);
+        program.accept(&mut serializer);
+        serializer.pop()
+    }
+
+    fn push(&mut self, node: AstNode) {
+        self.nodes.push(node);
+    }
+
+    fn pop(&mut self) -> AstNode {
+        self.nodes
+            .pop()
+            .expect("el serializador no produjo un nodo")
+    }
+
+    fn node(&mut self, expr: &Expression) -> AstNode {
+        self.visit_expression(expr);
+        self.pop()
+    }
+
+    fn nodes(&mut self, expressions: &[Expression]) -> Vec<AstNode> {
+        expressions.iter().map(|expr| self.node(expr)).collect()
+    }
+}
+
+/// Posición que abarca todas las expresiones, o la vacía si no hay ninguna.
+fn span_of(expressions: &[Expression]) -> Position {
+    match (expressions.first(), expressions.last()) {
+        (Some(first), Some(last)) => Position::new(first.position().start, last.position().end),
+        _ => Position::new(0, 0),
+    }
+}
+
+/// Nodos `Param` de los parámetros de una función o una lambda.
+fn param_nodes(params: &[FunctionParams]) -> Vec<AstNode> {
+    params
+        .iter()
+        .map(|param| {
+            AstNode::new("Param", param.name.position)
+                .attribute("name", AstValue::Text(param.name.name.clone()))
+                .attribute("signature", AstValue::Text(param.signature.to_string()))
+        })
+        .collect()
+}
+
+impl Visitor for AstSerializer<'_> {
+    fn visit_program(&mut self, program: &Program) {
+        let expressions = &program.expression_list.expressions;
+        let node = AstNode::new("Program", span_of(expressions))
+            .list("expressions", self.nodes(expressions));
+        self.push(node);
+    }
+
+    fn visit_expression_list(&mut self, expr_list: &ExpressionList) {
+        let expressions = &expr_list.expressions;
+        let node = AstNode::new("ExpressionList", span_of(expressions))
+            .list("expressions", self.nodes(expressions));
+        self.push(node);
+    }
+
+    /// Todas las expresiones pasan por aquí para recibir su posición completa
+    /// y el tipo anotado.
+    fn visit_expression(&mut self, expr: &Expression) {
+        match expr {
+            Expression::Print(value, _) => self.visit_print(value),
+            // `Range` no tiene nodo propio y su `accept` solo recorre los extremos
+            Expression::Range(start, end) => self.visit_range(start, end),
+            _ => expr.accept(self),
+        }
+        let mut node = self.pop();
+        node.span = expr.position();
+        node.ty = self
+            .expr_types
+            .and_then(|types| types.get(&expr.id()).cloned());
+        self.push(node);
+    }
+
+    fn visit_atom(&mut self, atom: &Atom) {
+        match atom {
+            Atom::NumberLiteral(lit) | Atom::BooleanLiteral(lit) | Atom::StringLiteral(lit) => {
+                self.visit_literal(lit)
+            }
+            Atom::Variable(identifier) => self.visit_identifier(identifier),
+            Atom::Group(group) => self.visit_group(group),
+        }
+    }
+
+    fn visit_binary_op(&mut self, binop: &BinaryOp) {
+        let left = self.node(&binop.left);
+        let right = self.node(&binop.right);
+        let node = AstNode::new("BinaryOp", Position::new(0, 0))
+            .attribute("operator", AstValue::Text(binop.operator.to_string()))
+            .child("left", left)
+            .child("right", right);
+        self.push(node);
+    }
+
+    fn visit_letin(&mut self, letin: &crate::ast::expressions::letin::LetIn) {
+        let bindings = letin
+            .bindings
+            .iter()
+            .map(|assign| {
+                self.visit_assignment(assign);
+                self.pop()
+            })
+            .collect();
+        let body = self.node(&letin.body);
+        let node = AstNode::new("LetIn", Position::new(0, 0))
+            .list("bindings", bindings)
+            .child("body", body);
+        self.push(node);
+    }
+
+    fn visit_assignment(&mut self, assign: &crate::ast::expressions::letin::Assignment) {
+        self.visit_atom(&assign.variable);
+        let variable = self.pop();
+        let body = self.node(&assign.body);
+        let span = Position::new(variable.span.start, body.span.end);
+        let node = AstNode::new("Assignment", span)
+            .child("variable", variable)
+            .child("value", body);
+        self.push(node);
+    }
+
+    fn visit_block(&mut self, block: &crate::ast::expressions::block::Block) {
+        let expressions = self.nodes(&block.expression_list.expressions);
+        let node = AstNode::new("Block", Position::new(0, 0)).list("expressions", expressions);
+        self.push(node);
+    }
+
+    fn visit_literal(&mut self, literal: &Literal) {
+        let node = match literal {
+            Literal::Number(n, p) => {
+                AstNode::new("Number", *p).attribute("value", AstValue::Number(*n))
+            }
+            Literal::Bool(b, p) => {
+                AstNode::new("Boolean", *p).attribute("value", AstValue::Boolean(*b))
+            }
+            Literal::Str(s, p) => {
+                AstNode::new("String", *p).attribute("value", AstValue::Text(s.clone()))
+            }
+        };
+        self.push(node);
+    }
+
+    fn visit_identifier(&mut self, identifier: &Identifier) {
+        let node = AstNode::new("Variable", identifier.position)
+            .attribute("name", AstValue::Text(identifier.name.clone()));
+        self.push(node);
+    }
+
+    fn visit_print(&mut self, expr: &Expression) {
+        let value = self.node(expr);
+        self.push(AstNode::new("Print", Position::new(0, 0)).child("value", value));
+    }
+
+    fn visit_while(&mut self, whilee: &whilee::While) {
+        let condition = self.node(&whilee.cond);
+        let body = self.node(&whilee.body);
+        let node = AstNode::new("While", Position::new(0, 0))
+            .child("condition", condition)
+            .child("body", body);
+        self.push(node);
+    }
+
+    fn visit_ifelse(&mut self, ifelse: &crate::ast::expressions::ifelse::IfElse) {
+        let condition = self.node(&ifelse.condition);
+        let then_branch = self.node(&ifelse.then_branch);
+        let elifs = ifelse
+            .elif_branches
+            .iter()
+            .map(|(keyword, condition, branch)| {
+                let span = Position::new(keyword.position().start, branch.position().end);
+                let condition = self.node(condition);
+                let body = self.node(branch);
+                AstNode::new("Elif", span)
+                    .child("condition", condition)
+                    .child("body", body)
+            })
+            .collect();
+        let mut node = AstNode::new("IfElse", Position::new(0, 0))
+            .child("condition", condition)
+            .child("then", then_branch)
+            .list("elifs", elifs);
+        if let Some(else_branch) = &ifelse.else_branch {
+            let else_branch = self.node(else_branch);
+            node = node.child("else", else_branch);
+        }
+        self.push(node);
+    }
+
+    fn visit_group(&mut self, group: &crate::ast::atoms::group::Group) {
+        let expression = self.node(&group.expression);
+        self.push(AstNode::new("Group", Position::new(0, 0)).child("expression", expression));
+    }
+
+    fn visit_unary_op(&mut self, unary_op: &crate::ast::expressions::unaryoperation::UnaryOp) {
+        let operand = self.node(&unary_op.expr);
+        let node = AstNode::new("UnaryOp", Position::new(0, 0))
+            .attribute("operator", AstValue::Text(unary_op.op.to_string()))
+            .child("operand", operand);
+        self.push(node);
+    }
+
+    fn visit_for(&mut self, forr: &forr::For) {
+        let variable = self.node(&forr.var);
+        let iterable = self.node(&forr.iterable);
+        let body = self.node(&forr.body);
+        let node = AstNode::new("For", Position::new(0, 0))
+            .child("variable", variable)
+            .child("iterable", iterable)
+            .child("body", body);
+        self.push(node);
+    }
+
+    fn visit_range(&mut self, start: &Expression, end: &Expression) {
+        let start = self.node(start);
+        let end = self.node(end);
+        let node = AstNode::new("Range", Position::new(0, 0))
+            .child("start", start)
+            .child("end", end);
+        self.push(node);
+    }
+
+    fn visit_function_call(&mut self, call: &crate::ast::expressions::functioncall::FunctionCall) {
+        let arguments = self.nodes(&call.arguments);
+        let node = AstNode::new("FunctionCall", Position::new(0, 0))
+            .attribute("name", AstValue::Text(call.funct_name.name.clone()))
+            .list("arguments", arguments);
+        self.push(node);
+    }
+
+    fn visit_function_def(&mut self, def: &FunctionDef) {
+        let params = param_nodes(&def.params);
+        let body = self.node(&def.body);
+        let node = AstNode::new("FunctionDef", Position::new(0, 0))
+            .attribute("name", AstValue::Text(def.name.name.clone()))
+            .attribute("return_type", AstValue::Text(def.return_type.to_string()))
+            .list("params", params)
+            .child("body", body);
+        self.push(node);
+    }
+
+    fn visit_lambda(&mut self, lambda: &Lambda) {
+        let params = param_nodes(&lambda.params);
+        let body = self.node(&lambda.body);
+        let node = AstNode::new("Lambda", Position::new(0, 0))
+            .list("params", params)
+            .child("body", body);
+        self.push(node);
+    }
+}
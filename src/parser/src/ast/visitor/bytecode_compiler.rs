@@ -0,0 +1,594 @@
+use super::semantic_type_checker::TypeAnnotations;
+use crate::ast::atoms::atom::Atom;
+use crate::ast::expressions::binoperation::BinaryOp;
+use crate::ast::expressions::expressions::Expression;
+use crate::ast::expressions::functiondeclaration::{FunctionDef, Lambda};
+use crate::ast::visitor::interpreter::operator_position;
+use crate::ast::visitor::types::Type;
+use crate::ast::visitor::visitor::Visitor;
+use crate::ast::{ExpressionList, Program};
+use crate::tokens::{BinOp, Identifier, Literal, Position};
+use crate::vm::{Function, OpCode, Value};
+use crate::{Visitable, whilee};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// Resultado de compilar un programa: el script de nivel superior y los
+/// nombres de las globales (una por función declarada).
+#[derive(Debug, Clone)]
+pub struct CompiledProgram {
+    pub script: Rc<Function>,
+    pub globals: Vec<String>,
+}
+
+impl CompiledProgram {
+    /// Listado del bytecode del script y de cada función que contiene.
+    pub fn disassemble(&self) -> String {
+        let mut out = String::new();
+        let mut pending = vec![Rc::clone(&self.script)];
+        while let Some(function) = pending.pop() {
+            out.push_str(&function.chunk.disassemble(&function.name));
+            out.push('\n');
+            for constant in function.chunk.constants.iter().rev() {
+                if let Value::Function(inner) = constant {
+                    pending.push(Rc::clone(inner));
+                }
+            }
+        }
+        out
+    }
+}
+
+/// Traduce el AST chequeado a bytecode para la `Vm`. Como con los `alloca`
+/// del backend LLVM, cada variable recibe un slot propio dentro del marco de
+/// su función, así los scopes no necesitan instrucciones para limpiar la pila.
+///
+/// Las funciones del nivel superior son globales. Las anidadas son variables
+/// locales con una clausura; lo que usan de las funciones que las contienen
+/// se copia al crearla y, al entrar, pasa de los upvalues a slots propios.
+/// Esos slots nunca se asignan: el chequeo rechaza `:=` sobre una captura.
+pub struct BytecodeCompiler {
+    pub function: Function,
+    /// Scopes de la función en curso: nombre de variable a slot local. El
+    /// primero guarda las capturas y la propia clausura.
+    pub scopes: Vec<HashMap<String, usize>>,
+    pub globals: HashMap<String, usize>,
+    pub global_names: Vec<String>,
+    pub expr_types: TypeAnnotations,
+    /// Slot local de cada upvalue de la función en curso
+    captured: Vec<usize>,
+    /// Funciones que contienen a la actual, desde el script hacia adentro
+    enclosing: Vec<FunctionState>,
+    /// Posición del último nodo visitado, asociada a cada instrucción emitida
+    position: Position,
+}
+
+/// Lo que se guarda de una función mientras se compila otra anidada en ella.
+struct FunctionState {
+    function: Function,
+    scopes: Vec<HashMap<String, usize>>,
+    captured: Vec<usize>,
+}
+
+impl BytecodeCompiler {
+    pub fn new(expr_types: TypeAnnotations) -> Self {
+        BytecodeCompiler {
+            function: Function::new("<script>", 0),
+            scopes: vec![HashMap::new()],
+            globals: HashMap::new(),
+            global_names: Vec::new(),
+            expr_types,
+            captured: Vec::new(),
+            enclosing: Vec::new(),
+            position: Position::new(0, 0),
+        }
+    }
+
+    /// Compila un programa ya chequeado.
+    pub fn compile(program: &Program, expr_types: TypeAnnotations) -> CompiledProgram {
+        let mut compiler = BytecodeCompiler::new(expr_types);
+        program.accept(&mut compiler);
+        CompiledProgram {
+            script: Rc::new(compiler.function),
+            globals: compiler.global_names,
+        }
+    }
+
+    fn emit(&mut self, op: OpCode) -> usize {
+        self.function.chunk.write(op, self.position)
+    }
+
+    fn emit_constant(&mut self, value: Value) {
+        let index = self.function.chunk.add_constant(value);
+        self.emit(OpCode::Constant(index));
+    }
+
+    /// Índice de la próxima instrucción, destino de los saltos hacia atrás.
+    fn here(&self) -> usize {
+        self.function.chunk.code.len()
+    }
+
+    fn patch_jump(&mut self, at: usize) {
+        let target = self.here();
+        self.function.chunk.patch_jump(at, target);
+    }
+
+    /// Compila algo que el chequeo rechaza como un error en tiempo de
+    /// ejecución en `position`.
+    fn fail(&mut self, message: &str, position: Position) {
+        self.position = position;
+        let index = self
+            .function
+            .chunk
+            .add_constant(Value::String(message.into()));
+        self.emit(OpCode::Fail(index));
+    }
+
+    /// Reserva un slot nuevo en el marco de la función en curso.
+    fn reserve_slot(&mut self) -> usize {
+        self.function.local_count += 1;
+        self.function.local_count - 1
+    }
+
+    fn declare_local(&mut self, name: &str) -> usize {
+        let slot = self.reserve_slot();
+        self.scopes
+            .last_mut()
+            .unwrap()
+            .insert(name.to_string(), slot);
+        slot
+    }
+
+    fn resolve_local(&mut self, identifier: &Identifier) -> usize {
+        self.resolve(&identifier.name)
+            .unwrap_or_else(|| panic!("Variable {} not found in scope", identifier.name))
+    }
+
+    /// Slot local de `name`. Si es una variable de una función exterior, la
+    /// captura la actual y cada una de las intermedias. `None` si no es una
+    /// variable: es una función global.
+    fn resolve(&mut self, name: &str) -> Option<usize> {
+        if let Some(slot) = find(&self.scopes, name) {
+            return Some(slot);
+        }
+        let current = self.save_state(Function::new("", 0), Vec::new());
+        self.enclosing.push(current);
+        let slot = self.capture(name, self.enclosing.len() - 1);
+        let current = self.enclosing.pop().unwrap();
+        self.restore_state(current);
+        slot
+    }
+
+    /// Busca `name` en la función `enclosing[depth]` y en las de afuera.
+    fn capture(&mut self, name: &str, depth: usize) -> Option<usize> {
+        if let Some(slot) = find(&self.enclosing[depth].scopes, name) {
+            return Some(slot);
+        }
+        if depth == 0 {
+            return None;
+        }
+        let outer = self.capture(name, depth - 1)?;
+        let state = &mut self.enclosing[depth];
+        state.function.local_count += 1;
+        let slot = state.function.local_count - 1;
+        state.function.upvalues.push(outer);
+        state.captured.push(slot);
+        state.scopes[0].insert(name.to_string(), slot);
+        Some(slot)
+    }
+
+    /// Empieza a compilar `function` y devuelve el estado de la anterior.
+    fn save_state(
+        &mut self,
+        function: Function,
+        scopes: Vec<HashMap<String, usize>>,
+    ) -> FunctionState {
+        FunctionState {
+            function: std::mem::replace(&mut self.function, function),
+            scopes: std::mem::replace(&mut self.scopes, scopes),
+            captured: std::mem::take(&mut self.captured),
+        }
+    }
+
+    /// Vuelve al estado `state` y devuelve la función que se compilaba.
+    fn restore_state(&mut self, state: FunctionState) -> Function {
+        self.scopes = state.scopes;
+        self.captured = state.captured;
+        std::mem::replace(&mut self.function, state.function)
+    }
+
+    /// Compila la función aparte. Una anidada empieza saltando a un prólogo,
+    /// emitido al final cuando ya se sabe qué captura, que copia su clausura
+    /// y sus upvalues a slots locales.
+    fn compile_function(&mut self, def: &FunctionDef, nested: bool) -> Function {
+        let function = Function::new(&def.name.name, def.params.len());
+        let params = def
+            .params
+            .iter()
+            .enumerate()
+            .map(|(slot, param)| (param.name.name.clone(), slot))
+            .collect();
+        let enclosing = self.save_state(function, vec![HashMap::new(), params]);
+        self.enclosing.push(enclosing);
+        self.position = def.name.position;
+        let prologue = nested.then(|| {
+            let jump = self.emit(OpCode::Jump(0));
+            let slot = self.reserve_slot();
+            self.scopes[0].insert(def.name.name.clone(), slot);
+            (jump, slot)
+        });
+
+        def.body.accept(self);
+        self.emit(OpCode::Return);
+
+        if let Some((jump, closure_slot)) = prologue {
+            self.position = def.name.position;
+            self.patch_jump(jump);
+            self.emit(OpCode::Callee);
+            self.emit(OpCode::SetLocal(closure_slot));
+            self.emit(OpCode::Pop);
+            for (index, slot) in self.captured.clone().into_iter().enumerate() {
+                self.emit(OpCode::GetUpvalue(index));
+                self.emit(OpCode::SetLocal(slot));
+                self.emit(OpCode::Pop);
+            }
+            self.emit(OpCode::Jump(jump + 1));
+        }
+        let enclosing = self.enclosing.pop().unwrap();
+        self.restore_state(enclosing)
+    }
+
+    /// Apila la función o variable `name`.
+    fn emit_get(&mut self, name: &str) {
+        match self.resolve(name) {
+            Some(slot) => self.emit(OpCode::GetLocal(slot)),
+            None => {
+                let global = self.global_slot(name);
+                self.emit(OpCode::GetGlobal(global))
+            }
+        };
+    }
+
+    fn global_slot(&mut self, name: &str) -> usize {
+        if let Some(slot) = self.globals.get(name) {
+            return *slot;
+        }
+        self.global_names.push(name.to_string());
+        self.globals
+            .insert(name.to_string(), self.global_names.len() - 1);
+        self.global_names.len() - 1
+    }
+
+    /// Guarda en `slot` el valor que tendría un bucle sin iteraciones.
+    fn init_loop_result(&mut self, body: &Expression) -> usize {
+        let slot = self.reserve_slot();
+        let default = match self.expr_types.get(&body.id()) {
+            Some(ty) => Value::default_for(ty),
+            None => Value::Number(0),
+        };
+        self.emit_constant(default);
+        self.emit(OpCode::SetLocal(slot));
+        self.emit(OpCode::Pop);
+        slot
+    }
+
+    /// Compila una lista de expresiones dejando solo el valor de la última.
+    fn compile_sequence(&mut self, expressions: &[Expression]) {
+        if expressions.is_empty() {
+            self.emit_constant(Value::Number(0));
+            return;
+        }
+        for (i, expr) in expressions.iter().enumerate() {
+            expr.accept(self);
+            if i + 1 < expressions.len() {
+                self.emit(OpCode::Pop);
+            }
+        }
+    }
+}
+
+impl Visitor for BytecodeCompiler {
+    fn visit_program(&mut self, program: &Program) {
+        let expressions = &program.expression_list.expressions;
+        // Las funciones se definen antes que el resto para poder llamarlas
+        // desde cualquier punto del programa, como en LLVM
+        for expr in expressions {
+            if let Expression::FunctionDef(def) = expr {
+                let global = self.global_slot(&def.name.name);
+                let function = self.compile_function(def, false);
+                self.position = def.name.position;
+                let index = self
+                    .function
+                    .chunk
+                    .add_constant(Value::Function(Rc::new(function)));
+                self.emit(OpCode::Closure(index));
+                self.emit(OpCode::SetGlobal(global));
+                self.emit(OpCode::Pop);
+            }
+        }
+        for expr in expressions {
+            if !matches!(expr, Expression::FunctionDef(_)) {
+                expr.accept(self);
+                self.emit(OpCode::Pop);
+            }
+        }
+        self.emit_constant(Value::Number(0));
+        self.emit(OpCode::Return);
+    }
+
+    fn visit_expression_list(&mut self, expr_list: &ExpressionList) {
+        self.compile_sequence(&expr_list.expressions);
+    }
+
+    fn visit_expression(&mut self, expr: &Expression) {
+        expr.accept(self);
+    }
+
+    fn visit_atom(&mut self, atom: &Atom) {
+        match atom {
+            Atom::NumberLiteral(lit) | Atom::BooleanLiteral(lit) | Atom::StringLiteral(lit) => {
+                self.visit_literal(lit)
+            }
+            Atom::Variable(identifier) => self.visit_identifier(identifier),
+            Atom::Group(group) => group.accept(self),
+        }
+    }
+
+    fn visit_binary_op(&mut self, binop: &BinaryOp) {
+        if let BinOp::Assign(position) = &binop.operator {
+            let identifier = match &*binop.left {
+                Expression::Atom(atom) => match &**atom {
+                    Atom::Variable(identifier) => Some(identifier),
+                    _ => None,
+                },
+                _ => None,
+            };
+            let Some(identifier) = identifier else {
+                self.fail("El lado izquierdo de := debe ser una variable", *position);
+                return;
+            };
+            let slot = self.resolve_local(identifier);
+            binop.right.accept(self);
+            // := devuelve el valor asignado, que queda en la pila
+            self.emit(OpCode::SetLocal(slot));
+            return;
+        }
+
+        // Ambos operandos se evalúan siempre, también en && y ||, como en LLVM
+        binop.left.accept(self);
+        binop.right.accept(self);
+        self.position = operator_position(&binop.operator);
+        let op = match &binop.operator {
+            BinOp::Plus(_) => OpCode::Add,
+            BinOp::Minus(_) => OpCode::Sub,
+            BinOp::Mul(_) => OpCode::Mul,
+            BinOp::Div(_) => OpCode::Div,
+            BinOp::Mod(_) => OpCode::Mod,
+            BinOp::Pow(_) => OpCode::Pow,
+            BinOp::EqualEqual(_) | BinOp::Equal(_) => OpCode::Equal,
+            BinOp::NotEqual(_) => OpCode::NotEqual,
+            BinOp::Less(_) => OpCode::Less,
+            BinOp::LessEqual(_) => OpCode::LessEqual,
+            BinOp::Greater(_) => OpCode::Greater,
+            BinOp::GreaterEqual(_) => OpCode::GreaterEqual,
+            BinOp::AndAnd(_) => OpCode::And,
+            BinOp::OrOr(_) => OpCode::Or,
+            BinOp::ConcatString(_) => OpCode::Concat,
+            BinOp::Assign(_) => unreachable!(),
+        };
+        self.emit(op);
+    }
+
+    fn visit_letin(&mut self, letin: &crate::ast::expressions::letin::LetIn) {
+        self.scopes.push(HashMap::new());
+        for assign in &letin.bindings {
+            // El valor se compila antes de declarar: `let x = x + 1` usa la `x` exterior
+            assign.body.accept(self);
+            let Atom::Variable(identifier) = &assign.variable else {
+                let position = operator_position(&assign.op);
+                self.fail(
+                    "El lado izquierdo de un let debe ser una variable",
+                    position,
+                );
+                continue;
+            };
+            let slot = self.declare_local(&identifier.name);
+            self.emit(OpCode::SetLocal(slot));
+            self.emit(OpCode::Pop);
+        }
+        letin.body.accept(self);
+        self.scopes.pop();
+    }
+
+    fn visit_block(&mut self, block: &crate::ast::expressions::block::Block) {
+        self.scopes.push(HashMap::new());
+        self.compile_sequence(&block.expression_list.expressions);
+        self.scopes.pop();
+    }
+
+    fn visit_literal(&mut self, literal: &Literal) {
+        match literal {
+            Literal::Number(n, p) => {
+                self.position = *p;
+                self.emit_constant(Value::Number(*n));
+            }
+            Literal::Bool(b, p) => {
+                self.position = *p;
+                self.emit_constant(Value::Boolean(*b));
+            }
+            Literal::Str(s, p) => {
+                self.position = *p;
+                self.emit_constant(Value::String(Rc::from(s.as_str())));
+            }
+        }
+    }
+
+    fn visit_identifier(&mut self, identifier: &Identifier) {
+        self.position = identifier.position;
+        self.emit_get(&identifier.name);
+    }
+
+    fn visit_print(&mut self, expr: &Expression) {
+        expr.accept(self);
+        self.emit(OpCode::Print);
+    }
+
+    fn visit_while(&mut self, whilee: &whilee::While) {
+        let result = self.init_loop_result(&whilee.body);
+        let loop_start = self.here();
+        whilee.cond.accept(self);
+        let exit_jump = self.emit(OpCode::JumpIfFalse(0));
+        whilee.body.accept(self);
+        self.emit(OpCode::SetLocal(result));
+        self.emit(OpCode::Pop);
+        self.emit(OpCode::Jump(loop_start));
+        self.patch_jump(exit_jump);
+        // El while vale lo que valió su última iteración
+        self.emit(OpCode::GetLocal(result));
+    }
+
+    fn visit_ifelse(&mut self, ifelse: &crate::ast::expressions::ifelse::IfElse) {
+        let mut end_jumps = Vec::new();
+        let branches = std::iter::once((&*ifelse.condition, &*ifelse.then_branch)).chain(
+            ifelse
+                .elif_branches
+                .iter()
+                .map(|(_, cond, branch)| (cond, branch)),
+        );
+        for (condition, branch) in branches {
+            condition.accept(self);
+            let next_jump = self.emit(OpCode::JumpIfFalse(0));
+            branch.accept(self);
+            end_jumps.push(self.emit(OpCode::Jump(0)));
+            self.patch_jump(next_jump);
+        }
+        match &ifelse.else_branch {
+            Some(else_branch) => else_branch.accept(self),
+            // Sin else el if vale 0, igual que en LLVM
+            None => self.emit_constant(Value::Number(0)),
+        }
+        for jump in end_jumps {
+            self.patch_jump(jump);
+        }
+    }
+
+    fn visit_group(&mut self, group: &crate::ast::atoms::group::Group) {
+        group.expression.accept(self);
+    }
+
+    fn visit_unary_op(&mut self, unary_op: &crate::ast::expressions::unaryoperation::UnaryOp) {
+        use crate::tokens::UnaryOp;
+        unary_op.expr.accept(self);
+        match unary_op.op {
+            UnaryOp::Plus(_) => {}
+            UnaryOp::Minus(p) => {
+                self.position = p;
+                self.emit(OpCode::Negate);
+            }
+            UnaryOp::Not(p) => {
+                self.position = p;
+                self.emit(OpCode::Not);
+            }
+        }
+    }
+
+    fn visit_for(&mut self, forr: &crate::forr::For) {
+        let identifier = match &*forr.var {
+            Expression::Atom(atom) => match &**atom {
+                Atom::Variable(identifier) => Some(identifier),
+                _ => None,
+            },
+            _ => None,
+        };
+        let Some(identifier) = identifier else {
+            let position = forr.var.position();
+            self.fail("La variable de un for debe ser un identificador", position);
+            return;
+        };
+        let Expression::Range(start, end) = &*forr.iterable else {
+            let position = forr.iterable.position();
+            self.fail("El iterable de un for debe ser un rango", position);
+            return;
+        };
+
+        start.accept(self);
+        self.scopes.push(HashMap::new());
+        let var = self.declare_local(&identifier.name);
+        self.emit(OpCode::SetLocal(var));
+        self.emit(OpCode::Pop);
+        end.accept(self);
+        let end_slot = self.reserve_slot();
+        self.emit(OpCode::SetLocal(end_slot));
+        self.emit(OpCode::Pop);
+        let result = self.init_loop_result(&forr.body);
+
+        let loop_start = self.here();
+        self.emit(OpCode::GetLocal(var));
+        self.emit(OpCode::GetLocal(end_slot));
+        self.emit(OpCode::Less);
+        let exit_jump = self.emit(OpCode::JumpIfFalse(0));
+        forr.body.accept(self);
+        self.emit(OpCode::SetLocal(result));
+        self.emit(OpCode::Pop);
+        // El cuerpo puede modificar la variable de control, como en LLVM
+        self.emit(OpCode::GetLocal(var));
+        self.emit_constant(Value::Number(1));
+        self.emit(OpCode::Add);
+        self.emit(OpCode::SetLocal(var));
+        self.emit(OpCode::Pop);
+        self.emit(OpCode::Jump(loop_start));
+        self.patch_jump(exit_jump);
+        self.scopes.pop();
+        self.emit(OpCode::GetLocal(result));
+    }
+
+    fn visit_range(&mut self, start: &Expression, end: &Expression) {
+        start.accept(self);
+        self.emit(OpCode::Pop);
+        end.accept(self);
+    }
+
+    fn visit_function_call(&mut self, call: &crate::ast::expressions::functioncall::FunctionCall) {
+        self.position = call.funct_name.position;
+        self.emit_get(&call.funct_name.name);
+        for arg in &call.arguments {
+            arg.accept(self);
+        }
+        self.position = call.funct_name.position;
+        self.emit(OpCode::Call(call.arguments.len()));
+    }
+
+    /// Solo llegan aquí las funciones anidadas: la clausura queda en una
+    /// variable nueva y la declaración vale 0, como en el intérprete.
+    fn visit_function_def(&mut self, def: &FunctionDef) {
+        let function = self.compile_function(def, true);
+        self.position = def.name.position;
+        let index = self
+            .function
+            .chunk
+            .add_constant(Value::Function(Rc::new(function)));
+        self.emit(OpCode::Closure(index));
+        let slot = self.declare_local(&def.name.name);
+        self.emit(OpCode::SetLocal(slot));
+        self.emit(OpCode::Pop);
+        self.emit_constant(Value::Number(0));
+    }
+
+    /// La clausura queda en la pila como valor de la expresión.
+    fn visit_lambda(&mut self, lambda: &Lambda) {
+        let function = self.compile_function(&lambda.as_function(Type::Unknown), true);
+        self.position = lambda.open_paren.position();
+        let index = self
+            .function
+            .chunk
+            .add_constant(Value::Function(Rc::new(function)));
+        self.emit(OpCode::Closure(index));
+    }
+}
+
+fn find(scopes: &[HashMap<String, usize>], name: &str) -> Option<usize> {
+    scopes
+        .iter()
+        .rev()
+        .find_map(|scope| scope.get(name).copied())
+}
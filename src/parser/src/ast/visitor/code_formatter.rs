@@ -0,0 +1,464 @@
+use super::types::Type;
+use crate::ast::atoms::atom::Atom;
+use crate::ast::expressions::binoperation::BinaryOp;
+use crate::ast::expressions::expressions::Expression;
+use crate::ast::expressions::functiondeclaration::{FunctionDef, FunctionParams, Lambda};
+use crate::ast::visitor::visitor::Visitor;
+use crate::ast::{ExpressionList, Program};
+use crate::comments::Comment;
+use crate::tokens::{BinOp, GroupingOperator, Identifier, Keyword, Literal};
+use crate::{Visitable, whilee};
+
+const INDENT: &str = "    ";
+
+/// Reescribe el AST como código HULK canónico: una expresión por línea,
+/// sangría de cuatro espacios y espacios alrededor de los operadores. Los
+/// comentarios entre expresiones se reubican entre las mismas expresiones, y
+/// los que están dentro de una quedan detrás del nodo que los precede.
+pub struct CodeFormatter {
+    pub out: String,
+    indent: usize,
+    /// Comentarios ordenados, con posiciones relativas al texto parseado
+    comments: Vec<Comment>,
+    next_comment: usize,
+}
+
+impl CodeFormatter {
+    pub fn new(comments: Vec<Comment>) -> Self {
+        CodeFormatter {
+            out: String::new(),
+            indent: 0,
+            comments,
+            next_comment: 0,
+        }
+    }
+
+    pub fn format(program: &Program, comments: Vec<Comment>) -> String {
+        let mut formatter = CodeFormatter::new(comments);
+        program.accept(&mut formatter);
+        formatter.out
+    }
+
+    fn write(&mut self, text: &str) {
+        self.out.push_str(text);
+    }
+
+    fn write_indent(&mut self) {
+        for _ in 0..self.indent {
+            self.out.push_str(INDENT);
+        }
+    }
+
+    /// Escribe los comentarios que empiezan antes de `offset`. Los que seguían
+    /// a código en su línea van al final de la última línea escrita.
+    fn flush_comments(&mut self, offset: usize) {
+        loop {
+            self.trailing_comments(offset);
+            let Some(comment) = self.comments.get(self.next_comment) else {
+                break;
+            };
+            if comment.position.start >= offset {
+                break;
+            }
+            let text = comment.text.clone();
+            self.next_comment += 1;
+            self.write_indent();
+            self.write(&text);
+            self.write("\n");
+        }
+    }
+
+    /// Escribe al final de la última línea terminada los comentarios que
+    /// empiezan antes de `offset` y seguían a código en su línea.
+    fn trailing_comments(&mut self, offset: usize) {
+        while let Some(comment) = self.comments.get(self.next_comment) {
+            if comment.position.start >= offset || comment.own_line || !self.out.ends_with('\n') {
+                break;
+            }
+            let text = comment.text.clone();
+            self.next_comment += 1;
+            self.out.pop();
+            self.write(" ");
+            self.write(&text);
+            self.write("\n");
+        }
+    }
+
+    /// Escribe en la línea actual, detrás de lo último escrito, los
+    /// comentarios que empiezan antes de `offset`. Un `//` pasa a `/* */`
+    /// porque detrás sigue código; si su texto contiene `*/` se deja para
+    /// el próximo `flush_comments`. Devuelve si escribió alguno.
+    fn inline_comments(&mut self, offset: usize) -> bool {
+        let mut written = false;
+        while let Some(comment) = self.comments.get(self.next_comment) {
+            if comment.position.start >= offset {
+                break;
+            }
+            let text = match comment.text.strip_prefix("//") {
+                Some(body) if body.contains("*/") => break,
+                Some(body) => format!("/* {} */", body.trim()),
+                None => comment.text.clone(),
+            };
+            self.next_comment += 1;
+            if !self.out.ends_with([' ', '\n', '(']) {
+                self.write(" ");
+            }
+            self.write(&text);
+            written = true;
+        }
+        written
+    }
+
+    /// Las expresiones de estas formas llevan `;` según la gramática.
+    fn needs_semicolon(expr: &Expression) -> bool {
+        match expr {
+            Expression::LetIn(_) | Expression::Print(..) | Expression::FunctionDef(_) => true,
+            Expression::BinaryOp(binop) => matches!(binop.operator, BinOp::Assign(_)),
+            _ => false,
+        }
+    }
+
+    /// Posición donde empieza `expr` en el texto parseado.
+    fn start_of(expr: &Expression) -> usize {
+        match expr {
+            Expression::BinaryOp(binop) => Self::start_of(&binop.left),
+            Expression::Atom(atom) => match &**atom {
+                Atom::NumberLiteral(lit) | Atom::BooleanLiteral(lit) | Atom::StringLiteral(lit) => {
+                    match lit {
+                        Literal::Number(_, p) | Literal::Str(_, p) | Literal::Bool(_, p) => p.start,
+                    }
+                }
+                Atom::Variable(identifier) => identifier.position.start,
+                Atom::Group(group) => match group.open_paren {
+                    GroupingOperator::OpenParen(p) => p.start,
+                    _ => Self::start_of(&group.expression),
+                },
+            },
+            Expression::IfElse(ifelse) => keyword_start(&ifelse.if_kw),
+            Expression::LetIn(letin) => keyword_start(&letin.let_token),
+            Expression::For(forr) => keyword_start(&forr.for_token),
+            Expression::While(whilee) => keyword_start(&whilee.while_token),
+            Expression::Print(_, position) => position.start,
+            Expression::Block(block) => match block.open_brace {
+                GroupingOperator::OpenBrace(p) => p.start,
+                _ => 0,
+            },
+            Expression::UnaryOp(unop) => match unop.op {
+                crate::tokens::UnaryOp::Plus(p)
+                | crate::tokens::UnaryOp::Minus(p)
+                | crate::tokens::UnaryOp::Not(p) => p.start,
+            },
+            Expression::Range(start, _) => Self::start_of(start),
+            Expression::FunctionCall(call) => call.funct_name.position.start,
+            Expression::FunctionDef(def) => def.name.position.start,
+            Expression::Lambda(lambda) => lambda.open_paren.position().start,
+        }
+    }
+
+    /// Escribe una secuencia de expresiones, una por línea, con los
+    /// comentarios que las preceden.
+    fn write_statements(&mut self, expressions: &[Expression], top_level: bool) {
+        for (i, expr) in expressions.iter().enumerate() {
+            let start = Self::start_of(expr);
+            // El comentario al final de la línea anterior se queda en ella,
+            // antes de la línea en blanco
+            self.trailing_comments(start);
+            // Las funciones de nivel superior van separadas por una línea en blanco
+            if top_level
+                && i > 0
+                && (matches!(expr, Expression::FunctionDef(_))
+                    || matches!(expressions[i - 1], Expression::FunctionDef(_)))
+            {
+                self.write("\n");
+            }
+            self.flush_comments(start);
+            self.write_indent();
+            self.visit_expression(expr);
+            self.write("\n");
+        }
+    }
+
+    fn type_name(ty: &Type) -> String {
+        match ty {
+            Type::Number => "number".to_string(),
+            Type::String => "string".to_string(),
+            Type::Boolean => "bool".to_string(),
+            Type::Function(params, ret) => format!(
+                "({}) -> {}",
+                params
+                    .iter()
+                    .map(Self::type_name)
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                Self::type_name(ret)
+            ),
+            other => other.to_string(),
+        }
+    }
+
+    /// `x: number, y: bool`, como van entre los paréntesis de una función.
+    /// Un comentario entre dos parámetros queda detrás del primero.
+    fn write_params(&mut self, params: &[FunctionParams]) {
+        for (i, param) in params.iter().enumerate() {
+            if i > 0 {
+                self.inline_comments(param.name.position.start);
+                self.write(", ");
+            }
+            self.write(&format!(
+                "{}: {}",
+                param.name.name,
+                Self::type_name(&param.signature)
+            ));
+        }
+    }
+
+    /// Escribe la rama de un `if` detrás de su condición o de su `else`. Si
+    /// el `if` va en varias líneas, una rama que no es un bloque pasa a la
+    /// línea siguiente con un nivel más de sangría.
+    fn write_branch(&mut self, branch: &Expression, multiline: bool) {
+        if multiline && !matches!(branch, Expression::Block(_)) {
+            self.write("\n");
+            self.indent += 1;
+            self.write_indent();
+            self.visit_expression(branch);
+            self.indent -= 1;
+        } else {
+            self.write(" ");
+            self.visit_expression(branch);
+        }
+    }
+
+    /// Separa de la rama anterior el `elif` o `else` que empieza en `offset`.
+    fn write_branch_keyword(&mut self, keyword: &str, offset: usize, multiline: bool) {
+        if multiline {
+            self.write("\n");
+            self.flush_comments(offset);
+            self.write_indent();
+        } else {
+            self.inline_comments(offset);
+            self.write(" ");
+        }
+        self.write(keyword);
+    }
+
+    fn operator(op: &BinOp) -> String {
+        match op {
+            // La gramática escribe los operadores lógicos con un solo carácter
+            BinOp::AndAnd(_) => "&".to_string(),
+            BinOp::OrOr(_) => "|".to_string(),
+            other => other.to_string(),
+        }
+    }
+}
+
+fn keyword_start(keyword: &Keyword) -> usize {
+    match keyword {
+        Keyword::Let(p)
+        | Keyword::In(p)
+        | Keyword::If(p)
+        | Keyword::Else(p)
+        | Keyword::Elif(p)
+        | Keyword::Print(p)
+        | Keyword::While(p)
+        | Keyword::For(p)
+        | Keyword::Function(p) => p.start,
+    }
+}
+
+impl Visitor for CodeFormatter {
+    fn visit_program(&mut self, program: &Program) {
+        self.write_statements(&program.expression_list.expressions, true);
+        self.flush_comments(usize::MAX);
+    }
+
+    fn visit_expression_list(&mut self, expr_list: &ExpressionList) {
+        self.write_statements(&expr_list.expressions, false);
+    }
+
+    /// Escribe `expr` seguida del `;` que exige su forma. Toda expresión hija
+    /// se escribe por aquí para no olvidarlo.
+    fn visit_expression(&mut self, expr: &Expression) {
+        if self.inline_comments(Self::start_of(expr)) {
+            self.write(" ");
+        }
+        match expr {
+            // `Range` no tiene nodo propio y su `accept` solo recorre los extremos
+            Expression::Range(start, end) => self.visit_range(start, end),
+            // `visit_print` no recibe la posición, que sirve para ubicar su `)`
+            Expression::Print(value, position) => {
+                self.write("print(");
+                self.visit_expression(value);
+                self.inline_comments(position.end);
+                self.write(")");
+            }
+            _ => expr.accept(self),
+        }
+        if Self::needs_semicolon(expr) {
+            self.write(";");
+        }
+    }
+
+    fn visit_atom(&mut self, atom: &Atom) {
+        match atom {
+            Atom::NumberLiteral(lit) | Atom::BooleanLiteral(lit) | Atom::StringLiteral(lit) => {
+                self.visit_literal(lit)
+            }
+            Atom::Variable(identifier) => self.visit_identifier(identifier),
+            Atom::Group(group) => self.visit_group(group),
+        }
+    }
+
+    fn visit_binary_op(&mut self, binop: &BinaryOp) {
+        self.visit_expression(&binop.left);
+        self.inline_comments(binop.operator.position().start);
+        let op = Self::operator(&binop.operator);
+        self.write(&format!(" {} ", op));
+        self.visit_expression(&binop.right);
+    }
+
+    fn visit_letin(&mut self, letin: &crate::ast::expressions::letin::LetIn) {
+        self.write("let ");
+        for (i, assign) in letin.bindings.iter().enumerate() {
+            if i > 0 {
+                self.write(", ");
+            }
+            self.visit_assignment(assign);
+        }
+        self.write(" in ");
+        self.visit_expression(&letin.body);
+    }
+
+    fn visit_assignment(&mut self, assign: &crate::ast::expressions::letin::Assignment) {
+        self.visit_atom(&assign.variable);
+        self.write(" = ");
+        self.visit_expression(&assign.body);
+    }
+
+    fn visit_block(&mut self, block: &crate::ast::expressions::block::Block) {
+        self.write("{\n");
+        self.indent += 1;
+        block.expression_list.accept(self);
+        if let GroupingOperator::CloseBrace(p) = block.close_brace {
+            self.flush_comments(p.start);
+        }
+        self.indent -= 1;
+        self.write_indent();
+        self.write("}");
+    }
+
+    fn visit_literal(&mut self, literal: &Literal) {
+        match literal {
+            // El contenido se guarda tal como se escribió, escapes incluidos
+            Literal::Str(s, _) => self.write(&format!("\"{}\"", s)),
+            other => self.write(&other.to_string()),
+        }
+    }
+
+    fn visit_identifier(&mut self, identifier: &Identifier) {
+        self.write(&identifier.name);
+    }
+
+    fn visit_while(&mut self, whilee: &whilee::While) {
+        self.write("while (");
+        self.visit_expression(&whilee.cond);
+        self.write(") ");
+        self.visit_expression(&whilee.body);
+    }
+
+    /// Si alguna rama es una sentencia con `;`, como `print(x);`, cada
+    /// `elif` y `else` empieza su propia línea.
+    fn visit_ifelse(&mut self, ifelse: &crate::ast::expressions::ifelse::IfElse) {
+        let multiline = std::iter::once(&*ifelse.then_branch)
+            .chain(ifelse.elif_branches.iter().map(|(_, _, branch)| branch))
+            .chain(ifelse.else_branch.as_deref())
+            .any(Self::needs_semicolon);
+        self.write("if (");
+        self.visit_expression(&ifelse.condition);
+        self.write(")");
+        self.write_branch(&ifelse.then_branch, multiline);
+        for (elif_kw, condition, branch) in &ifelse.elif_branches {
+            self.write_branch_keyword("elif (", elif_kw.position().start, multiline);
+            self.visit_expression(condition);
+            self.write(")");
+            self.write_branch(branch, multiline);
+        }
+        if let Some(else_branch) = &ifelse.else_branch {
+            let offset = ifelse
+                .else_kw
+                .as_ref()
+                .map_or(else_branch.position().start, |kw| kw.position().start);
+            self.write_branch_keyword("else", offset, multiline);
+            self.write_branch(else_branch, multiline);
+        }
+    }
+
+    fn visit_group(&mut self, group: &crate::ast::atoms::group::Group) {
+        self.write("(");
+        self.visit_expression(&group.expression);
+        self.inline_comments(group.close_paren.position().start);
+        self.write(")");
+    }
+
+    fn visit_unary_op(&mut self, unary_op: &crate::ast::expressions::unaryoperation::UnaryOp) {
+        self.write(&unary_op.op.to_string());
+        self.visit_expression(&unary_op.expr);
+    }
+
+    fn visit_for(&mut self, forr: &crate::forr::For) {
+        self.write("for (");
+        self.visit_expression(&forr.var);
+        self.write(" in ");
+        self.visit_expression(&forr.iterable);
+        self.write(") ");
+        self.visit_expression(&forr.body);
+    }
+
+    fn visit_range(&mut self, start: &Expression, end: &Expression) {
+        self.write("range(");
+        self.visit_expression(start);
+        self.write(", ");
+        self.visit_expression(end);
+        self.write(")");
+    }
+
+    fn visit_function_call(&mut self, call: &crate::ast::expressions::functioncall::FunctionCall) {
+        // La `@` que agrega el preprocesador no forma parte del código fuente
+        self.write(&call.funct_name.name);
+        self.write("(");
+        for (i, arg) in call.arguments.iter().enumerate() {
+            if i > 0 {
+                self.write(", ");
+            }
+            self.visit_expression(arg);
+        }
+        self.inline_comments(call.close_paren.position().start);
+        self.write(")");
+    }
+
+    fn visit_function_def(&mut self, def: &FunctionDef) {
+        self.write(&format!(
+            "function {}: {}(",
+            def.name.name,
+            Self::type_name(&def.return_type)
+        ));
+        self.write_params(&def.params);
+        self.write(")");
+        self.inline_comments(Self::start_of(&def.body));
+        self.write(" ");
+        if let Expression::Block(_) = &*def.body {
+            self.visit_expression(&def.body);
+        } else {
+            // La forma `=>` lleva su propio `;` además del de la declaración
+            self.write("=> ");
+            self.visit_expression(&def.body);
+            self.write(";");
+        }
+    }
+
+    fn visit_lambda(&mut self, lambda: &Lambda) {
+        self.write("(");
+        self.write_params(&lambda.params);
+        self.write(") => ");
+        self.visit_expression(&lambda.body);
+    }
+}
@@ -0,0 +1,294 @@
+use crate::ast::atoms::atom::Atom;
+use crate::ast::atoms::group::Group;
+use crate::ast::expressions::binoperation::BinaryOp;
+use crate::ast::expressions::block::Block;
+use crate::ast::expressions::expressions::Expression;
+use crate::ast::expressions::forr::For;
+use crate::ast::expressions::functioncall::FunctionCall;
+use crate::ast::expressions::functiondeclaration::{FunctionDef, Lambda};
+use crate::ast::expressions::ifelse::IfElse;
+use crate::ast::expressions::letin::{Assignment, LetIn};
+use crate::ast::expressions::unaryoperation::UnaryOp;
+use crate::ast::expressions::whilee::While;
+use crate::ast::{ExpressionList, Program};
+use crate::tokens::{Identifier, Literal, Position};
+
+/// Transformación que consume el AST y devuelve uno nuevo.
+///
+/// Cada método recibe el nodo por valor y devuelve su reemplazo. Los nodos
+/// que son expresiones devuelven una `Expression`, así una pasada puede
+/// cambiar un nodo por otro de distinta clase (una suma por un literal, un
+/// `if` por una de sus ramas). Por defecto cada método reconstruye el nodo
+/// plegando sus hijos con la función `walk_*` del mismo nombre; una pasada
+/// solo sobreescribe los nodos que le interesan.
+pub trait Folder {
+    fn fold_program(&mut self, program: Program) -> Program {
+        walk_program(self, program)
+    }
+    fn fold_expression_list(&mut self, expr_list: ExpressionList) -> ExpressionList {
+        walk_expression_list(self, expr_list)
+    }
+    fn fold_expression(&mut self, expr: Expression) -> Expression {
+        walk_expression(self, expr)
+    }
+    fn fold_atom(&mut self, atom: Atom) -> Expression {
+        walk_atom(self, atom)
+    }
+    fn fold_binary_op(&mut self, binop: BinaryOp) -> Expression {
+        walk_binary_op(self, binop)
+    }
+    fn fold_letin(&mut self, letin: LetIn) -> Expression {
+        walk_letin(self, letin)
+    }
+    fn fold_assignment(&mut self, assign: Assignment) -> Assignment {
+        walk_assignment(self, assign)
+    }
+    fn fold_block(&mut self, block: Block) -> Expression {
+        walk_block(self, block)
+    }
+    fn fold_literal(&mut self, literal: Literal) -> Literal {
+        literal
+    }
+    fn fold_identifier(&mut self, identifier: Identifier) -> Identifier {
+        identifier
+    }
+    fn fold_print(&mut self, expr: Expression, position: Position) -> Expression {
+        walk_print(self, expr, position)
+    }
+    fn fold_while(&mut self, whilee: While) -> Expression {
+        walk_while(self, whilee)
+    }
+    fn fold_ifelse(&mut self, ifelse: IfElse) -> Expression {
+        walk_ifelse(self, ifelse)
+    }
+    fn fold_group(&mut self, group: Group) -> Expression {
+        walk_group(self, group)
+    }
+    fn fold_unary_op(&mut self, unary_op: UnaryOp) -> Expression {
+        walk_unary_op(self, unary_op)
+    }
+    fn fold_for(&mut self, forr: For) -> Expression {
+        walk_for(self, forr)
+    }
+    fn fold_range(&mut self, start: Expression, end: Expression) -> Expression {
+        walk_range(self, start, end)
+    }
+    fn fold_function_call(&mut self, call: FunctionCall) -> Expression {
+        walk_function_call(self, call)
+    }
+    fn fold_function_def(&mut self, def: FunctionDef) -> Expression {
+        walk_function_def(self, def)
+    }
+    fn fold_lambda(&mut self, lambda: Lambda) -> Expression {
+        walk_lambda(self, lambda)
+    }
+}
+
+/// Pliega una expresión guardada en un `Box` reutilizando la caja.
+fn fold_boxed<F: Folder + ?Sized>(folder: &mut F, mut expr: Box<Expression>) -> Box<Expression> {
+    *expr = folder.fold_expression(*expr);
+    expr
+}
+
+pub fn walk_program<F: Folder + ?Sized>(folder: &mut F, program: Program) -> Program {
+    Program::new(folder.fold_expression_list(program.expression_list))
+}
+
+pub fn walk_expression_list<F: Folder + ?Sized>(
+    folder: &mut F,
+    expr_list: ExpressionList,
+) -> ExpressionList {
+    ExpressionList::new(
+        expr_list
+            .expressions
+            .into_iter()
+            .map(|expr| folder.fold_expression(expr))
+            .collect(),
+    )
+}
+
+pub fn walk_expression<F: Folder + ?Sized>(folder: &mut F, expr: Expression) -> Expression {
+    match expr {
+        Expression::BinaryOp(binop) => folder.fold_binary_op(binop),
+        Expression::Atom(atom) => folder.fold_atom(*atom),
+        Expression::IfElse(ifelse) => folder.fold_ifelse(*ifelse),
+        Expression::LetIn(letin) => folder.fold_letin(*letin),
+        Expression::For(forr) => folder.fold_for(*forr),
+        Expression::Print(expr, position) => folder.fold_print(*expr, position),
+        Expression::While(whilee) => folder.fold_while(*whilee),
+        Expression::Block(block) => folder.fold_block(*block),
+        Expression::UnaryOp(unary_op) => folder.fold_unary_op(unary_op),
+        Expression::Range(start, end) => folder.fold_range(*start, *end),
+        Expression::FunctionCall(call) => folder.fold_function_call(call),
+        Expression::FunctionDef(def) => folder.fold_function_def(def),
+        Expression::Lambda(lambda) => folder.fold_lambda(lambda),
+    }
+}
+
+pub fn walk_atom<F: Folder + ?Sized>(folder: &mut F, atom: Atom) -> Expression {
+    let atom = match atom {
+        Atom::NumberLiteral(literal) => Atom::NumberLiteral(folder.fold_literal(literal)),
+        Atom::BooleanLiteral(literal) => Atom::BooleanLiteral(folder.fold_literal(literal)),
+        Atom::StringLiteral(literal) => Atom::StringLiteral(folder.fold_literal(literal)),
+        Atom::Variable(identifier) => Atom::Variable(folder.fold_identifier(identifier)),
+        Atom::Group(group) => return folder.fold_group(*group),
+    };
+    Expression::new_atom(atom)
+}
+
+pub fn walk_binary_op<F: Folder + ?Sized>(folder: &mut F, binop: BinaryOp) -> Expression {
+    Expression::BinaryOp(BinaryOp {
+        left: fold_boxed(folder, binop.left),
+        right: fold_boxed(folder, binop.right),
+        operator: binop.operator,
+    })
+}
+
+pub fn walk_letin<F: Folder + ?Sized>(folder: &mut F, letin: LetIn) -> Expression {
+    Expression::LetIn(Box::new(LetIn {
+        let_token: letin.let_token,
+        bindings: letin
+            .bindings
+            .into_iter()
+            .map(|assign| folder.fold_assignment(assign))
+            .collect(),
+        in_token: letin.in_token,
+        body: fold_boxed(folder, letin.body),
+    }))
+}
+
+pub fn walk_assignment<F: Folder + ?Sized>(folder: &mut F, assign: Assignment) -> Assignment {
+    // El lado izquierdo es el nombre que se declara, no un valor
+    let variable = match assign.variable {
+        Atom::Variable(identifier) => Atom::Variable(folder.fold_identifier(identifier)),
+        variable => variable,
+    };
+    Assignment {
+        variable,
+        op: assign.op,
+        body: fold_boxed(folder, assign.body),
+    }
+}
+
+pub fn walk_block<F: Folder + ?Sized>(folder: &mut F, block: Block) -> Expression {
+    Expression::new_block(Block {
+        open_brace: block.open_brace,
+        close_brace: block.close_brace,
+        expression_list: folder.fold_expression_list(block.expression_list),
+    })
+}
+
+pub fn walk_print<F: Folder + ?Sized>(
+    folder: &mut F,
+    expr: Expression,
+    position: Position,
+) -> Expression {
+    Expression::new_print(folder.fold_expression(expr), position)
+}
+
+pub fn walk_while<F: Folder + ?Sized>(folder: &mut F, whilee: While) -> Expression {
+    Expression::new_while(While {
+        while_token: whilee.while_token,
+        cond: fold_boxed(folder, whilee.cond),
+        body: fold_boxed(folder, whilee.body),
+    })
+}
+
+pub fn walk_ifelse<F: Folder + ?Sized>(folder: &mut F, ifelse: IfElse) -> Expression {
+    Expression::new_ifelse(IfElse {
+        if_kw: ifelse.if_kw,
+        condition: fold_boxed(folder, ifelse.condition),
+        then_branch: fold_boxed(folder, ifelse.then_branch),
+        elif_branches: ifelse
+            .elif_branches
+            .into_iter()
+            .map(|(keyword, condition, branch)| {
+                (
+                    keyword,
+                    folder.fold_expression(condition),
+                    folder.fold_expression(branch),
+                )
+            })
+            .collect(),
+        else_kw: ifelse.else_kw,
+        else_branch: ifelse.else_branch.map(|branch| fold_boxed(folder, branch)),
+    })
+}
+
+pub fn walk_group<F: Folder + ?Sized>(folder: &mut F, group: Group) -> Expression {
+    Expression::new_atom(Atom::new_grouped_expression(Group {
+        open_paren: group.open_paren,
+        close_paren: group.close_paren,
+        expression: folder.fold_expression(group.expression),
+    }))
+}
+
+pub fn walk_unary_op<F: Folder + ?Sized>(folder: &mut F, unary_op: UnaryOp) -> Expression {
+    Expression::UnaryOp(UnaryOp {
+        op: unary_op.op,
+        expr: fold_boxed(folder, unary_op.expr),
+    })
+}
+
+pub fn walk_for<F: Folder + ?Sized>(folder: &mut F, forr: For) -> Expression {
+    Expression::new_for(For {
+        for_token: forr.for_token,
+        var: fold_boxed(folder, forr.var),
+        in_token: forr.in_token,
+        iterable: fold_boxed(folder, forr.iterable),
+        body: fold_boxed(folder, forr.body),
+    })
+}
+
+pub fn walk_range<F: Folder + ?Sized>(
+    folder: &mut F,
+    start: Expression,
+    end: Expression,
+) -> Expression {
+    let start = folder.fold_expression(start);
+    let end = folder.fold_expression(end);
+    Expression::new_range(start, end)
+}
+
+pub fn walk_function_call<F: Folder + ?Sized>(folder: &mut F, call: FunctionCall) -> Expression {
+    Expression::FunctionCall(FunctionCall {
+        funct_name: folder.fold_identifier(call.funct_name),
+        arguments: call
+            .arguments
+            .into_iter()
+            .map(|arg| folder.fold_expression(arg))
+            .collect(),
+        close_paren: call.close_paren,
+    })
+}
+
+pub fn walk_function_def<F: Folder + ?Sized>(folder: &mut F, def: FunctionDef) -> Expression {
+    Expression::FunctionDef(FunctionDef {
+        name: folder.fold_identifier(def.name),
+        params: def
+            .params
+            .into_iter()
+            .map(|mut param| {
+                param.name = folder.fold_identifier(param.name);
+                param
+            })
+            .collect(),
+        return_type: def.return_type,
+        body: fold_boxed(folder, def.body),
+    })
+}
+
+pub fn walk_lambda<F: Folder + ?Sized>(folder: &mut F, lambda: Lambda) -> Expression {
+    Expression::Lambda(Lambda {
+        open_paren: lambda.open_paren,
+        params: lambda
+            .params
+            .into_iter()
+            .map(|mut param| {
+                param.name = folder.fold_identifier(param.name);
+                param
+            })
+            .collect(),
+        body: fold_boxed(folder, lambda.body),
+    })
+}
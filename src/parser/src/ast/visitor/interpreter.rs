@@ -0,0 +1,699 @@
+use super::semantic_type_checker::TypeAnnotations;
+use super::types::Type;
+use crate::ast::atoms::atom::Atom;
+use crate::ast::expressions::binoperation::BinaryOp;
+use crate::ast::expressions::block::Block;
+use crate::ast::expressions::expressions::Expression;
+use crate::ast::expressions::functioncall::FunctionCall;
+use crate::ast::expressions::functiondeclaration::{FunctionDef, Lambda};
+use crate::ast::expressions::ifelse::IfElse;
+use crate::ast::expressions::letin::{Assignment, LetIn};
+use crate::ast::expressions::unaryoperation::UnaryOp;
+use crate::ast::visitor::value_visitor::ValueVisitor;
+use crate::ast::{ExpressionList, Program};
+use crate::tokens::{BinOp, Identifier, Literal, Position};
+use crate::whilee;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, Write};
+use std::rc::Rc;
+
+/// Profundidad máxima de llamadas antes de abortar con un error en lugar de
+/// desbordar la pila del intérprete.
+pub const MAX_CALL_DEPTH: usize = 10_000;
+
+/// Valor de HULK en tiempo de ejecución.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Number(i32),
+    Boolean(bool),
+    String(String),
+    Function(Rc<Closure>),
+}
+
+/// Función usada como valor: su declaración y una copia de las variables
+/// que veía donde se declaró.
+#[derive(Debug)]
+pub struct Closure {
+    pub def: Rc<FunctionDef>,
+    pub captured: HashMap<String, Value>,
+}
+
+/// Dos funciones son iguales solo si son el mismo valor.
+impl PartialEq for Closure {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl Value {
+    pub fn get_type(&self) -> Type {
+        match self {
+            Value::Number(_) => Type::Number,
+            Value::Boolean(_) => Type::Boolean,
+            Value::String(_) => Type::String,
+            Value::Function(closure) => Type::Function(
+                closure
+                    .def
+                    .params
+                    .iter()
+                    .map(|p| p.signature.clone())
+                    .collect(),
+                Box::new(closure.def.return_type.clone()),
+            ),
+        }
+    }
+
+    /// Valor de un bucle que no llega a ejecutar su cuerpo, igual que en LLVM.
+    pub fn default_for(ty: &Type) -> Value {
+        match ty {
+            Type::Boolean => Value::Boolean(false),
+            Type::String => Value::String(String::new()),
+            _ => Value::Number(0),
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Number(n) => write!(f, "{}", n),
+            Value::Boolean(b) => write!(f, "{}", b),
+            Value::String(s) => write!(f, "{}", s),
+            Value::Function(closure) => write!(f, "<función {}>", closure.def.name.name),
+        }
+    }
+}
+
+/// Error que aborta la ejecución, con la posición del código que lo provocó.
+#[derive(Debug, Clone)]
+pub struct RuntimeError {
+    pub message: String,
+    pub position: Position,
+    /// El mismo error en el runtime de los ejecutables nativos, si también
+    /// lo detecta
+    pub kind: Option<hulk_runtime::RuntimeError>,
+}
+
+impl RuntimeError {
+    pub fn new<S: Into<String>>(message: S, position: Position) -> Self {
+        RuntimeError {
+            message: message.into(),
+            position,
+            kind: None,
+        }
+    }
+
+    /// Error que el ejecutable nativo informa con el mismo mensaje.
+    pub fn native(kind: hulk_runtime::RuntimeError, position: Position) -> Self {
+        RuntimeError {
+            message: kind.to_string(),
+            position,
+            kind: Some(kind),
+        }
+    }
+
+    /// Código con que termina el programa: el del ejecutable nativo para el
+    /// mismo error y 1 para los que solo detectan los backends interpretados.
+    pub fn exit_code(&self) -> i32 {
+        self.kind.map_or(1, |kind| kind.exit_code())
+    }
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+/// Backend que evalúa el AST directamente, sin pasar por LLVM. Sirve como
+/// semántica de referencia para comparar la salida del código generado.
+pub struct Interpreter {
+    pub env_stack: Vec<HashMap<String, Value>>,
+    pub functions: HashMap<String, Rc<Closure>>,
+    pub expr_types: TypeAnnotations,
+    pub call_depth: usize,
+    output: Box<dyn Write>,
+}
+
+impl Interpreter {
+    pub fn new(expr_types: TypeAnnotations) -> Self {
+        Interpreter::with_output(expr_types, Box::new(io::stdout()))
+    }
+
+    /// Intérprete que escribe lo que imprime el programa en `output`.
+    pub fn with_output(expr_types: TypeAnnotations, output: Box<dyn Write>) -> Self {
+        Interpreter {
+            env_stack: vec![HashMap::new()],
+            functions: HashMap::new(),
+            expr_types,
+            call_depth: 0,
+            output,
+        }
+    }
+
+    /// Ejecuta un programa ya chequeado y devuelve el valor de su última expresión.
+    pub fn run(&mut self, program: &Program) -> Result<Value, RuntimeError> {
+        let result = self.visit_program(program);
+        let _ = self.output.flush();
+        result
+    }
+
+    /// Evalúa `expr` y devuelve su valor o el error que detuvo la evaluación.
+    pub fn eval(&mut self, expr: &Expression) -> Result<Value, RuntimeError> {
+        self.visit_expression(expr)
+    }
+
+    fn lookup_var(&self, name: &str) -> Option<&Value> {
+        self.env_stack
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name))
+    }
+
+    /// Declara `name` en el scope más interno; en el nivel superior queda
+    /// visible para todo lo que se ejecute después (lo usa el REPL).
+    pub fn declare_var(&mut self, name: &str, value: Value) {
+        self.env_stack
+            .last_mut()
+            .unwrap()
+            .insert(name.to_string(), value);
+    }
+
+    /// Cambia el valor de una variable existente en el scope más interno que la declare.
+    fn assign_var(&mut self, identifier: &Identifier, value: Value) -> Result<(), RuntimeError> {
+        for scope in self.env_stack.iter_mut().rev() {
+            if let Some(slot) = scope.get_mut(&identifier.name) {
+                *slot = value;
+                return Ok(());
+            }
+        }
+        Err(undefined_variable(identifier))
+    }
+
+    /// Valor de un bucle sin iteraciones según el tipo anotado de su cuerpo.
+    fn loop_default(&self, body: &Expression) -> Value {
+        match self.expr_types.get(&body.id()) {
+            Some(ty) => Value::default_for(ty),
+            None => Value::Number(0),
+        }
+    }
+
+    fn eval_atom_variable(&self, identifier: &Identifier) -> Result<Value, RuntimeError> {
+        self.lookup_var(&identifier.name)
+            .cloned()
+            .ok_or_else(|| undefined_variable(identifier))
+    }
+
+    /// Función a la que se refiere `name`: primero una variable que guarde
+    /// una función y, si no hay, una función declarada en el nivel superior.
+    fn lookup_function(&self, name: &str) -> Option<Rc<Closure>> {
+        if let Some(Value::Function(closure)) = self.lookup_var(name) {
+            return Some(Rc::clone(closure));
+        }
+        self.functions.get(name).cloned()
+    }
+
+    /// Clausura de `def` con una copia de todas las variables visibles. La
+    /// función ve los valores de este momento aunque afuera cambien después,
+    /// y el chequeo no deja asignarlos adentro.
+    fn closure(&self, def: &FunctionDef) -> Rc<Closure> {
+        let mut captured = HashMap::new();
+        for scope in &self.env_stack {
+            captured.extend(scope.iter().map(|(k, v)| (k.clone(), v.clone())));
+        }
+        Rc::new(Closure {
+            def: Rc::new(def.clone()),
+            captured,
+        })
+    }
+
+    fn eval_binary_op(&mut self, binop: &BinaryOp) -> Result<Value, RuntimeError> {
+        if let BinOp::Assign(position) = &binop.operator {
+            return match &*binop.left {
+                Expression::Atom(atom) => match &**atom {
+                    Atom::Variable(identifier) => {
+                        let value = self.eval(&binop.right)?;
+                        self.assign_var(identifier, value.clone())?;
+                        // := devuelve el valor asignado
+                        Ok(value)
+                    }
+                    _ => Err(RuntimeError::new(
+                        "El lado izquierdo de := debe ser una variable",
+                        *position,
+                    )),
+                },
+                _ => Err(RuntimeError::new(
+                    "El lado izquierdo de := debe ser una variable",
+                    *position,
+                )),
+            };
+        }
+
+        // Como en el código LLVM, ambos operandos se evalúan siempre, también en && y ||
+        let left = self.eval(&binop.left)?;
+        let right = self.eval(&binop.right)?;
+        let position = operator_position(&binop.operator);
+        let ordered = |ordering| {
+            compare(&binop.operator, ordering)
+                .map(Value::Boolean)
+                .ok_or_else(|| invalid_operands(&binop.operator, position))
+        };
+        match (&binop.operator, left, right) {
+            (BinOp::EqualEqual(_), l, r) => Ok(Value::Boolean(l == r)),
+            (BinOp::NotEqual(_), l, r) => Ok(Value::Boolean(l != r)),
+            (BinOp::AndAnd(_), Value::Boolean(l), Value::Boolean(r)) => Ok(Value::Boolean(l && r)),
+            (BinOp::OrOr(_), Value::Boolean(l), Value::Boolean(r)) => Ok(Value::Boolean(l || r)),
+            (BinOp::ConcatString(_), l, r) => Ok(Value::String(format!("{}{}", l, r))),
+            // Los strings se ordenan por sus bytes, como `hulk_str_cmp`
+            (_, Value::String(l), Value::String(r)) => ordered(l.cmp(&r)),
+            (op, Value::Number(l), Value::Number(r)) => match op {
+                // La aritmética es de 32 bits con desbordamiento circular, igual que en LLVM
+                BinOp::Plus(_) => Ok(Value::Number(l.wrapping_add(r))),
+                BinOp::Minus(_) => Ok(Value::Number(l.wrapping_sub(r))),
+                BinOp::Mul(_) => Ok(Value::Number(l.wrapping_mul(r))),
+                BinOp::Div(_) | BinOp::Mod(_) if r == 0 => Err(RuntimeError::native(
+                    hulk_runtime::RuntimeError::DivisionByZero,
+                    position,
+                )),
+                BinOp::Div(_) => Ok(Value::Number(l.wrapping_div(r))),
+                BinOp::Mod(_) => Ok(Value::Number(l.wrapping_rem(r))),
+                BinOp::Pow(_) if r < 0 => Err(RuntimeError::new(
+                    format!("Exponente negativo en potencia: {}", r),
+                    position,
+                )),
+                BinOp::Pow(_) => Ok(Value::Number(l.wrapping_pow(r as u32))),
+                _ => ordered(l.cmp(&r)),
+            },
+            _ => Err(invalid_operands(&binop.operator, position)),
+        }
+    }
+
+    fn eval_function_call(
+        &mut self,
+        call: &crate::ast::expressions::functioncall::FunctionCall,
+    ) -> Result<Value, RuntimeError> {
+        let name = &call.funct_name;
+        let closure = match self.lookup_function(&name.name) {
+            Some(closure) => closure,
+            None => {
+                return Err(RuntimeError::new(
+                    format!("Función '{}' no definida", name.name),
+                    name.position,
+                ));
+            }
+        };
+        let def = Rc::clone(&closure.def);
+        if def.params.len() != call.arguments.len() {
+            return Err(RuntimeError::new(
+                format!(
+                    "Función '{}' espera {} argumentos, pero se pasaron {}",
+                    name.name,
+                    def.params.len(),
+                    call.arguments.len()
+                ),
+                name.position,
+            ));
+        }
+        if self.call_depth >= MAX_CALL_DEPTH {
+            return Err(RuntimeError::new(
+                format!(
+                    "Desbordamiento de pila: más de {} llamadas anidadas",
+                    MAX_CALL_DEPTH
+                ),
+                name.position,
+            ));
+        }
+
+        let mut arguments = Vec::with_capacity(def.params.len());
+        for arg in &call.arguments {
+            arguments.push(self.eval(arg)?);
+        }
+
+        // El cuerpo ve lo que capturó, su propio nombre y sus parámetros, no
+        // las variables de quien llama
+        let mut frame = closure.captured.clone();
+        frame.insert(def.name.name.clone(), Value::Function(Rc::clone(&closure)));
+        for (param, value) in def.params.iter().zip(arguments) {
+            frame.insert(param.name.name.clone(), value);
+        }
+        let caller_env = std::mem::replace(&mut self.env_stack, vec![frame]);
+        self.call_depth += 1;
+        let result = self.eval(&def.body);
+        self.call_depth -= 1;
+        self.env_stack = caller_env;
+        result
+    }
+
+    fn eval_for(&mut self, forr: &crate::forr::For) -> Result<Value, RuntimeError> {
+        let var = match &*forr.var {
+            Expression::Atom(atom) => match &**atom {
+                Atom::Variable(identifier) => Some(identifier),
+                _ => None,
+            },
+            _ => None,
+        };
+        let Some(var) = var else {
+            return Err(RuntimeError::new(
+                "La variable de un for debe ser un identificador",
+                forr.var.position(),
+            ));
+        };
+        let Expression::Range(start, end) = &*forr.iterable else {
+            return Err(RuntimeError::new(
+                "El iterable de un for debe ser un rango",
+                forr.iterable.position(),
+            ));
+        };
+        let start = expect_number(self.eval(start)?, var)?;
+        let end = expect_number(self.eval(end)?, var)?;
+
+        self.env_stack.push(HashMap::new());
+        self.declare_var(&var.name, Value::Number(start));
+        let result = self.eval_for_scoped(forr, var, end);
+        self.env_stack.pop();
+        result
+    }
+
+    fn eval_for_scoped(
+        &mut self,
+        forr: &crate::forr::For,
+        var: &Identifier,
+        end: i32,
+    ) -> Result<Value, RuntimeError> {
+        let mut result = self.loop_default(&forr.body);
+        loop {
+            // El cuerpo puede modificar la variable de control, como en LLVM
+            let current = expect_number(self.eval_atom_variable(var)?, var)?;
+            if current >= end {
+                return Ok(result);
+            }
+            result = self.eval(&forr.body)?;
+            let current = expect_number(self.eval_atom_variable(var)?, var)?;
+            self.assign_var(var, Value::Number(current.wrapping_add(1)))?;
+        }
+    }
+
+    fn eval_while(&mut self, whilee: &whilee::While) -> Result<Value, RuntimeError> {
+        let mut result = self.loop_default(&whilee.body);
+        while expect_bool(self.eval(&whilee.cond)?, &whilee.cond)? {
+            result = self.eval(&whilee.body)?;
+        }
+        Ok(result)
+    }
+
+    fn eval_ifelse(
+        &mut self,
+        ifelse: &crate::ast::expressions::ifelse::IfElse,
+    ) -> Result<Value, RuntimeError> {
+        let branches = std::iter::once((&*ifelse.condition, &*ifelse.then_branch)).chain(
+            ifelse
+                .elif_branches
+                .iter()
+                .map(|(_, cond, branch)| (cond, branch)),
+        );
+        for (condition, branch) in branches {
+            if expect_bool(self.eval(condition)?, condition)? {
+                return self.eval(branch);
+            }
+        }
+        match &ifelse.else_branch {
+            Some(else_branch) => self.eval(else_branch),
+            // Sin else el if vale 0, igual que en LLVM
+            None => Ok(Value::Number(0)),
+        }
+    }
+
+    fn eval_letin_scoped(
+        &mut self,
+        letin: &crate::ast::expressions::letin::LetIn,
+    ) -> Result<Value, RuntimeError> {
+        for assign in &letin.bindings {
+            self.visit_assignment(assign)?;
+        }
+        self.eval(&letin.body)
+    }
+
+    fn eval_print(&mut self, expr: &Expression) -> Result<Value, RuntimeError> {
+        let value = self.eval(expr)?;
+        // Un fallo al escribir (p. ej. una tubería cerrada) no detiene el programa
+        let _ = writeln!(self.output, "{}", value);
+        // print devuelve el valor impreso
+        Ok(value)
+    }
+
+    fn eval_unary_op(
+        &mut self,
+        unary_op: &crate::ast::expressions::unaryoperation::UnaryOp,
+    ) -> Result<Value, RuntimeError> {
+        use crate::tokens::UnaryOp;
+        let value = self.eval(&unary_op.expr)?;
+        match (&unary_op.op, value) {
+            (UnaryOp::Plus(_), Value::Number(n)) => Ok(Value::Number(n)),
+            (UnaryOp::Minus(_), Value::Number(n)) => Ok(Value::Number(n.wrapping_neg())),
+            (UnaryOp::Not(_), Value::Boolean(b)) => Ok(Value::Boolean(!b)),
+            (UnaryOp::Plus(pos) | UnaryOp::Minus(pos) | UnaryOp::Not(pos), value) => {
+                Err(RuntimeError::new(
+                    format!(
+                        "Operador '{}' no aplicable a un valor de tipo {:?}",
+                        unary_op.op,
+                        value.get_type()
+                    ),
+                    *pos,
+                ))
+            }
+        }
+    }
+}
+
+fn literal_value(literal: &Literal) -> Value {
+    match literal {
+        Literal::Number(n, _) => Value::Number(*n),
+        Literal::Bool(b, _) => Value::Boolean(*b),
+        Literal::Str(s, _) => Value::String(s.clone()),
+    }
+}
+
+fn undefined_variable(identifier: &Identifier) -> RuntimeError {
+    RuntimeError::new(
+        format!("Variable '{}' no definida", identifier.name),
+        identifier.position,
+    )
+}
+
+/// Posición del operador, donde se reportan los errores de la operación.
+pub(crate) fn operator_position(op: &BinOp) -> Position {
+    match op {
+        BinOp::Mul(p)
+        | BinOp::Div(p)
+        | BinOp::Mod(p)
+        | BinOp::Pow(p)
+        | BinOp::Plus(p)
+        | BinOp::Minus(p)
+        | BinOp::EqualEqual(p)
+        | BinOp::NotEqual(p)
+        | BinOp::Less(p)
+        | BinOp::LessEqual(p)
+        | BinOp::Greater(p)
+        | BinOp::GreaterEqual(p)
+        | BinOp::AndAnd(p)
+        | BinOp::OrOr(p)
+        | BinOp::Equal(p)
+        | BinOp::Assign(p)
+        | BinOp::ConcatString(p) => *p,
+    }
+}
+
+fn invalid_operands(op: &BinOp, position: Position) -> RuntimeError {
+    RuntimeError::new(
+        format!("Operandos inválidos para el operador '{}'", op),
+        position,
+    )
+}
+
+/// Resultado de un operador de orden según cómo se comparan sus operandos,
+/// o `None` si `op` no es `<`, `<=`, `>` ni `>=`.
+fn compare(op: &BinOp, ordering: Ordering) -> Option<bool> {
+    match op {
+        BinOp::Less(_) => Some(ordering.is_lt()),
+        BinOp::LessEqual(_) => Some(ordering.is_le()),
+        BinOp::Greater(_) => Some(ordering.is_gt()),
+        BinOp::GreaterEqual(_) => Some(ordering.is_ge()),
+        _ => None,
+    }
+}
+
+fn expect_number(value: Value, var: &Identifier) -> Result<i32, RuntimeError> {
+    match value {
+        Value::Number(n) => Ok(n),
+        other => Err(RuntimeError::new(
+            format!(
+                "Los límites y la variable de un for deben ser números, no {:?}",
+                other.get_type()
+            ),
+            var.position,
+        )),
+    }
+}
+
+/// Posición aproximada de una expresión, para errores sobre condiciones.
+fn expression_position(expr: &Expression) -> Position {
+    match expr {
+        Expression::Atom(atom) => match &**atom {
+            Atom::NumberLiteral(lit) | Atom::BooleanLiteral(lit) | Atom::StringLiteral(lit) => {
+                match lit {
+                    Literal::Number(_, p) | Literal::Str(_, p) | Literal::Bool(_, p) => *p,
+                }
+            }
+            Atom::Variable(identifier) => identifier.position,
+            Atom::Group(group) => expression_position(&group.expression),
+        },
+        Expression::BinaryOp(binop) => operator_position(&binop.operator),
+        Expression::FunctionCall(call) => call.funct_name.position,
+        Expression::FunctionDef(def) => def.name.position,
+        Expression::Print(_, position) => *position,
+        _ => Position::new(0, 0),
+    }
+}
+
+fn expect_bool(value: Value, condition: &Expression) -> Result<bool, RuntimeError> {
+    match value {
+        Value::Boolean(b) => Ok(b),
+        other => Err(RuntimeError::new(
+            format!("La condición debe ser Boolean, no {:?}", other.get_type()),
+            expression_position(condition),
+        )),
+    }
+}
+
+impl ValueVisitor for Interpreter {
+    type Output = Result<Value, RuntimeError>;
+
+    /// Las funciones del nivel superior pueden llamarse antes de su
+    /// declaración, como en LLVM. Solo capturan las variables que el REPL
+    /// dejó definidas.
+    fn visit_program(&mut self, program: &Program) -> Self::Output {
+        for expr in &program.expression_list.expressions {
+            if let Expression::FunctionDef(def) = expr {
+                let closure = self.closure(def);
+                self.functions.insert(def.name.name.clone(), closure);
+            }
+        }
+        let mut result = Value::Number(0);
+        for expr in &program.expression_list.expressions {
+            if !matches!(expr, Expression::FunctionDef(_)) {
+                result = self.visit_expression(expr)?;
+            }
+        }
+        Ok(result)
+    }
+
+    /// Una lista vale lo que vale su última expresión; las declaraciones de
+    /// funciones no cuentan, igual que en LLVM.
+    fn visit_expression_list(&mut self, expr_list: &ExpressionList) -> Self::Output {
+        let mut result = Value::Number(0);
+        for expr in &expr_list.expressions {
+            let value = self.visit_expression(expr)?;
+            if !matches!(expr, Expression::FunctionDef(_)) {
+                result = value;
+            }
+        }
+        Ok(result)
+    }
+
+    fn visit_binary_op(&mut self, binop: &BinaryOp) -> Self::Output {
+        self.eval_binary_op(binop)
+    }
+
+    fn visit_letin(&mut self, letin: &LetIn) -> Self::Output {
+        self.env_stack.push(HashMap::new());
+        let result = self.eval_letin_scoped(letin);
+        self.env_stack.pop();
+        result
+    }
+
+    fn visit_assignment(&mut self, assign: &Assignment) -> Self::Output {
+        let value = self.eval(&assign.body)?;
+        match &assign.variable {
+            Atom::Variable(identifier) => self.declare_var(&identifier.name, value.clone()),
+            _ => {
+                return Err(RuntimeError::new(
+                    "El lado izquierdo de un let debe ser una variable",
+                    operator_position(&assign.op),
+                ));
+            }
+        }
+        Ok(value)
+    }
+
+    fn visit_block(&mut self, block: &Block) -> Self::Output {
+        self.env_stack.push(HashMap::new());
+        let result = self.visit_expression_list(&block.expression_list);
+        self.env_stack.pop();
+        result
+    }
+
+    fn visit_literal(&mut self, literal: &Literal) -> Self::Output {
+        Ok(literal_value(literal))
+    }
+
+    fn visit_identifier(&mut self, identifier: &Identifier) -> Self::Output {
+        match self.lookup_var(&identifier.name) {
+            Some(value) => Ok(value.clone()),
+            None => self
+                .lookup_function(&identifier.name)
+                .map(Value::Function)
+                .ok_or_else(|| undefined_variable(identifier)),
+        }
+    }
+
+    fn visit_print(&mut self, expr: &Expression) -> Self::Output {
+        self.eval_print(expr)
+    }
+
+    fn visit_while(&mut self, whilee: &whilee::While) -> Self::Output {
+        self.eval_while(whilee)
+    }
+
+    fn visit_ifelse(&mut self, ifelse: &IfElse) -> Self::Output {
+        self.eval_ifelse(ifelse)
+    }
+
+    fn visit_unary_op(&mut self, unary_op: &UnaryOp) -> Self::Output {
+        self.eval_unary_op(unary_op)
+    }
+
+    fn visit_for(&mut self, forr: &crate::forr::For) -> Self::Output {
+        self.eval_for(forr)
+    }
+
+    /// Fuera de un `for` un rango vale su extremo final, como en LLVM.
+    fn visit_range(&mut self, start: &Expression, end: &Expression) -> Self::Output {
+        self.eval(start)?;
+        self.eval(end)
+    }
+
+    fn visit_function_call(&mut self, call: &FunctionCall) -> Self::Output {
+        self.eval_function_call(call)
+    }
+
+    /// Una función anidada es una variable más del ámbito donde se declara,
+    /// con una copia de lo que ese ámbito ve en este momento.
+    fn visit_function_def(&mut self, def: &FunctionDef) -> Self::Output {
+        let closure = self.closure(def);
+        self.declare_var(&def.name.name, Value::Function(closure));
+        Ok(Value::Number(0))
+    }
+
+    fn visit_lambda(&mut self, lambda: &Lambda) -> Self::Output {
+        let return_type = self
+            .expr_types
+            .get(&lambda.body.id())
+            .cloned()
+            .unwrap_or(Type::Unknown);
+        Ok(Value::Function(
+            self.closure(&lambda.as_function(return_type)),
+        ))
+    }
+}
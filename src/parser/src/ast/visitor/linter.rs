@@ -0,0 +1,314 @@
+use crate::ast::Program;
+use crate::ast::atoms::atom::Atom;
+use crate::ast::expressions::binoperation::BinaryOp;
+use crate::ast::expressions::block::Block;
+use crate::ast::expressions::expressions::Expression;
+use crate::ast::expressions::functioncall::FunctionCall;
+use crate::ast::expressions::functiondeclaration::{FunctionDef, Lambda};
+use crate::ast::expressions::ifelse::IfElse;
+use crate::ast::expressions::letin::{Assignment, LetIn};
+use crate::ast::visitor::visitor::{Visitor, walk_block, walk_ifelse, walk_letin};
+use crate::diagnostics::Diagnostic;
+use crate::forr::For;
+use crate::tokens::{BinOp, Identifier, Literal};
+use std::collections::{HashMap, HashSet};
+
+/// Pasada de advertencias sobre un programa ya chequeado: variables de `let`
+/// y parámetros que nunca se leen, ramas `elif`/`else` que siguen a una
+/// condición constante `true` y funciones que nadie llama.
+///
+/// Los nombres que empiezan con `_` no se reportan. Asignar con `:=` no
+/// cuenta como uso de la variable.
+pub struct Linter {
+    pub diagnostics: Vec<Diagnostic>,
+    /// Advierte también cuando una declaración oculta a otra visible
+    pub warn_shadowing: bool,
+    /// Índice en `bindings` de cada nombre visible, por ámbito
+    scopes: Vec<HashMap<String, usize>>,
+    bindings: Vec<Binding>,
+    /// Funciones declaradas, en orden de declaración
+    functions: Vec<Identifier>,
+    /// Funciones llamadas desde fuera de su propio cuerpo
+    called: HashSet<String>,
+    /// Funciones cuyo cuerpo se está recorriendo
+    enclosing: Vec<String>,
+}
+
+struct Binding {
+    name: Identifier,
+    kind: BindingKind,
+    used: bool,
+}
+
+#[derive(PartialEq)]
+enum BindingKind {
+    Let,
+    /// Parámetro de la función con ese nombre
+    Param(String),
+    /// Parámetro de una lambda
+    LambdaParam,
+    /// Variable de control de un `for`, que no se reporta
+    For,
+}
+
+impl Linter {
+    pub fn new() -> Self {
+        Linter {
+            diagnostics: Vec::new(),
+            warn_shadowing: false,
+            scopes: vec![HashMap::new()],
+            bindings: Vec::new(),
+            functions: Vec::new(),
+            called: HashSet::new(),
+            enclosing: Vec::new(),
+        }
+    }
+
+    /// Advertencias de `program`, ordenadas por posición.
+    pub fn lint(program: &Program, warn_shadowing: bool) -> Vec<Diagnostic> {
+        let mut linter = Linter {
+            warn_shadowing,
+            ..Linter::new()
+        };
+        linter.visit_program(program);
+        linter.exit_scope();
+        for function in std::mem::take(&mut linter.functions) {
+            if !linter.called.contains(&function.name) {
+                linter.diagnostics.push(
+                    Diagnostic::warning(format!("La función '{}' nunca se llama", function.name))
+                        .at(function.position),
+                );
+            }
+        }
+        linter
+            .diagnostics
+            .sort_by_key(|diagnostic| diagnostic.position.map(|position| position.start));
+        linter.diagnostics
+    }
+
+    fn enter_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    /// Cierra el ámbito actual y reporta lo que se declaró en él sin usarse.
+    fn exit_scope(&mut self) {
+        let Some(scope) = self.scopes.pop() else {
+            return;
+        };
+        let mut unused: Vec<usize> = scope
+            .into_values()
+            .filter(|&index| !self.bindings[index].used)
+            .collect();
+        unused.sort_unstable();
+        for index in unused {
+            self.report_unused(index);
+        }
+    }
+
+    fn report_unused(&mut self, index: usize) {
+        let binding = &self.bindings[index];
+        if binding.name.name.starts_with('_') {
+            return;
+        }
+        let message = match &binding.kind {
+            BindingKind::Let => format!(
+                "La variable '{}' se declara pero nunca se usa",
+                binding.name.name
+            ),
+            BindingKind::Param(function) => format!(
+                "El parámetro '{}' de '{}' nunca se usa",
+                binding.name.name, function
+            ),
+            BindingKind::LambdaParam => format!(
+                "El parámetro '{}' de la lambda nunca se usa",
+                binding.name.name
+            ),
+            BindingKind::For => return,
+        };
+        let diagnostic = Diagnostic::warning(message)
+            .with_note(format!(
+                "si es intencional, llámela '_{}'",
+                binding.name.name
+            ))
+            .at(binding.name.position);
+        self.diagnostics.push(diagnostic);
+    }
+
+    /// Declara `name` en el ámbito actual. Si ya había una declaración con
+    /// ese nombre en el mismo ámbito, queda oculta y se reporta si no se usó.
+    fn declare(&mut self, name: &Identifier, kind: BindingKind) {
+        if self.warn_shadowing && !name.name.starts_with('_') {
+            let visible = self
+                .scopes
+                .iter()
+                .rev()
+                .find_map(|scope| scope.get(&name.name));
+            if let Some(&previous) = visible {
+                self.diagnostics.push(
+                    Diagnostic::warning(format!(
+                        "'{}' oculta una declaración anterior con el mismo nombre",
+                        name.name
+                    ))
+                    .with_related(
+                        "declarado antes aquí",
+                        self.bindings[previous].name.position,
+                    )
+                    .at(name.position),
+                );
+            }
+        }
+        self.bindings.push(Binding {
+            name: name.clone(),
+            kind,
+            used: false,
+        });
+        let index = self.bindings.len() - 1;
+        let scope = self
+            .scopes
+            .last_mut()
+            .expect("siempre hay un ámbito abierto");
+        if let Some(previous) = scope.insert(name.name.clone(), index)
+            && !self.bindings[previous].used
+        {
+            self.report_unused(previous);
+        }
+    }
+
+    /// Marca como usada la variable `name`; si no hay ninguna visible, el
+    /// nombre es de una función y cuenta como llamada. Las referencias desde
+    /// el cuerpo de la propia función no cuentan: una función que solo se
+    /// llama a sí misma sigue sin usarse.
+    fn mark_used(&mut self, name: &str) {
+        let found = self.scopes.iter().rev().find_map(|scope| scope.get(name));
+        match found {
+            Some(&index) => self.bindings[index].used = true,
+            None if !self.enclosing.iter().any(|function| function == name) => {
+                self.called.insert(name.to_string());
+            }
+            None => {}
+        }
+    }
+}
+
+impl Default for Linter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Indica si la condición es el literal `true`, quizás entre paréntesis.
+fn is_always_true(condition: &Expression) -> bool {
+    match condition {
+        Expression::Atom(atom) => match atom.as_ref() {
+            Atom::BooleanLiteral(Literal::Bool(true, _)) => true,
+            Atom::Group(group) => is_always_true(&group.expression),
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+impl Visitor for Linter {
+    fn visit_identifier(&mut self, identifier: &Identifier) {
+        self.mark_used(&identifier.name);
+    }
+
+    /// El lado izquierdo de `:=` se escribe, no se lee.
+    fn visit_binary_op(&mut self, binop: &BinaryOp) {
+        if !matches!(binop.operator, BinOp::Assign(_)) {
+            self.visit_expression(&binop.left);
+        }
+        self.visit_expression(&binop.right);
+    }
+
+    /// El valor se recorre antes de declarar: en `let x = x + 1` la `x` del
+    /// valor es la exterior.
+    fn visit_assignment(&mut self, assign: &Assignment) {
+        self.visit_expression(&assign.body);
+        if let Atom::Variable(identifier) = &assign.variable {
+            self.declare(identifier, BindingKind::Let);
+        }
+    }
+
+    fn visit_letin(&mut self, letin: &LetIn) {
+        self.enter_scope();
+        walk_letin(self, letin);
+        self.exit_scope();
+    }
+
+    fn visit_block(&mut self, block: &Block) {
+        self.enter_scope();
+        walk_block(self, block);
+        self.exit_scope();
+    }
+
+    fn visit_for(&mut self, forr: &For) {
+        self.visit_expression(&forr.iterable);
+        self.enter_scope();
+        if let Expression::Atom(atom) = forr.var.as_ref()
+            && let Atom::Variable(identifier) = atom.as_ref()
+        {
+            self.declare(identifier, BindingKind::For);
+        }
+        self.visit_expression(&forr.body);
+        self.exit_scope();
+    }
+
+    /// Después de una condición siempre verdadera, las ramas que siguen no
+    /// se ejecutan nunca.
+    fn visit_ifelse(&mut self, ifelse: &IfElse) {
+        walk_ifelse(self, ifelse);
+        let mut conditions = std::iter::once(ifelse.condition.as_ref()).chain(
+            ifelse
+                .elif_branches
+                .iter()
+                .map(|(_, condition, _)| condition),
+        );
+        let Some(first_true) = conditions.position(is_always_true) else {
+            return;
+        };
+        let cause = "la condición de una rama anterior siempre es verdadera";
+        for (elif_kw, _, _) in ifelse.elif_branches.iter().skip(first_true) {
+            self.diagnostics.push(
+                Diagnostic::warning("Rama 'elif' inalcanzable")
+                    .with_note(cause)
+                    .at(elif_kw.position()),
+            );
+        }
+        if let Some(else_kw) = &ifelse.else_kw {
+            self.diagnostics.push(
+                Diagnostic::warning("Rama 'else' inalcanzable")
+                    .with_note(cause)
+                    .at(else_kw.position()),
+            );
+        }
+    }
+
+    fn visit_function_call(&mut self, call: &FunctionCall) {
+        self.mark_used(&call.funct_name.name);
+        for arg in &call.arguments {
+            self.visit_expression(arg);
+        }
+    }
+
+    fn visit_function_def(&mut self, def: &FunctionDef) {
+        self.functions.push(def.name.clone());
+        self.enclosing.push(def.name.name.clone());
+        self.enter_scope();
+        for param in &def.params {
+            self.declare(&param.name, BindingKind::Param(def.name.name.clone()));
+        }
+        self.visit_expression(&def.body);
+        self.exit_scope();
+        self.enclosing.pop();
+    }
+
+    fn visit_lambda(&mut self, lambda: &Lambda) {
+        self.enter_scope();
+        for param in &lambda.params {
+            self.declare(&param.name, BindingKind::LambdaParam);
+        }
+        self.visit_expression(&lambda.body);
+        self.exit_scope();
+    }
+}
@@ -0,0 +1,132 @@
+use std::collections::HashMap;
+
+/// Bloque básico: una etiqueta y sus instrucciones, la última de ellas un terminador.
+#[derive(Debug, Clone)]
+pub struct BasicBlock {
+    pub label: String,
+    pub instructions: Vec<String>,
+}
+
+impl BasicBlock {
+    pub fn new(label: &str) -> Self {
+        BasicBlock {
+            label: label.to_string(),
+            instructions: Vec::new(),
+        }
+    }
+}
+
+/// Función LLVM en construcción o terminada. El primer bloque es siempre `entry`.
+#[derive(Debug, Clone)]
+pub struct LlvmFunction {
+    pub name: String,
+    pub return_type: String,
+    /// Parámetros como pares (tipo LLVM, nombre con `%`)
+    pub params: Vec<(String, String)>,
+    pub blocks: Vec<BasicBlock>,
+    allocas: usize,
+    local_names: HashMap<String, usize>,
+}
+
+impl LlvmFunction {
+    pub fn new(name: &str, return_type: &str, params: Vec<(String, String)>) -> Self {
+        LlvmFunction {
+            name: name.to_string(),
+            return_type: return_type.to_string(),
+            params,
+            blocks: vec![BasicBlock::new("entry")],
+            allocas: 0,
+            local_names: HashMap::new(),
+        }
+    }
+
+    /// Bloque en el que se está emitiendo código.
+    pub fn current_block(&self) -> &BasicBlock {
+        self.blocks.last().unwrap()
+    }
+
+    pub fn push(&mut self, instruction: String) {
+        self.blocks
+            .last_mut()
+            .unwrap()
+            .instructions
+            .push(instruction);
+    }
+
+    pub fn start_block(&mut self, label: &str) {
+        self.blocks.push(BasicBlock::new(label));
+    }
+
+    /// Reserva una variable local con un nombre único dentro de la función
+    /// (`x.0`, `x.1`, ...) y devuelve ese nombre sin el `%`. El `alloca` va al
+    /// inicio del bloque de entrada para que los bucles no hagan crecer la pila.
+    pub fn alloca(&mut self, name: &str, llvm_type: &str) -> String {
+        let count = self.local_names.entry(name.to_string()).or_insert(0);
+        let unique_var = format!("{}.{}", name, count);
+        *count += 1;
+        self.blocks[0].instructions.insert(
+            self.allocas,
+            format!("%{} = alloca {}", unique_var, llvm_type),
+        );
+        self.allocas += 1;
+        unique_var
+    }
+
+    pub fn to_ir_string(&self) -> String {
+        let params = self
+            .params
+            .iter()
+            .map(|(ty, name)| format!("{} {}", ty, name))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let mut out = format!(
+            "define {} @{}({}) {{\n",
+            self.return_type, self.name, params
+        );
+        for block in &self.blocks {
+            out.push_str(&format!("{}:\n", block.label));
+            for instruction in &block.instructions {
+                out.push_str(&format!("  {}\n", instruction));
+            }
+        }
+        out.push_str("}\n");
+        out
+    }
+}
+
+/// Módulo LLVM completo: globales, declaraciones externas y funciones.
+#[derive(Debug, Clone, Default)]
+pub struct LlvmModule {
+    pub globals: Vec<String>,
+    pub declarations: Vec<String>,
+    pub functions: Vec<LlvmFunction>,
+}
+
+impl LlvmModule {
+    pub fn new() -> Self {
+        LlvmModule::default()
+    }
+
+    pub fn function(&self, name: &str) -> Option<&LlvmFunction> {
+        self.functions.iter().find(|f| f.name == name)
+    }
+
+    /// Texto `.ll` del módulo, listo para `llc`/`clang`.
+    pub fn to_ir_string(&self) -> String {
+        let mut out = String::new();
+        for global in &self.globals {
+            out.push_str(global);
+            out.push('\n');
+        }
+        out.push('\n');
+        for declaration in &self.declarations {
+            out.push_str(declaration);
+            out.push('\n');
+        }
+        for function in &self.functions {
+            out.push('\n');
+            out.push_str(&function.to_ir_string());
+        }
+        out
+    }
+}
@@ -1,683 +1,545 @@
-use super::symbol_table::{SymbolInfo, SymbolTable};
-use super::types::Type;
-use crate::ast::atoms::atom::Atom;
-use crate::ast::expressions::binoperation::BinaryOp;
-use crate::ast::expressions::expressions::Expression;
-use crate::ast::visitor::visitor::Visitor;
-use crate::ast::{ExpressionList, Program};
-use crate::tokens::Literal;
-use crate::{Visitable, whilee};
-use std::collections::HashMap;
-
-pub struct LLVMGenerator {
-    pub code: Vec<String>,
-    pub functions: Vec<String>,
-    pub temp_count: usize,
-    pub last_temp: String,
-    pub string_globals: Vec<String>,
-    pub env_stack: Vec<HashMap<String, String>>,
-    pub string_sizes: HashMap<String, usize>,
-    pub string_label_count: usize,
-    pub symbol_table: SymbolTable,
+use super::llvm_module::{LlvmFunction, LlvmModule};
+use crate::mir::{
+    self, BinaryOp, Const, Function, FunctionId, InstKind, Operand, Terminator, Ty, UnaryOp,
+};
+use hulk_runtime::TypeTag;
+use std::collections::{HashMap, HashSet};
+
+/// Traducción del MIR a LLVM IR. Cada bloque, `phi` e instrucción del MIR
+/// tiene su equivalente directo: `bbN` es el bloque `N` (el primero es
+/// `entry`), `%vN` el valor `N` y cada variable un `alloca` en la entrada.
+///
+/// Una clausura es un struct cuyo primer campo es el puntero a la función y
+/// los siguientes sus capturas; se maneja como `i8*`. Se reserva con
+/// `hulk_gc_alloc`, del recolector del runtime, que encuentra las raíces en
+/// la pila sombra que arma `GcFrame`.
+///
+/// Todo lo que no es aritmética simple o control de flujo (imprimir,
+/// comparar strings, dividir, reservar memoria) es una llamada al crate
+/// `hulk_runtime`, que el compilador enlaza con el ejecutable.
+pub struct LLVMGenerator<'a> {
+    pub mir: &'a mir::Module,
+    /// El programa imprime al terminar las estadísticas del recolector
+    pub gc_stats: bool,
+    /// Módulo generado: globales, declaraciones y funciones ya terminadas
+    pub module: LlvmModule,
+    /// Global de cada string constante ya emitido, con su largo con el `\0`
+    strings: HashMap<String, (String, usize)>,
+    /// Temporales auxiliares de la función en curso (`%tN`), para las
+    /// instrucciones del MIR que ocupan más de una instrucción LLVM
+    temp_count: usize,
+    /// Funciones cuyo struct de clausura ya se declaró
+    closure_types: HashSet<FunctionId>,
 }
 
-impl LLVMGenerator {
-    pub fn new(symbol_table: SymbolTable) -> Self {
+impl<'a> LLVMGenerator<'a> {
+    pub fn new(mir: &'a mir::Module) -> Self {
+        let mut module = LlvmModule::new();
+        module.declarations = vec![
+            "declare void @hulk_runtime_init(i32)".to_string(),
+            "declare void @hulk_runtime_finish()".to_string(),
+            "declare void @hulk_print_int(i32)".to_string(),
+            "declare void @hulk_print_bool(i1 zeroext)".to_string(),
+            "declare void @hulk_print_str(i8*)".to_string(),
+            "declare i32 @hulk_str_cmp(i8*, i8*)".to_string(),
+            "declare i32 @hulk_div(i32, i32)".to_string(),
+            "declare i32 @hulk_rem(i32, i32)".to_string(),
+            "declare void @hulk_gc_push_frame(i8*)".to_string(),
+            "declare void @hulk_gc_pop_frame(i8*)".to_string(),
+            "declare i8* @hulk_gc_alloc(i32, i64, i64*, i64)".to_string(),
+        ];
         LLVMGenerator {
-            code: Vec::new(),
-            functions: Vec::new(),
+            mir,
+            gc_stats: false,
+            module,
+            strings: HashMap::new(),
             temp_count: 0,
-            last_temp: String::new(),
-            string_globals: Vec::new(),
-            env_stack: vec![HashMap::new()],
-            string_sizes: HashMap::new(),
-            string_label_count: 0,
-            symbol_table,
+            closure_types: HashSet::new(),
         }
     }
-    fn next_temp(&mut self) -> String {
-        let t = format!("%t{}", self.temp_count);
-        self.temp_count += 1;
-        t
+
+    /// Genera el módulo completo de un programa ya bajado al MIR.
+    pub fn generate(mir: &mir::Module, gc_stats: bool) -> LlvmModule {
+        let mut generator = LLVMGenerator::new(mir);
+        generator.gc_stats = gc_stats;
+        for function in &mir.functions {
+            let function = generator.function(function);
+            generator.module.functions.push(function);
+        }
+        generator.module
     }
 
-    pub fn llvm_header() -> Vec<String> {
-        vec![
-            "@.fmt_int = private unnamed_addr constant [4 x i8] c\"%d\\0A\\00\"".to_string(),
-            "@.fmt_str = private unnamed_addr constant [4 x i8] c\"%s\\0A\\00\"".to_string(),
-            "@.true_str = private unnamed_addr constant [5 x i8] c\"true\\00\"".to_string(),
-            "@.false_str = private unnamed_addr constant [6 x i8] c\"false\\00\"".to_string(),
-            "declare i32 @printf(i8*, ...)".to_string(),
-            "".to_string(),
-            "define i32 @main() {".to_string(),
-        ]
+    /// Representación LLVM de un tipo del MIR.
+    pub fn llvm_type(ty: Ty) -> &'static str {
+        match ty {
+            Ty::Int => "i32",
+            Ty::Bool => "i1",
+            Ty::Ptr => "i8*",
+        }
     }
-    pub fn llvm_footer() -> Vec<String> {
-        vec!["  ret i32 0".to_string(), "}".to_string()]
+
+    fn next_temp(&mut self) -> String {
+        let temp = format!("%t{}", self.temp_count);
+        self.temp_count += 1;
+        temp
     }
 
-    fn lookup_var(&self, name: &str) -> Option<&String> {
-        for scope in self.env_stack.iter().rev() {
-            if let Some(ptr) = scope.get(name) {
-                return Some(ptr);
-            }
+    fn label(block: mir::BlockId) -> String {
+        if block == 0 {
+            "entry".to_string()
+        } else {
+            format!("bb{}", block)
         }
-        None
     }
-}
 
-impl Visitor for LLVMGenerator {
-    fn visit_program(&mut self, program: &Program) {
-        program.expression_list.accept(self);
+    /// Puntero al primer carácter del string, como expresión constante.
+    fn string(&mut self, s: &str) -> String {
+        if !self.strings.contains_key(s) {
+            let label = format!("@.str_{}", self.strings.len());
+            let len = s.len() + 1;
+            self.module.globals.push(format!(
+                "{label} = private unnamed_addr constant [{len} x i8] c\"{s}\\00\"",
+                s = s.replace("\\", "\\5C").replace("\"", "\\22")
+            ));
+            self.strings.insert(s.to_string(), (label, len));
+        }
+        let (label, len) = &self.strings[s];
+        format!("getelementptr inbounds ([{len} x i8], [{len} x i8]* {label}, i32 0, i32 0)")
     }
 
-    fn visit_range(&mut self, _start: &crate::ast::Expression, _end: &crate::ast::Expression) {}
-
-    fn visit_function_call(&mut self, call: &crate::ast::expressions::functioncall::FunctionCall) {
-        let (ret_type, param_types) = match self.symbol_table.lookup(&call.funct_name.name) {
-            Some(SymbolInfo::Function {
-                return_type,
-                param_types,
-            }) => (return_type.clone(), param_types.clone()),
-            _ => panic!("'{}' no es una función", call.funct_name.name),
-        };
+    fn operand(&mut self, operand: &Operand) -> String {
+        match operand {
+            Operand::Value(id) => format!("%v{}", id),
+            Operand::Const(Const::Int(n)) => n.to_string(),
+            Operand::Const(Const::Bool(b)) => b.to_string(),
+            Operand::Const(Const::Str(s)) => self.string(s),
+            Operand::Const(Const::Null) => "null".to_string(),
+        }
+    }
 
-        // Evalúa los argumentos y guarda los temporales
-        let mut arg_temps = Vec::new();
-        for arg in &call.arguments {
-            arg.accept(self);
-            arg_temps.push(self.last_temp.clone());
+    /// Tipo del struct de las clausuras de `function`; lo declara la primera
+    /// vez, junto con el mapa de sus campos de puntero.
+    fn closure_type(&mut self, function: FunctionId) -> String {
+        let target = &self.mir.functions[function];
+        let name = format!("%{}.clausura", target.name);
+        if self.closure_types.insert(function) {
+            let fields = std::iter::once("i8*")
+                .chain(target.captures.iter().map(|&ty| Self::llvm_type(ty)))
+                .collect::<Vec<_>>()
+                .join(", ");
+            self.module
+                .globals
+                .push(format!("{} = type {{ {} }}", name, fields));
+            // El primer campo apunta al código, no a un objeto
+            let offsets = Self::pointer_captures(target)
+                .map(|field| {
+                    format!(
+                        "i64 ptrtoint (i8** getelementptr ({name}, {name}* null, i32 0, i32 {}) to i64)",
+                        field
+                    )
+                })
+                .collect::<Vec<_>>();
+            if !offsets.is_empty() {
+                self.module.globals.push(format!(
+                    "@{}.clausura.punteros = private unnamed_addr constant [{} x i64] [{}]",
+                    target.name,
+                    offsets.len(),
+                    offsets.join(", ")
+                ));
+            }
         }
+        name
+    }
 
-        // Prepara la lista de argumentos para el call con el tipo correcto
-        let args_llvm = arg_temps
+    /// Campos del struct de clausura de `function` que guardan punteros.
+    fn pointer_captures(function: &Function) -> impl Iterator<Item = usize> + '_ {
+        function
+            .captures
             .iter()
-            .zip(param_types.iter())
-            .map(|(t, ty)| match ty {
-                Type::Number => format!("i32 {}", t),
-                Type::Boolean => format!("i1 {}", t),
-                Type::String => format!("i8* {}", t),
-                Type::Custom(_) => format!("i32 {}", t), // Ajusta según tu representación
-                _ => panic!("Tipo de argumento no soportado"),
-            })
-            .collect::<Vec<_>>()
-            .join(", ");
-
-        // Determina el tipo de retorno LLVM
-        let ret_llvm = match ret_type {
-            Type::Number => "i32",
-            Type::Boolean => "i1",
-            Type::String => "i8*",
-            Type::Custom(_) => "i32", // Ajusta según tu representación
-            _ => panic!("Tipo de retorno no soportado"),
-        };
+            .enumerate()
+            .filter(|(_, ty)| **ty == Ty::Ptr)
+            .map(|(index, _)| index + 1)
+    }
 
-        // Llama a la función y guarda el resultado en un temporal
-        let temp = self.next_temp();
-        self.code.push(format!(
-            "{temp} = call {ret_llvm} @{name}({args})",
-            temp = temp,
-            ret_llvm = ret_llvm,
-            name = call.funct_name.name,
-            args = args_llvm
-        ));
-        self.last_temp = temp.clone();
+    /// Tipo LLVM de un puntero a una función que devuelve `return_ty`.
+    fn function_pointer_type(return_ty: Ty, params: impl Iterator<Item = Ty>) -> String {
+        let params = params.map(Self::llvm_type).collect::<Vec<_>>().join(", ");
+        format!("{} ({})*", Self::llvm_type(return_ty), params)
     }
 
-    fn visit_function_def(
+    /// Puntero al campo `field` de una clausura de `function`.
+    fn closure_field(
         &mut self,
-        def: &crate::ast::expressions::functiondeclaration::FunctionDef,
-    ) {
-        let mut fn_code = Vec::new();
-        let fn_name = &def.name.name;
+        function: FunctionId,
+        closure: String,
+        field: usize,
+        lines: &mut Vec<String>,
+    ) -> String {
+        let ty = self.closure_type(function);
+        let (cast, field_ptr) = (self.next_temp(), self.next_temp());
+        lines.push(format!("{} = bitcast i8* {} to {}*", cast, closure, ty));
+        lines.push(format!(
+            "{} = getelementptr {ty}, {ty}* {}, i32 0, i32 {}",
+            field_ptr, cast, field
+        ));
+        field_ptr
+    }
 
-        let (ret_type, param_types) = match self.symbol_table.lookup(fn_name) {
-            Some(SymbolInfo::Function {
-                return_type,
-                param_types,
-            }) => (return_type.clone(), param_types.clone()),
-            _ => panic!(
-                "Función '{}' no encontrada en la tabla de símbolos",
-                fn_name
-            ),
-        };
+    /// Operando precedido de su tipo, como lo piden `store`, `call` y `ret`.
+    fn typed_operand(&mut self, function: &Function, operand: &Operand) -> String {
+        let ty = Self::llvm_type(function.operand_ty(operand));
+        format!("{} {}", ty, self.operand(operand))
+    }
 
-        let params_llvm = param_types
+    fn function(&mut self, function: &Function) -> LlvmFunction {
+        self.temp_count = 0;
+        let params = function
+            .params
             .iter()
             .enumerate()
-            .map(|(i, ty)| match ty {
-                Type::Number => format!("i32 %p{i}"),
-                Type::Boolean => format!("i1 %p{i}"),
-                Type::String => format!("i8* %p{i}"),
-                Type::Custom(_) => format!("i32 %p{i}"), // Ajusta si tienes structs
-                _ => panic!("Tipo de parámetro no soportado"),
-            })
-            .collect::<Vec<_>>()
-            .join(", ");
-
-        let ret_llvm = match ret_type {
-            Type::Number => "i32".to_string(),
-            Type::Boolean => "i1".to_string(),
-            Type::String => "i8*".to_string(),
-            Type::Custom(name) => format!("%{}*", name), // Ajusta si tienes structs
-            _ => panic!("Tipo de retorno no soportado"),
-        };
-
-        fn_code.push(format!(
-            "define {} @{}({}) {{",
-            ret_llvm, fn_name, params_llvm
-        ));
-
-        self.env_stack.push(HashMap::new());
-        for (i, param) in def.params.iter().enumerate() {
-            let unique_var = format!("{}_{}", param.name.name, self.env_stack.len());
-            let llvm_type = match &param.signature {
-                Type::Number => "i32",
-                Type::Boolean => "i1",
-                Type::String => "i8*",
-                Type::Custom(_) => "i32", // Ajusta si tienes structs
-                _ => panic!("Tipo de parámetro no soportado"),
-            };
-            fn_code.push(format!("%{unique_var} = alloca {llvm_type}"));
-            fn_code.push(format!(
-                "store {llvm_type} %p{i}, {llvm_type}* %{unique_var}"
+            .map(|(i, &ty)| (Self::llvm_type(ty).to_string(), format!("%v{}", i)))
+            .collect();
+        let mut out =
+            LlvmFunction::new(&function.name, Self::llvm_type(function.return_ty), params);
+        let is_main = function.name == mir::ENTRY;
+        if is_main {
+            out.push(format!(
+                "call void @hulk_runtime_init(i32 {})",
+                i32::from(self.gc_stats)
             ));
-            self.env_stack
-                .last_mut()
-                .unwrap()
-                .insert(param.name.name.clone(), format!("%{unique_var}"));
         }
-
-        // Guarda el código generado temporalmente
-        let old_code = std::mem::replace(&mut self.code, Vec::new());
-        def.body.accept(self);
-        fn_code.extend(self.code.drain(..));
-        fn_code.push(format!("ret {} {}", ret_llvm, self.last_temp));
-        self.env_stack.pop();
-        fn_code.push("}".to_string());
-        self.code = old_code;
-
-        self.functions.extend(fn_code);
-    }
-
-    fn visit_expression_list(&mut self, expr_list: &ExpressionList) {
-        for expr in &expr_list.expressions {
-            expr.accept(self);
+        let frame = GcFrame::new(function, &mut out);
+        let slots = function
+            .slots
+            .iter()
+            .enumerate()
+            .map(|(id, slot)| match frame.slot_root(id) {
+                Some(root) => root,
+                None => format!("%{}", out.alloca(&slot.name, Self::llvm_type(slot.ty))),
+            })
+            .collect::<Vec<_>>();
+        for (id, _) in function.params.iter().enumerate() {
+            frame.keep(id, &mut out);
         }
-    }
-
-    fn visit_expression(&mut self, expr: &Expression) {
-        expr.accept(self);
-    }
 
-    fn visit_atom(&mut self, atom: &Atom) {
-        match atom {
-            Atom::NumberLiteral(lit) => self.visit_literal(lit),
-            Atom::BooleanLiteral(lit) => self.visit_literal(lit),
-            Atom::StringLiteral(lit) => self.visit_literal(lit),
-            Atom::Variable(identifier) => {
-                let ptr = self
-                    .lookup_var(&identifier.name)
-                    .unwrap_or_else(|| panic!("Variable {} not found in scope", identifier.name))
-                    .clone();
-                let temp = self.next_temp();
-
-                self.code.push(format!(
-                    "{temp} = load i32, i32* {ptr}",
-                    temp = temp,
-                    ptr = ptr
+        for (id, block) in function.blocks.iter().enumerate() {
+            if id > 0 {
+                out.start_block(&Self::label(id));
+            }
+            for phi in &block.phis {
+                let incoming = phi
+                    .incoming
+                    .iter()
+                    .map(|(pred, value)| {
+                        format!("[ {}, %{} ]", self.operand(value), Self::label(*pred))
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                out.push(format!(
+                    "%v{} = phi {} {}",
+                    phi.dest,
+                    Self::llvm_type(function.values[phi.dest]),
+                    incoming
                 ));
-                self.last_temp = temp;
             }
-            Atom::Group(group) => {
-                group.accept(self);
+            for phi in &block.phis {
+                frame.keep(phi.dest, &mut out);
+            }
+            for inst in &block.insts {
+                for line in self.inst(function, &slots, inst.dest, &inst.kind) {
+                    out.push(line);
+                }
+                if let Some(dest) = inst.dest {
+                    frame.keep(dest, &mut out);
+                }
             }
+            let terminator = match &block.terminator {
+                Some(Terminator::Jump(target)) => format!("br label %{}", Self::label(*target)),
+                Some(Terminator::Branch(cond, then_block, else_block)) => format!(
+                    "br i1 {}, label %{}, label %{}",
+                    self.operand(cond),
+                    Self::label(*then_block),
+                    Self::label(*else_block)
+                ),
+                Some(Terminator::Return(value)) => {
+                    frame.pop(&mut out);
+                    if is_main {
+                        out.push("call void @hulk_runtime_finish()".to_string());
+                    }
+                    format!("ret {}", self.typed_operand(function, value))
+                }
+                None => panic!("bb{} de {} no tiene terminador", id, function.name),
+            };
+            out.push(terminator);
         }
+        out
     }
 
-    fn visit_binary_op(&mut self, binop: &BinaryOp) {
-        use crate::tokens::BinOp;
-        match &binop.operator {
-            BinOp::Assign(_) => {
-                // Lado izquierdo debe ser una variable
-                if let Expression::Atom(atom) = &*binop.left {
-                    if let Atom::Variable(identifier) = &**atom {
-                        let ptr = self
-                            .lookup_var(&identifier.name)
-                            .unwrap_or_else(|| {
-                                panic!("Variable {} not found in scope", identifier.name)
-                            })
-                            .clone();
-                        binop.right.accept(self);
-                        let value = self.last_temp.clone();
-                        self.code.push(format!("store i32 {}, i32* {}", value, ptr));
-                        self.last_temp = value; // := devuelve el valor asignado
-                        return;
-                    }
-                }
-                panic!("Left side of := must be a variable");
+    /// Instrucciones LLVM de una instrucción del MIR. `dest` es el valor que
+    /// define, si define uno.
+    fn inst(
+        &mut self,
+        function: &Function,
+        slots: &[String],
+        dest: Option<mir::ValueId>,
+        kind: &InstKind,
+    ) -> Vec<String> {
+        let dest_id = || dest.expect("la instrucción debe definir un valor");
+        let dest = || format!("%v{}", dest_id());
+        match kind {
+            InstKind::Binary(op, lhs, rhs) => {
+                let ty = function.operand_ty(lhs);
+                let (lhs, rhs) = (self.operand(lhs), self.operand(rhs));
+                self.binary(dest(), *op, ty, lhs, rhs)
             }
-            // Operadores booleanos y de comparación
-            BinOp::EqualEqual(_)
-            | BinOp::NotEqual(_)
-            | BinOp::Less(_)
-            | BinOp::LessEqual(_)
-            | BinOp::Greater(_)
-            | BinOp::GreaterEqual(_) => {
-                binop.left.accept(self);
-                let left = self.last_temp.clone();
-                binop.right.accept(self);
-                let right = self.last_temp.clone();
-                let temp = self.next_temp();
-                let op = match &binop.operator {
-                    BinOp::EqualEqual(_) => "eq",
-                    BinOp::NotEqual(_) => "ne",
-                    BinOp::Less(_) => "slt",
-                    BinOp::LessEqual(_) => "sle",
-                    BinOp::Greater(_) => "sgt",
-                    BinOp::GreaterEqual(_) => "sge",
-                    _ => unreachable!(),
-                };
-                self.code.push(format!(
-                    "{temp} = icmp {op} i32 {left}, {right}",
-                    temp = temp,
-                    op = op,
-                    left = left,
-                    right = right
-                ));
-                self.last_temp = temp;
+            InstKind::Unary(UnaryOp::Neg, value) => {
+                vec![format!("{} = sub i32 0, {}", dest(), self.operand(value))]
+            }
+            InstKind::Unary(UnaryOp::Not, value) => {
+                vec![format!("{} = xor i1 {}, true", dest(), self.operand(value))]
             }
-            // Operadores lógicos (AND, OR)
-            BinOp::AndAnd(_) | BinOp::OrOr(_) => {
-                binop.left.accept(self);
-                let left = self.last_temp.clone();
-                binop.right.accept(self);
-                let right = self.last_temp.clone();
-                let temp = self.next_temp();
-                let op = match &binop.operator {
-                    BinOp::AndAnd(_) => "and",
-                    BinOp::OrOr(_) => "or",
-                    _ => unreachable!(),
+            InstKind::Load(slot) => {
+                let ty = Self::llvm_type(function.slots[*slot].ty);
+                vec![format!("{} = load {ty}, {ty}* {}", dest(), slots[*slot])]
+            }
+            InstKind::Store(slot, value) => {
+                let ty = Self::llvm_type(function.slots[*slot].ty);
+                vec![format!(
+                    "store {} {}, {ty}* {}",
+                    ty,
+                    self.operand(value),
+                    slots[*slot]
+                )]
+            }
+            InstKind::Call(callee, args) => {
+                let callee = &self.mir.functions[*callee];
+                let args = args
+                    .iter()
+                    .map(|arg| self.typed_operand(function, arg))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                vec![format!(
+                    "{} = call {} @{}({})",
+                    dest(),
+                    Self::llvm_type(callee.return_ty),
+                    callee.name,
+                    args
+                )]
+            }
+            InstKind::Print(value) => {
+                let ty = function.operand_ty(value);
+                let value = self.operand(value);
+                Self::print(ty, value)
+            }
+            InstKind::MakeClosure(target) => {
+                let ty = self.closure_type(*target);
+                let (end, size) = (self.next_temp(), self.next_temp());
+                let callee = &self.mir.functions[*target];
+                let pointer =
+                    Self::function_pointer_type(callee.return_ty, callee.params.iter().copied());
+                let pointers = Self::pointer_captures(callee).count();
+                let map = match pointers {
+                    0 => "i64* null".to_string(),
+                    n => format!(
+                        "i64* getelementptr inbounds ([{n} x i64], [{n} x i64]* @{}.clausura.punteros, i32 0, i32 0)",
+                        callee.name
+                    ),
                 };
-                self.code.push(format!(
-                    "{temp} = {op} i1 {left}, {right}",
-                    temp = temp,
-                    op = op,
-                    left = left,
-                    right = right
+                let mut lines = vec![
+                    // Tamaño del struct: dirección del segundo elemento de un arreglo en 0
+                    format!("{} = getelementptr {ty}, {ty}* null, i32 1", end),
+                    format!("{} = ptrtoint {}* {} to i64", size, ty, end),
+                    format!(
+                        "{} = call i8* @hulk_gc_alloc(i32 {}, i64 {}, {}, i64 {})",
+                        dest(),
+                        TypeTag::Closure as i32,
+                        size,
+                        map,
+                        pointers
+                    ),
+                ];
+                let code = self.closure_field(*target, dest(), 0, &mut lines);
+                lines.push(format!(
+                    "store i8* bitcast ({} @{} to i8*), i8** {}",
+                    pointer, callee.name, code
                 ));
-                self.last_temp = temp;
+                lines
             }
-            // Operadores aritméticos
-            _ => {
-                binop.left.accept(self);
-                let left = self.last_temp.clone();
-                binop.right.accept(self);
-                let right = self.last_temp.clone();
-                let temp = self.next_temp();
-                let op = match &binop.operator {
-                    BinOp::Plus(_) => "add",
-                    BinOp::Minus(_) => "sub",
-                    BinOp::Mul(_) => "mul",
-                    BinOp::Div(_) => "sdiv",
-                    BinOp::Mod(_) => "srem",
-                    _ => "add",
-                };
-                self.code.push(format!("{temp} = {op} i32 {left}, {right}"));
-                self.last_temp = temp;
+            InstKind::SetCapture(closure, target, index, value) => {
+                let ty = Self::llvm_type(function.operand_ty(value));
+                let (closure, value) = (self.operand(closure), self.operand(value));
+                let mut lines = Vec::new();
+                let field = self.closure_field(*target, closure, index + 1, &mut lines);
+                lines.push(format!("store {ty} {}, {ty}* {}", value, field));
+                lines
+            }
+            InstKind::GetCapture(closure, target, index) => {
+                let ty = Self::llvm_type(self.mir.functions[*target].captures[*index]);
+                let closure = self.operand(closure);
+                let mut lines = Vec::new();
+                let field = self.closure_field(*target, closure, index + 1, &mut lines);
+                lines.push(format!("{} = load {ty}, {ty}* {}", dest(), field));
+                lines
+            }
+            // La función se lee del primer campo y recibe la clausura antes
+            // que los argumentos
+            InstKind::CallClosure(closure, args) => {
+                let return_ty = function.values[dest_id()];
+                let closure = self.operand(closure);
+                let pointer = Self::function_pointer_type(
+                    return_ty,
+                    std::iter::once(Ty::Ptr).chain(args.iter().map(|arg| function.operand_ty(arg))),
+                );
+                let args = std::iter::once(format!("i8* {}", closure))
+                    .chain(args.iter().map(|arg| self.typed_operand(function, arg)))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let (code_ptr, code, callee) =
+                    (self.next_temp(), self.next_temp(), self.next_temp());
+                vec![
+                    format!("{} = bitcast i8* {} to i8**", code_ptr, closure),
+                    format!("{} = load i8*, i8** {}", code, code_ptr),
+                    format!("{} = bitcast i8* {} to {}", callee, code, pointer),
+                    format!(
+                        "{} = call {} {}({})",
+                        dest(),
+                        Self::llvm_type(return_ty),
+                        callee,
+                        args
+                    ),
+                ]
             }
         }
     }
 
-    fn visit_for(&mut self, forr: &crate::forr::For) {
-        use crate::ast::atoms::atom::Atom;
-        use crate::ast::expressions::expressions::Expression;
-
-        // Extrae el nombre de la variable de control
-        let var_name = if let Expression::Atom(atom) = &*forr.var {
-            if let Atom::Variable(identifier) = &**atom {
-                &identifier.name
-            } else {
-                panic!("For variable must be an identifier");
+    fn binary(
+        &mut self,
+        dest: String,
+        op: BinaryOp,
+        ty: Ty,
+        lhs: String,
+        rhs: String,
+    ) -> Vec<String> {
+        let llvm_op = match op {
+            BinaryOp::Add => "add",
+            BinaryOp::Sub => "sub",
+            BinaryOp::Mul => "mul",
+            // El runtime corta el programa si el divisor es cero
+            BinaryOp::Div | BinaryOp::Rem => {
+                let callee = match op {
+                    BinaryOp::Div => "hulk_div",
+                    _ => "hulk_rem",
+                };
+                return vec![format!(
+                    "{} = call i32 @{}(i32 {}, i32 {})",
+                    dest, callee, lhs, rhs
+                )];
             }
-        } else {
-            panic!("For variable must be an identifier expression");
+            BinaryOp::And => "and",
+            BinaryOp::Or => "or",
+            BinaryOp::Eq => "icmp eq",
+            BinaryOp::Ne => "icmp ne",
+            BinaryOp::Lt => "icmp slt",
+            BinaryOp::Le => "icmp sle",
+            BinaryOp::Gt => "icmp sgt",
+            BinaryOp::Ge => "icmp sge",
         };
-
-        // Crea variable local para el for (scope actual)
-        let scope_depth = self.env_stack.len();
-        let unique_var = format!("{}_{}", var_name, scope_depth);
-        self.code.push(format!("%{} = alloca i32", unique_var));
-
-        // Inicializa variable (asume que el iterable es un rango: range(start, end))
-        // Evaluamos el start
-        if let Expression::Range(start, end) = &*forr.iterable {
-            start.accept(self);
-            let start_temp = self.last_temp.clone();
-            self.code
-                .push(format!("store i32 {}, i32* %{}", start_temp, unique_var));
-            // Evaluamos el end
-            end.accept(self);
-            let end_temp = self.last_temp.clone();
-
-            // Etiquetas
-            let loop_cond = self.next_temp();
-            let loop_body = self.next_temp();
-            let loop_exit = self.next_temp();
-
-            // Guardamos el puntero en el scope
-            self.env_stack
-                .last_mut()
-                .unwrap()
-                .insert(var_name.to_string(), format!("%{}", unique_var));
-
-            // Salto a condición
-            self.code
-                .push(format!("br label %{cond}", cond = &loop_cond[1..]));
-
-            // Condición
-            self.code.push(format!("{}:", &loop_cond[1..]));
-            let x_val = self.next_temp();
-            self.code.push(format!(
-                "{x_val} = load i32, i32* %{var}",
-                x_val = x_val,
-                var = unique_var
-            ));
+        if op.is_comparison() && ty == Ty::Ptr {
+            // Los strings se comparan por contenido
             let cmp = self.next_temp();
-            self.code.push(format!(
-                "{cmp} = icmp slt i32 {x_val}, {end}",
-                cmp = cmp,
-                x_val = x_val,
-                end = end_temp
-            ));
-            self.code.push(format!(
-                "br i1 {cmp}, label %{body}, label %{exit}",
-                cmp = cmp,
-                body = &loop_body[1..],
-                exit = &loop_exit[1..]
-            ));
-
-            // Cuerpo
-            self.code.push(format!("{}:", &loop_body[1..]));
-            forr.body.accept(self);
-
-            // Incremento
-            let x_val2 = self.next_temp();
-            self.code.push(format!(
-                "{x_val2} = load i32, i32* %{var}",
-                x_val2 = x_val2,
-                var = unique_var
-            ));
-            let inc = self.next_temp();
-            self.code.push(format!(
-                "{inc} = add i32 {x_val2}, 1",
-                inc = inc,
-                x_val2 = x_val2
-            ));
-            self.code.push(format!(
-                "store i32 {inc}, i32* %{var}",
-                inc = inc,
-                var = unique_var
-            ));
-            self.code
-                .push(format!("br label %{cond}", cond = &loop_cond[1..]));
-
-            // Exit
-            self.code.push(format!("{}:", &loop_exit[1..]));
-        } else {
-            panic!("For iterable must be a range expression");
-        }
-    }
-
-    fn visit_letin(&mut self, letin: &crate::ast::expressions::letin::LetIn) {
-        self.env_stack.push(HashMap::new()); // Nuevo scope
-
-        for assign in &letin.bindings {
-            let var_name = match &assign.variable {
-                Atom::Variable(identifier) => &identifier.name,
-                _ => panic!("Expected variable in assignment"),
-            };
-            let scope_depth = self.env_stack.len();
-            let unique_var = format!("{}_{}", var_name, scope_depth);
-            self.code.push(format!("%{} = alloca i32", unique_var));
-            assign.body.accept(self);
-            self.code.push(format!(
-                "store i32 {}, i32* %{}",
-                self.last_temp, unique_var
-            ));
-            // Guarda el puntero en el scope actual
-            self.env_stack
-                .last_mut()
-                .unwrap()
-                .insert(var_name.clone(), format!("%{}", unique_var));
+            return vec![
+                format!("{} = call i32 @hulk_str_cmp(i8* {}, i8* {})", cmp, lhs, rhs),
+                format!("{} = {} i32 {}, 0", dest, llvm_op, cmp),
+            ];
         }
-
-        letin.body.accept(self);
-
-        self.env_stack.pop(); // Sale del scope
-    }
-
-    fn visit_assignment(&mut self, _assign: &crate::ast::expressions::letin::Assignment) {}
-
-    fn visit_block(&mut self, block: &crate::ast::expressions::block::Block) {
-        self.env_stack.push(HashMap::new()); // Nuevo scope
-
-        block.expression_list.accept(self);
-
-        self.env_stack.pop(); // Sale del scope
+        let ty = if op.is_logical() {
+            "i1"
+        } else {
+            Self::llvm_type(ty)
+        };
+        vec![format!("{} = {} {} {}, {}", dest, llvm_op, ty, lhs, rhs)]
     }
 
-    fn visit_literal(&mut self, literal: &Literal) {
-        let temp = self.next_temp();
-        match literal {
-            Literal::Number(n, _) => {
-                self.code.push(format!("{temp} = add i32 0, {n}"));
-                self.last_temp = temp;
-            }
-            Literal::Bool(val, _) => {
-                let bool_val = if *val { 1 } else { 0 };
-                self.code.push(format!(
-                    "{temp} = icmp eq i1 {bool_val}, 1",
-                    temp = temp,
-                    bool_val = bool_val
-                ));
-                self.last_temp = temp;
-            }
-            Literal::Str(s, _) => {
-                let label = format!("@.str_{}", self.string_label_count);
-                let bytes = s.as_bytes();
-                let len = bytes.len() + 1;
-                self.string_globals.push(format!(
-                    "{label} = private unnamed_addr constant [{len} x i8] c\"{s}\\00\"",
-                    label = label,
-                    len = len,
-                    s = s.replace("\\", "\\5C").replace("\"", "\\22")
-                ));
-                self.string_sizes.insert(label.clone(), len);
-                self.last_temp = label;
-                self.string_label_count += 1;
-            }
-        }
+    fn print(ty: Ty, value: String) -> Vec<String> {
+        let line = match ty {
+            Ty::Int => format!("call void @hulk_print_int(i32 {})", value),
+            Ty::Bool => format!("call void @hulk_print_bool(i1 zeroext {})", value),
+            Ty::Ptr => format!("call void @hulk_print_str(i8* {})", value),
+        };
+        vec![line]
     }
+}
 
-    fn visit_identifier(&mut self, _identifier: &crate::tokens::Identifier) {}
-
-    fn visit_print(&mut self, expr: &Expression) {
-        use crate::ast::atoms::atom::Atom;
-        expr.accept(self);
+/// Marco de la pila sombra de una función: un `alloca` con la forma
+/// `{ anterior, cantidad, [raíces] }` que espera el runtime. Tiene una raíz
+/// por cada variable de puntero, que vive ahí en lugar de en su propio
+/// `alloca`, y una por cada valor de puntero, que se guarda al definirse.
+/// Un valor queda vivo hasta que la función vuelve o se lo redefine.
+struct GcFrame {
+    /// Marco como `i8*`, o `None` si la función no maneja punteros
+    frame: Option<String>,
+    /// Puntero a la raíz de cada variable y de cada valor
+    slot_roots: HashMap<mir::SlotId, String>,
+    value_roots: HashMap<mir::ValueId, String>,
+}
 
-        if let Expression::Atom(atom) = expr {
-            if let Atom::StringLiteral(_) = &**atom {
-                let label = self.last_temp.clone();
-                let len = *self.string_sizes.get(&label).unwrap_or(&0);
-                let fmt_ptr = self.next_temp();
-                let str_ptr = self.next_temp();
-                self.code.push(format!(
-                    "{fmt_ptr} = getelementptr [4 x i8], [4 x i8]* @.fmt_str, i32 0, i32 0",
-                    fmt_ptr = fmt_ptr
-                ));
-                self.code.push(format!(
-                    "{str_ptr} = getelementptr [{len} x i8], [{len} x i8]* {label}, i32 0, i32 0",
-                    str_ptr = str_ptr,
-                    len = len,
-                    label = label
-                ));
-                self.code.push(format!(
-                    "call i32 (i8*, ...) @printf(i8* {fmt_ptr}, i8* {str_ptr})",
-                    fmt_ptr = fmt_ptr,
-                    str_ptr = str_ptr
-                ));
-                return;
-            }
-            if let Atom::BooleanLiteral(_) = &**atom {
-                // Imprime como "true"/"false"
-                let bool_temp = self.last_temp.clone();
-                let true_ptr = self.next_temp();
-                let false_ptr = self.next_temp();
-                let result_ptr = self.next_temp();
-                self.code.push(format!(
-                "{true_ptr} = getelementptr inbounds [5 x i8], [5 x i8]* @.true_str, i32 0, i32 0"
-            ));
-                self.code.push(format!(
-                "{false_ptr} = getelementptr inbounds [6 x i8], [6 x i8]* @.false_str, i32 0, i32 0"
-            ));
-                self.code.push(format!(
-                    "{result_ptr} = select i1 {cond}, i8* {true_ptr}, i8* {false_ptr}",
-                    result_ptr = result_ptr,
-                    cond = bool_temp,
-                    true_ptr = true_ptr,
-                    false_ptr = false_ptr
-                ));
-                let fmt_ptr = self.next_temp();
-                self.code.push(format!(
-                    "{fmt_ptr} = getelementptr [4 x i8], [4 x i8]* @.fmt_str, i32 0, i32 0",
-                    fmt_ptr = fmt_ptr
-                ));
-                self.code.push(format!(
-                    "call i32 (i8*, ...) @printf(i8* {fmt_ptr}, i8* {result_ptr})",
-                    fmt_ptr = fmt_ptr,
-                    result_ptr = result_ptr
-                ));
-                return;
-            }
+impl GcFrame {
+    /// Reserva el marco, con todas las raíces en `null`, y lo apila.
+    fn new(function: &Function, out: &mut LlvmFunction) -> GcFrame {
+        let pointer_slots = (0..function.slots.len())
+            .filter(|&id| function.slots[id].ty == Ty::Ptr)
+            .collect::<Vec<_>>();
+        let pointer_values = (0..function.values.len())
+            .filter(|&id| function.values[id] == Ty::Ptr)
+            .collect::<Vec<_>>();
+        let count = pointer_slots.len() + pointer_values.len();
+        let mut frame = GcFrame {
+            frame: None,
+            slot_roots: HashMap::new(),
+            value_roots: HashMap::new(),
+        };
+        if count == 0 {
+            return frame;
         }
-        self.code.push(format!(
-            "call i32 (i8*, ...) @printf(i8* getelementptr inbounds ([4 x i8], [4 x i8]* @.fmt_int, i32 0, i32 0), i32 {})",
-            self.last_temp
+        let ty = format!("{{ i8*, i64, [{} x i8*] }}", count);
+        let marco = format!("%{}", out.alloca("gc.marco", &ty));
+        out.push(format!(
+            "store {ty} {{ i8* null, i64 {count}, [{count} x i8*] zeroinitializer }}, {ty}* {marco}"
         ));
+        let roots = (0..count)
+            .map(|index| {
+                let root = format!("%gc.raiz{}", index);
+                out.push(format!(
+                    "{root} = getelementptr {ty}, {ty}* {marco}, i32 0, i32 2, i32 {index}"
+                ));
+                root
+            })
+            .collect::<Vec<_>>();
+        let mut roots = roots.into_iter();
+        frame.slot_roots = pointer_slots.into_iter().zip(roots.by_ref()).collect();
+        frame.value_roots = pointer_values.into_iter().zip(roots).collect();
+        out.push(format!("%gc.marco = bitcast {ty}* {marco} to i8*"));
+        out.push("call void @hulk_gc_push_frame(i8* %gc.marco)".to_string());
+        frame.frame = Some("%gc.marco".to_string());
+        frame
     }
 
-    fn visit_while(&mut self, whilee: &whilee::While) {
-        let cond_label = self.next_temp();
-        let body_label = self.next_temp();
-        let end_label = self.next_temp();
-
-        // Salto a la condición
-        self.code
-            .push(format!("br label %{cond}", cond = &cond_label[1..]));
-
-        // Etiqueta de condición
-        self.code.push(format!("{}:", &cond_label[1..]));
-        whilee.cond.accept(self);
-        let cond_temp = self.last_temp.clone();
-        self.code.push(format!(
-            "br i1 {cond}, label %{body}, label %{end}",
-            cond = cond_temp,
-            body = &body_label[1..],
-            end = &end_label[1..]
-        ));
-
-        // Etiqueta de cuerpo
-        self.code.push(format!("{}:", &body_label[1..]));
-        whilee.body.accept(self);
-        // Al terminar el cuerpo, vuelve a la condición
-        self.code
-            .push(format!("br label %{cond}", cond = &cond_label[1..]));
-
-        // Etiqueta de fin
-        self.code.push(format!("{}:", &end_label[1..]));
-    }
-
-    fn visit_ifelse(&mut self, ifelse: &crate::ast::expressions::ifelse::IfElse) {
-        // Genera etiquetas únicas para cada bloque
-        let then_label = format!("then{}", self.temp_count);
-        let else_label = format!("else{}", self.temp_count);
-        let merge_label = format!("merge{}", self.temp_count);
-        self.temp_count += 1;
-
-        // Evalúa la condición principal
-        ifelse.condition.accept(self);
-        let cond_temp = self.last_temp.clone();
-
-        // Salto condicional
-        self.code.push(format!(
-            "br i1 {}, label %{}, label %{}",
-            cond_temp, then_label, else_label
-        ));
-
-        // THEN branch
-        self.code.push(format!("{}:", then_label));
-        ifelse.then_branch.accept(self);
-        let then_result = self.last_temp.clone();
-        self.code.push(format!("br label %{}", merge_label));
-
-        // ELSE branch (puede ser otro ifelse, o un bloque, o nada)
-        self.code.push(format!("{}:", else_label));
-        let else_result = if let Some(else_branch) = &ifelse.else_branch {
-            // Si el else es otro IfElse (elif), lo procesamos recursivamente
-            if let Expression::IfElse(elif) = &**else_branch {
-                self.visit_ifelse(elif);
-                self.last_temp.clone()
-            } else {
-                // Es un bloque normal
-                else_branch.accept(self);
-                self.last_temp.clone()
-            }
-        } else {
-            // Si no hay else, valor por defecto
-            let zero_temp = self.next_temp();
-            self.code.push(format!("{} = add i32 0, 0", zero_temp));
-            zero_temp
-        };
-        self.code.push(format!("br label %{}", merge_label));
-
-        // MERGE
-        self.code.push(format!("{}:", merge_label));
-        let phi_temp = self.next_temp();
-        self.code.push(format!(
-            "{} = phi i32 [ {}, %{} ], [ {}, %{} ]",
-            phi_temp, then_result, then_label, else_result, else_label
-        ));
-        self.last_temp = phi_temp;
+    /// Raíz donde vive la variable `slot`, si es un puntero.
+    fn slot_root(&self, slot: mir::SlotId) -> Option<String> {
+        self.slot_roots.get(&slot).cloned()
     }
 
-    fn visit_group(&mut self, group: &crate::ast::atoms::group::Group) {
-        group.expression.accept(self);
+    /// Guarda el valor en su raíz, si es un puntero.
+    fn keep(&self, value: mir::ValueId, out: &mut LlvmFunction) {
+        if let Some(root) = self.value_roots.get(&value) {
+            out.push(format!("store i8* %v{}, i8** {}", value, root));
+        }
     }
 
-    fn visit_unary_op(&mut self, unary_op: &crate::ast::expressions::unaryoperation::UnaryOp) {
-        unary_op.expr.accept(self);
-        let expr_temp = self.last_temp.clone();
-        let temp = self.next_temp();
-        match unary_op.op {
-            crate::tokens::UnaryOp::Minus(_) => {
-                self.code.push(format!(
-                    "{temp} = sub i32 0, {expr}",
-                    temp = temp,
-                    expr = expr_temp
-                ));
-            }
-            crate::tokens::UnaryOp::Not(_) => {
-                self.code.push(format!(
-                    "{temp} = xor i1 {expr}, true",
-                    temp = temp,
-                    expr = expr_temp
-                ));
-            }
-            _ => {
-                panic!("Unsupported unary operation: {:?}", unary_op.op);
-            }
+    /// Desapila el marco antes de volver.
+    fn pop(&self, out: &mut LlvmFunction) {
+        if let Some(frame) = &self.frame {
+            out.push(format!("call void @hulk_gc_pop_frame(i8* {})", frame));
         }
-        self.last_temp = temp;
     }
 }
@@ -1,17 +1,35 @@
 pub mod visitor;
 pub mod ast_printer_visitor;
 pub mod llvm_visitor;
+pub mod llvm_module;
 pub mod ast_optimizer;
 pub mod type_checker;
 pub mod types;
 pub mod symbol_table;
 pub mod semantic_type_checker;
+pub mod interpreter;
+pub mod bytecode_compiler;
+pub mod code_formatter;
+pub mod ast_serializer;
+pub mod folder;
+pub mod visitor_mut;
+pub mod value_visitor;
+pub mod linter;
 pub use visitor::Visitor;
 pub use visitor::Visitable;
 pub use ast_printer_visitor::AstPrinterVisitor;
 pub use llvm_visitor::LLVMGenerator;
+pub use llvm_module::{BasicBlock, LlvmFunction, LlvmModule};
 pub use ast_optimizer::AstOptimizer;
 pub use type_checker::TypeChecker;
 pub use types::Type;
 pub use symbol_table::SymbolTable;
 pub use semantic_type_checker::SemanticTypeChecker;
+pub use interpreter::{Interpreter, RuntimeError, Value};
+pub use bytecode_compiler::{BytecodeCompiler, CompiledProgram};
+pub use code_formatter::CodeFormatter;
+pub use ast_serializer::{AstChild, AstNode, AstSerializer, AstValue};
+pub use folder::Folder;
+pub use visitor_mut::VisitorMut;
+pub use value_visitor::ValueVisitor;
+pub use linter::Linter;
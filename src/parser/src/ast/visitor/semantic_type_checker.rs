@@ -1,13 +1,28 @@
 use super::symbol_table::{SymbolInfo, SymbolTable};
 use super::types::Type;
-use crate::ast::visitor::visitor::Visitor;
+use crate::ast::visitor::value_visitor::{ValueVisitor, walk_expression};
 use crate::ast::*;
+use crate::diagnostics::Diagnostic;
 use crate::tokens::*;
+use std::collections::HashMap;
+
+/// Tipo resuelto de cada expresión del AST, indexado por su `ExprId`. Sigue
+/// valiendo para copias del programa y para el que devuelve el optimizador.
+pub type TypeAnnotations = HashMap<ExprId, Type>;
 
 #[derive(Debug, Clone)]
 pub struct SemanticTypeChecker {
     pub symbol_table: SymbolTable,
     pub errors: Vec<String>,
+    /// Posición de cada error de `errors`, en el mismo orden
+    pub error_positions: Vec<Position>,
+    /// Nota sobre otro fragmento del código para cada error de `errors`,
+    /// como la declaración anterior de un nombre repetido
+    pub error_related: Vec<Option<(String, Position)>>,
+    pub expr_types: TypeAnnotations,
+    /// Scope de los parámetros de cada función o lambda que se está
+    /// chequeando, de afuera hacia adentro. Lo declarado antes es captura.
+    pub function_scopes: Vec<usize>,
 }
 
 impl SemanticTypeChecker {
@@ -15,261 +30,506 @@ impl SemanticTypeChecker {
         SemanticTypeChecker {
             symbol_table: SymbolTable::new(),
             errors: Vec::new(),
+            error_positions: Vec::new(),
+            error_related: Vec::new(),
+            expr_types: HashMap::new(),
+            function_scopes: Vec::new(),
         }
     }
 
     pub fn check(&mut self, program: &Program) {
-        program.accept(self);
+        self.visit_program(program);
     }
 
-    fn type_of_atom(&mut self, atom: &Atom) -> Type {
-        match atom {
-            Atom::NumberLiteral(_) => Type::Number,
-            Atom::BooleanLiteral(_) => Type::Boolean,
-            Atom::StringLiteral(_) => Type::String,
-            Atom::Variable(ident) => {
-                if let Some(SymbolInfo::Variable { var_type }) =
-                    self.symbol_table.lookup(&ident.name)
-                {
-                    var_type.clone()
-                } else {
-                    self.errors
-                        .push(format!("Variable '{}' no declarada", ident.name));
-                    Type::Unknown
+    fn error(&mut self, message: String, position: Position) {
+        self.errors.push(message);
+        self.error_positions.push(position);
+        self.error_related.push(None);
+    }
+
+    /// Error por un nombre ya declarado en el mismo scope, con una nota que
+    /// señala la declaración anterior si se conoce su posición.
+    fn redeclaration(&mut self, message: String, position: Position, previous: Option<Position>) {
+        self.error(message, position);
+        if let Some(previous) = previous {
+            *self.error_related.last_mut().unwrap() =
+                Some(("declarado antes aquí".to_string(), previous));
+        }
+    }
+
+    /// Los errores encontrados, como diagnósticos.
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        self.errors
+            .iter()
+            .zip(&self.error_positions)
+            .zip(&self.error_related)
+            .map(|((message, position), related)| {
+                let diagnostic = Diagnostic::error(message.clone()).at(*position);
+                match related {
+                    Some((note, previous)) => diagnostic.with_related(note.clone(), *previous),
+                    None => diagnostic,
                 }
-            }
-            _ => Type::Unknown,
+            })
+            .collect()
+    }
+
+    /// Tipo anotado para `expr` durante el chequeo (`Unknown` si no se visitó).
+    pub fn type_of(&self, expr: &Expression) -> Type {
+        self.expr_types
+            .get(&expr.id())
+            .cloned()
+            .unwrap_or(Type::Unknown)
+    }
+
+    /// Tipo de una expresión con varias ramas posibles (if/elif/else).
+    pub(crate) fn unify_types(types: Vec<Type>) -> Type {
+        let mut iter = types.into_iter();
+        let first = iter.next().unwrap_or(Type::Unknown);
+        iter.fold(first, |acc, ty| match (acc, ty) {
+            (Type::Unknown, other) | (other, Type::Unknown) => other,
+            (a, b) if a == b => a,
+            _ => Type::Object,
+        })
+    }
+
+    /// Chequea `expr` en un lugar donde se usa su valor. Un `if` cuyas
+    /// ramas tienen tipos distintos no tiene un valor que los backends
+    /// puedan representar: solo puede ir donde su valor se descarta.
+    fn value(&mut self, expr: &Expression) -> Type {
+        let ty = self.visit_expression(expr);
+        if let Some((position, then_ty, else_ty)) = self.mixed_if(expr) {
+            self.error(
+                format!(
+                    "Se usa el valor de un if cuyas ramas tienen tipos distintos ('{}' y '{}').",
+                    then_ty, else_ty
+                ),
+                position,
+            );
         }
+        ty
     }
 
-    fn infer_expr_type(&mut self, expr: &Expression) -> Type {
+    /// Primer `if` con ramas de tipos distintos cuyo valor es el de `expr`,
+    /// con los tipos de dos de sus ramas. Sin `else` la rama que falta no
+    /// cuenta: ese `if` vale lo de sus ramas o nada.
+    fn mixed_if(&self, expr: &Expression) -> Option<(Position, Type, Type)> {
         match expr {
-            Expression::Atom(atom) => self.type_of_atom(atom),
             Expression::IfElse(ifelse) => {
-                ifelse.condition.accept(self);
-                ifelse.then_branch.accept(self);
-                for (_elif_kw, condition, branch) in &ifelse.elif_branches {
-                    condition.accept(self);
-                    branch.accept(self);
-                }
-                if let Some(else_branch) = &ifelse.else_branch {
-                    else_branch.accept(self);
-                }
-                Type::Unknown // El tipo de IfElse puede ser más complejo
-            }
-            Expression::FunctionCall(call) => {
-                if let Some(SymbolInfo::Function { return_type, .. }) =
-                    self.symbol_table.lookup(&call.funct_name.name)
-                {
-                    return_type.clone()
-                } else {
-                    self.errors
-                        .push(format!("Función '{}' no declarada", call.funct_name.name));
-                    Type::Unknown
+                let branches: Vec<&Expression> = std::iter::once(&*ifelse.then_branch)
+                    .chain(ifelse.elif_branches.iter().map(|(_, _, branch)| branch))
+                    .chain(ifelse.else_branch.as_deref())
+                    .collect();
+                let known: Vec<Type> = branches
+                    .iter()
+                    .map(|branch| self.type_of(branch))
+                    .filter(|ty| *ty != Type::Unknown)
+                    .collect();
+                if let Some(other) = known.iter().find(|ty| **ty != known[0]) {
+                    return Some((expr.position(), known[0].clone(), other.clone()));
                 }
+                branches
+                    .into_iter()
+                    .find_map(|branch| self.mixed_if(branch))
             }
+            Expression::Block(block) => block
+                .expression_list
+                .expressions
+                .last()
+                .and_then(|last| self.mixed_if(last)),
+            Expression::LetIn(letin) => self.mixed_if(&letin.body),
+            Expression::While(whilee) => self.mixed_if(&whilee.body),
+            Expression::For(forr) => self.mixed_if(&forr.body),
+            Expression::Atom(atom) => match &**atom {
+                Atom::Group(group) => self.mixed_if(&group.expression),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
 
-            Expression::BinaryOp(binop) => {
-                let left_ty = self.infer_expr_type(&binop.left);
-                let right_ty = self.infer_expr_type(&binop.right);
-                use crate::tokens::BinOp;
-                match &binop.operator {
-                    BinOp::Plus(_)
-                    | BinOp::Minus(_)
-                    | BinOp::Mul(_)
-                    | BinOp::Div(_)
-                    | BinOp::Mod(_) => {
-                        if left_ty != Type::Number || right_ty != Type::Number {
-                            self.errors
-                                .push("Operación aritmética requiere números".to_string());
-                        }
-                        Type::Number
-                    }
-                    BinOp::EqualEqual(_)
-                    | BinOp::NotEqual(_)
-                    | BinOp::Greater(_)
-                    | BinOp::Less(_)
-                    | BinOp::GreaterEqual(_)
-                    | BinOp::LessEqual(_)
-                    | BinOp::AndAnd(_)
-                    | BinOp::OrOr(_) => {
-                        if left_ty != right_ty {
-                            self.errors
-                                .push("Comparación entre tipos incompatibles".to_string());
-                        }
-                        Type::Boolean
-                    }
-                    BinOp::ConcatString(_) => {
-                        if !(left_ty == Type::String || left_ty == Type::Number)
-                            || !(right_ty == Type::String || right_ty == Type::Number)
-                        {
-                            self.errors
-                                .push("Concatenación requiere string o número".to_string());
-                        }
-                        Type::String
-                    }
-                    _ => Type::Unknown,
-                }
+    /// Las clausuras capturan por copia: ven el valor de cada variable
+    /// exterior al crearse, y por eso no pueden asignarla. Un `:=` adentro
+    /// cambiaría solo la copia y afuera no se vería.
+    fn check_captured_assign(&mut self, target: &Expression) {
+        let Expression::Atom(atom) = target else {
+            return;
+        };
+        let (atoms::atom::Atom::Variable(ident), Some(&function)) =
+            (&**atom, self.function_scopes.last())
+        else {
+            return;
+        };
+        if let Some(scope) = self.symbol_table.scope_of(&ident.name)
+            && scope < function
+            && let Some(SymbolInfo::Variable { .. }) = self.symbol_table.lookup(&ident.name)
+        {
+            self.error(
+                format!(
+                    "No se puede asignar '{}' dentro de una función que la captura: la clausura guarda una copia.",
+                    ident.name
+                ),
+                ident.position,
+            );
+        }
+    }
+
+    /// Nombre de la variable de control de un `for`.
+    fn for_variable(forr: &forr::For) -> Option<&Identifier> {
+        match &*forr.var {
+            Expression::Atom(atom) => match &**atom {
+                Atom::Variable(ident) => Some(ident),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Reporta los extremos de un rango que no son números.
+    fn check_range_bounds(&mut self, start: &Expression, end: &Expression) {
+        for bound in [start, end] {
+            let ty = self.value(bound);
+            if ty != Type::Number && ty != Type::Unknown {
+                self.error(
+                    format!("Los extremos de un rango deben ser números, no '{}'.", ty),
+                    bound.position(),
+                );
             }
-            // Agrega aquí otros casos según tu AST
-            _ => Type::Unknown,
+        }
+    }
+
+    /// Reporta una condición de `if`, `elif` o `while` que no es booleana.
+    fn check_condition(&mut self, ty: Type, condition: &Expression, construct: &str) {
+        if ty != Type::Boolean {
+            self.error(
+                format!("Condición de {} debe ser booleana", construct),
+                condition.position(),
+            );
         }
     }
 }
 
-impl Visitor for SemanticTypeChecker {
-    fn visit_program(&mut self, program: &Program) {
-        program.expression_list.accept(self);
+impl ValueVisitor for SemanticTypeChecker {
+    type Output = Type;
+
+    fn visit_program(&mut self, program: &Program) -> Type {
+        self.visit_expression_list(&program.expression_list)
     }
 
-    fn visit_expression_list(&mut self, expr_list: &ExpressionList) {
+    /// Una lista vale lo que vale su última expresión.
+    fn visit_expression_list(&mut self, expr_list: &ExpressionList) -> Type {
+        let mut ty = Type::Unknown;
         for expr in &expr_list.expressions {
-            expr.accept(self);
+            ty = self.visit_expression(expr);
         }
+        ty
     }
 
-    fn visit_expression(&mut self, expr: &Expression) {
-        expr.accept(self);
+    /// Chequea `expr` y anota su tipo. Los hijos se recorren siempre por aquí
+    /// para que cada expresión del programa quede anotada.
+    fn visit_expression(&mut self, expr: &Expression) -> Type {
+        let ty = walk_expression(self, expr);
+        self.expr_types.insert(expr.id(), ty.clone());
+        ty
     }
 
-    fn visit_block(&mut self, block: &expressions::block::Block) {
+    fn visit_block(&mut self, block: &expressions::block::Block) -> Type {
         self.symbol_table.enter_scope();
-        block.expression_list.accept(self);
+        let ty = self.visit_expression_list(&block.expression_list);
         self.symbol_table.exit_scope();
+        ty
     }
 
-    fn visit_print(&mut self, expr: &Expression) {
-        expr.accept(self);
+    fn visit_print(&mut self, expr: &Expression) -> Type {
+        let ty = self.value(expr);
+        if let Type::Function(..) = ty {
+            self.error(
+                format!("No se puede imprimir una función de tipo '{}'.", ty),
+                expr.position(),
+            );
+        }
+        ty
     }
 
-    fn visit_literal(&mut self, _literal: &Literal) {
-        // match literal {
-        //     Literal::Number(_, _) => {}
-        //     Literal::Bool(_, _) => {}
-        //     Literal::Str(_, _) => {}
-        // }
+    fn visit_literal(&mut self, literal: &Literal) -> Type {
+        match literal {
+            Literal::Number(..) => Type::Number,
+            Literal::Bool(..) => Type::Boolean,
+            Literal::Str(..) => Type::String,
+        }
     }
 
-    fn visit_group(&mut self, group: &atoms::group::Group) {
-        group.expression.accept(self);
+    fn visit_identifier(&mut self, ident: &Identifier) -> Type {
+        match self.symbol_table.lookup(&ident.name) {
+            Some(SymbolInfo::Variable { var_type }) => var_type.clone(),
+            // Una función nombrada sin llamarla es un valor más
+            Some(SymbolInfo::Function {
+                return_type,
+                param_types,
+            }) => Type::Function(param_types.clone(), Box::new(return_type.clone())),
+            None => {
+                self.error(
+                    format!("Variable '{}' no declarada.", ident.name),
+                    ident.position,
+                );
+                Type::Unknown
+            }
+        }
     }
 
-    fn visit_for(&mut self, forr: &forr::For) {
-        forr.iterable.accept(self);
+    /// Un bucle vale lo que valió su última iteración.
+    fn visit_for(&mut self, forr: &forr::For) -> Type {
+        // El rango del for se chequea aquí: en cualquier otro lugar es un error
+        let iterable = match &*forr.iterable {
+            Expression::Range(start, end) => {
+                self.check_range_bounds(start, end);
+                Type::Range
+            }
+            iterable => self.visit_expression(iterable),
+        };
+        self.expr_types.insert(forr.iterable.id(), iterable.clone());
+        if iterable != Type::Range {
+            self.error(
+                format!(
+                    "El iterable de un for debe ser un rango, pero es de tipo '{}'.",
+                    iterable
+                ),
+                forr.iterable.position(),
+            );
+        }
         self.symbol_table.enter_scope();
-        forr.var.accept(self);
-        forr.body.accept(self);
+        // La variable de control recorre un rango de números
+        match Self::for_variable(forr) {
+            Some(ident) => self.symbol_table.insert(
+                ident.name.clone(),
+                SymbolInfo::Variable {
+                    var_type: Type::Number,
+                },
+            ),
+            None => self.error(
+                "La variable de un for debe ser un identificador".to_string(),
+                forr.var.position(),
+            ),
+        }
+        let body_type = self.visit_expression(&forr.body);
         self.symbol_table.exit_scope();
+        body_type
     }
 
-    fn visit_range(&mut self, start: &Expression, end: &Expression) {
-        start.accept(self);
-        end.accept(self);
+    fn visit_range(&mut self, start: &Expression, end: &Expression) -> Type {
+        self.check_range_bounds(start, end);
+        self.error(
+            "Un rango solo puede usarse como iterable de un for".to_string(),
+            Position::new(start.position().start, end.position().end),
+        );
+        Type::Range
     }
 
-    fn visit_identifier(&mut self, _identifier: &Identifier) {}
-
-    fn visit_unary_op(&mut self, unop: &expressions::unaryoperation::UnaryOp) {
-        unop.expr.accept(self);
+    fn visit_unary_op(&mut self, unop: &expressions::unaryoperation::UnaryOp) -> Type {
+        self.value(&unop.expr);
+        match unop.op {
+            crate::tokens::UnaryOp::Not(_) => Type::Boolean,
+            _ => Type::Number,
+        }
     }
 
-    fn visit_function_def(&mut self, def: &expressions::functiondeclaration::FunctionDef) {
+    /// Registra la función y comprueba que el cuerpo tenga el tipo de
+    /// retorno declarado; la declaración en sí no tiene tipo.
+    fn visit_function_def(&mut self, def: &expressions::functiondeclaration::FunctionDef) -> Type {
         let param_types = def.params.iter().map(|p| p.signature.clone()).collect();
-        self.symbol_table.insert(
+        let declared = self.symbol_table.declare(
             def.name.name.clone(),
             SymbolInfo::Function {
                 return_type: def.return_type.clone(),
                 param_types,
             },
+            def.name.position,
         );
+        if let Err(previous) = declared {
+            self.redeclaration(
+                format!(
+                    "La función '{}' ya está declarada en este ámbito.",
+                    def.name.name
+                ),
+                def.name.position,
+                previous,
+            );
+        }
         self.symbol_table.enter_scope();
+        self.function_scopes.push(self.symbol_table.depth() - 1);
         for param in &def.params {
-            self.symbol_table.insert(
+            let declared = self.symbol_table.declare(
                 param.name.name.clone(),
                 SymbolInfo::Variable {
                     var_type: param.signature.clone(),
                 },
+                param.name.position,
             );
+            if let Err(previous) = declared {
+                self.redeclaration(
+                    format!(
+                        "El parámetro '{}' está repetido en la función '{}'.",
+                        param.name.name, def.name.name
+                    ),
+                    param.name.position,
+                    previous,
+                );
+            }
         }
-        def.body.accept(self);
+        let body_type = self.value(&def.body);
+        self.function_scopes.pop();
         self.symbol_table.exit_scope();
+        // El código generado toma el tipo de retorno de la declaración
+        if body_type != Type::Unknown
+            && def.return_type != Type::Unknown
+            && body_type != def.return_type
+        {
+            self.error(
+                format!(
+                    "La función '{}' devuelve '{}', pero se declaró que devuelve '{}'.",
+                    def.name.name, body_type, def.return_type
+                ),
+                def.body.position(),
+            );
+        }
+        Type::Unknown
     }
 
-    fn visit_function_call(&mut self, call: &expressions::functioncall::FunctionCall) {
-        if let Some(SymbolInfo::Function { param_types, .. }) =
-            self.symbol_table.lookup(&call.funct_name.name)
-        {
-            let param_types = param_types.clone();
-            if param_types.len() != call.arguments.len() {
-                self.errors.push(format!(
-                    "Función '{}' espera {} argumentos, pero se pasaron {}.",
-                    call.funct_name.name,
-                    param_types.len(),
-                    call.arguments.len()
-                ));
-            }
-            for (arg, expected_type) in call.arguments.iter().zip(param_types.iter()) {
-                let arg_type = self.infer_expr_type(arg);
-                if &arg_type != expected_type {
-                    self.errors.push(format!(
-                "El argumento tiene tipo '{:?}', pero se esperaba '{:?}' en la función '{}'.",
-                arg_type, expected_type, call.funct_name.name
-            ));
-                }
+    /// Una lambda tiene el tipo de función que forman sus parámetros y el
+    /// tipo de su cuerpo.
+    fn visit_lambda(&mut self, lambda: &expressions::functiondeclaration::Lambda) -> Type {
+        self.symbol_table.enter_scope();
+        self.function_scopes.push(self.symbol_table.depth() - 1);
+        for param in &lambda.params {
+            let declared = self.symbol_table.declare(
+                param.name.name.clone(),
+                SymbolInfo::Variable {
+                    var_type: param.signature.clone(),
+                },
+                param.name.position,
+            );
+            if let Err(previous) = declared {
+                self.redeclaration(
+                    format!(
+                        "El parámetro '{}' está repetido en la lambda.",
+                        param.name.name
+                    ),
+                    param.name.position,
+                    previous,
+                );
             }
-        } else {
-            self.errors
-                .push(format!("Función '{}' no declarada.", call.funct_name.name));
         }
-        for arg in &call.arguments {
-            arg.accept(self);
+        let reported = self.errors.len();
+        let return_type = self.value(&lambda.body);
+        self.function_scopes.pop();
+        self.symbol_table.exit_scope();
+        // Una lambda no declara qué devuelve: sin el tipo del cuerpo no hay
+        // con qué representar su resultado
+        if return_type == Type::Unknown && self.errors.len() == reported {
+            self.error(
+                "No se puede deducir qué devuelve la lambda: su cuerpo no tiene un tipo conocido."
+                    .to_string(),
+                lambda.body.position(),
+            );
         }
+        let param_types = lambda.params.iter().map(|p| p.signature.clone()).collect();
+        Type::Function(param_types, Box::new(return_type))
     }
 
-    fn visit_atom(&mut self, atom: &atoms::atom::Atom) {
-        if let atoms::atom::Atom::Variable(ident) = atom {
-            if self.symbol_table.lookup(&ident.name).is_none() {
-                self.errors
-                    .push(format!("Variable '{}' no declarada.", ident.name));
+    fn visit_function_call(&mut self, call: &expressions::functioncall::FunctionCall) -> Type {
+        let arg_types: Vec<Type> = call.arguments.iter().map(|arg| self.value(arg)).collect();
+        // Se puede llamar a una función declarada o a una variable que
+        // guarde una
+        let (param_types, return_type) = match self.symbol_table.lookup(&call.funct_name.name) {
+            Some(SymbolInfo::Function {
+                return_type,
+                param_types,
+            }) => (param_types.clone(), return_type.clone()),
+            Some(SymbolInfo::Variable {
+                var_type: Type::Function(param_types, return_type),
+            }) => (param_types.clone(), (**return_type).clone()),
+            Some(SymbolInfo::Variable {
+                var_type: Type::Unknown,
+            }) => return Type::Unknown,
+            Some(SymbolInfo::Variable { var_type }) => {
+                let message = format!(
+                    "'{}' no es una función: su tipo es '{}'.",
+                    call.funct_name.name, var_type
+                );
+                self.error(message, call.funct_name.position);
+                return Type::Unknown;
+            }
+            None => {
+                self.error(
+                    format!("Función '{}' no declarada.", call.funct_name.name),
+                    call.funct_name.position,
+                );
+                return Type::Unknown;
+            }
+        };
+        if param_types.len() != call.arguments.len() {
+            self.error(
+                format!(
+                    "Función '{}' espera {} argumentos, pero se pasaron {}.",
+                    call.funct_name.name,
+                    param_types.len(),
+                    call.arguments.len()
+                ),
+                call.funct_name.position,
+            );
+        }
+        for ((arg, arg_type), expected_type) in
+            call.arguments.iter().zip(&arg_types).zip(&param_types)
+        {
+            if arg_type != expected_type {
+                self.error(
+                    format!(
+                        "El argumento tiene tipo '{}', pero se esperaba '{}' en la función '{}'.",
+                        arg_type, expected_type, call.funct_name.name
+                    ),
+                    arg.position(),
+                );
             }
         }
+        return_type
     }
 
-    fn visit_assignment(&mut self, assign: &expressions::letin::Assignment) {
+    fn visit_assignment(&mut self, assign: &expressions::letin::Assignment) -> Type {
+        // El cuerpo se chequea antes de declarar la variable: `let x = x + 1`
+        // se refiere a la `x` exterior
+        let assigned_type = self.value(&assign.body);
         if let atoms::atom::Atom::Variable(ident) = &assign.variable {
-            let assigned_type = self.infer_expr_type(&assign.body);
             self.symbol_table.insert(
                 ident.name.clone(),
                 SymbolInfo::Variable {
-                    var_type: assigned_type,
+                    var_type: assigned_type.clone(),
                 },
             );
         }
-        assign.body.accept(self);
+        assigned_type
     }
 
-    fn visit_letin(&mut self, letin: &expressions::letin::LetIn) {
+    fn visit_letin(&mut self, letin: &expressions::letin::LetIn) -> Type {
         self.symbol_table.enter_scope();
         for assign in &letin.bindings {
-            assign.accept(self);
+            self.visit_assignment(assign);
         }
-        letin.body.accept(self);
+        let body_type = self.visit_expression(&letin.body);
         self.symbol_table.exit_scope();
+        body_type
     }
 
-    fn visit_binary_op(&mut self, binop: &crate::ast::expressions::binoperation::BinaryOp) {
-        binop.left.accept(self);
-        binop.right.accept(self);
+    fn visit_binary_op(&mut self, binop: &crate::ast::expressions::binoperation::BinaryOp) -> Type {
         use crate::tokens::BinOp;
-        let left_ty = self.infer_expr_type(&binop.left);
-        let right_ty = self.infer_expr_type(&binop.right);
+        let left_ty = self.value(&binop.left);
+        let right_ty = self.value(&binop.right);
+        let span = Position::new(binop.left.position().start, binop.right.position().end);
 
         match &binop.operator {
             BinOp::Plus(_) | BinOp::Minus(_) | BinOp::Mul(_) | BinOp::Div(_) | BinOp::Mod(_) => {
                 if left_ty != Type::Number || right_ty != Type::Number {
-                    self.errors
-                        .push("Operación aritmética requiere números".to_string());
+                    self.error("Operación aritmética requiere números".to_string(), span);
                 }
+                Type::Number
             }
             BinOp::EqualEqual(_)
             | BinOp::NotEqual(_)
@@ -278,61 +538,63 @@ impl Visitor for SemanticTypeChecker {
             | BinOp::GreaterEqual(_)
             | BinOp::LessEqual(_) => {
                 if left_ty != right_ty {
-                    self.errors
-                        .push("Comparación entre tipos incompatibles".to_string());
+                    self.error("Comparación entre tipos incompatibles".to_string(), span);
+                } else if let Type::Function(..) = left_ty {
+                    self.error("Las funciones no se pueden comparar".to_string(), span);
                 }
+                Type::Boolean
             }
             BinOp::AndAnd(_) | BinOp::OrOr(_) => {
                 if left_ty != Type::Boolean || right_ty != Type::Boolean {
-                    self.errors
-                        .push("Operador lógico requiere booleanos".to_string());
+                    self.error("Operador lógico requiere booleanos".to_string(), span);
                 }
+                Type::Boolean
             }
             BinOp::ConcatString(_) => {
                 if !(left_ty == Type::String || left_ty == Type::Number)
                     || !(right_ty == Type::String || right_ty == Type::Number)
                 {
-                    self.errors
-                        .push("Concatenación requiere string o número".to_string());
+                    self.error("Concatenación requiere string o número".to_string(), span);
+                }
+                Type::String
+            }
+            // := devuelve el valor asignado
+            BinOp::Assign(_) => {
+                self.check_captured_assign(&binop.left);
+                // La variable conserva el tipo con que se declaró
+                if left_ty != right_ty && left_ty != Type::Unknown && right_ty != Type::Unknown {
+                    self.error(
+                        format!(
+                            "No se puede asignar un valor de tipo '{}' a una variable de tipo '{}'.",
+                            right_ty, left_ty
+                        ),
+                        span,
+                    );
                 }
+                right_ty
             }
-            BinOp::Assign(_) => { /* handled in assignment */ }
-            _ => {}
+            _ => Type::Unknown,
         }
     }
 
-    fn visit_ifelse(&mut self, ifelse: &crate::ast::expressions::ifelse::IfElse) {
-        ifelse.condition.accept(self);
-        let cond_ty = self.infer_expr_type(&ifelse.condition);
-        if cond_ty != Type::Boolean {
-            self.errors
-                .push("Condición de if debe ser booleana".to_string());
-        }
-        ifelse.then_branch.accept(self);
+    fn visit_ifelse(&mut self, ifelse: &crate::ast::expressions::ifelse::IfElse) -> Type {
+        let cond_ty = self.value(&ifelse.condition);
+        self.check_condition(cond_ty, &ifelse.condition, "if");
+        let mut branch_types = vec![self.visit_expression(&ifelse.then_branch)];
         for (_, cond, branch) in &ifelse.elif_branches {
-            cond.accept(self);
-            let t = self.infer_expr_type(cond);
-            if t != Type::Boolean {
-                self.errors
-                    .push("Condición de elif debe ser booleana".to_string());
-            }
-            branch.accept(self);
+            let cond_ty = self.value(cond);
+            self.check_condition(cond_ty, cond, "elif");
+            branch_types.push(self.visit_expression(branch));
         }
         if let Some(branch) = &ifelse.else_branch {
-            branch.accept(self);
+            branch_types.push(self.visit_expression(branch));
         }
+        Self::unify_types(branch_types)
     }
 
-    fn visit_while(&mut self, whilee: &crate::ast::expressions::whilee::While) {
-        whilee.cond.accept(self);
-        let cond_ty = self.infer_expr_type(&whilee.cond);
-        if cond_ty != Type::Boolean {
-            self.errors
-                .push("Condición de while debe ser booleana".to_string());
-        }
-        whilee.body.accept(self);
+    fn visit_while(&mut self, whilee: &crate::ast::expressions::whilee::While) -> Type {
+        let cond_ty = self.value(&whilee.cond);
+        self.check_condition(cond_ty, &whilee.cond, "while");
+        self.visit_expression(&whilee.body)
     }
-
-    // Implementa los demás métodos igual que antes, usando self.errors para reportar problemas
-    // ...
 }
@@ -1,5 +1,6 @@
 use std::collections::HashMap;
 use super::types::Type;
+use crate::tokens::Position;
 
 #[derive(Debug, Clone)]
 pub enum SymbolInfo {
@@ -7,9 +8,16 @@ pub enum SymbolInfo {
     Function { return_type: Type, param_types: Vec<Type> },
 }
 
+/// Símbolo de un scope y, si se conoce, dónde se declaró.
+#[derive(Debug, Clone)]
+struct Symbol {
+    info: SymbolInfo,
+    position: Option<Position>,
+}
+
 #[derive(Debug, Clone)]
 pub struct SymbolTable {
-    scopes: Vec<HashMap<String, SymbolInfo>>,
+    scopes: Vec<HashMap<String, Symbol>>,
 }
 
 impl SymbolTable {
@@ -25,18 +33,68 @@ impl SymbolTable {
         self.scopes.pop();
     }
 
+    /// Agrega `name` al scope actual, reemplazando lo que hubiera con ese
+    /// nombre en el mismo scope.
     pub fn insert(&mut self, name: String, info: SymbolInfo) {
         if let Some(scope) = self.scopes.last_mut() {
-            scope.insert(name, info);
+            scope.insert(name, Symbol { info, position: None });
+        }
+    }
+
+    /// Declara `name` en el scope actual. Si ya estaba declarado en ese
+    /// scope no lo reemplaza y devuelve la posición de la declaración
+    /// anterior, si se conoce.
+    pub fn declare(
+        &mut self,
+        name: String,
+        info: SymbolInfo,
+        position: Position,
+    ) -> Result<(), Option<Position>> {
+        let Some(scope) = self.scopes.last_mut() else {
+            return Ok(());
+        };
+        if let Some(previous) = scope.get(&name) {
+            return Err(previous.position);
+        }
+        scope.insert(name, Symbol { info, position: Some(position) });
+        Ok(())
+    }
+
+    /// Quita `name` del scope actual.
+    pub fn remove(&mut self, name: &str) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.remove(name);
         }
     }
 
     pub fn lookup(&self, name: &str) -> Option<&SymbolInfo> {
         for scope in self.scopes.iter().rev() {
-            if let Some(info) = scope.get(name) {
-                return Some(info);
+            if let Some(symbol) = scope.get(name) {
+                return Some(&symbol.info);
             }
         }
         None
     }
-}
\ No newline at end of file
+
+    /// Cantidad de scopes abiertos; el actual es el último.
+    pub fn depth(&self) -> usize {
+        self.scopes.len()
+    }
+
+    /// Índice del scope más interno que declara `name`.
+    pub fn scope_of(&self, name: &str) -> Option<usize> {
+        self.scopes.iter().rposition(|scope| scope.contains_key(name))
+    }
+
+    /// Símbolos visibles desde el scope actual, ordenados por nombre. Un
+    /// nombre declarado en un scope interno oculta al de los externos.
+    pub fn visible(&self) -> Vec<(&String, &SymbolInfo)> {
+        let mut visible: HashMap<&String, &SymbolInfo> = HashMap::new();
+        for scope in &self.scopes {
+            visible.extend(scope.iter().map(|(name, symbol)| (name, &symbol.info)));
+        }
+        let mut symbols: Vec<_> = visible.into_iter().collect();
+        symbols.sort_by(|a, b| a.0.cmp(b.0));
+        symbols
+    }
+}
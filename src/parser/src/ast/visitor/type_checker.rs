@@ -1,7 +1,6 @@
 use crate::ast;
 // use crate::ast::expressions::binoperation::BinaryOp;
 use crate::ast::visitor::{Visitable, Visitor};
-use crate::tokens;
 // use crate::tokens::BinOp;
 use crate::visitor::Type;
 
@@ -45,43 +44,16 @@ impl TypeChecker {
 }
 
 impl Visitor for TypeChecker {
-    fn visit_atom(&mut self, _atom: &ast::atoms::atom::Atom) {
-        
-    }
-    fn visit_block(&mut self, _block: &ast::expressions::block::Block) {
-        
-    }
-    fn visit_expression(&mut self, _expr: &ast::Expression) {
-        
-    }
-    fn visit_expression_list(&mut self, expr_list: &ast::ExpressionList) {
-        for expr in &expr_list.expressions {
-            expr.accept(self);
+    fn visit_for(&mut self, forr: &ast::forr::For) {
+        forr.iterable.accept(self);
+        self.enter_scope();
+        if let ast::Expression::Atom(atom) = &*forr.var {
+            if let ast::atoms::atom::Atom::Variable(id) = &**atom {
+                self.declare_var(&id.name, Type::Number);
+            }
         }
-    }
-    fn visit_for(&mut self, _forr: &ast::forr::For) {
-        
-    }
-    fn visit_group(&mut self, _group: &ast::atoms::group::Group) {
-        
-    }
-    fn visit_identifier(&mut self, _identifier: &tokens::Identifier) {
-        
-    }
-    fn visit_literal(&mut self, _literal: &tokens::Literal) {
-        
-    }
-    fn visit_print(&mut self, expr: &ast::Expression) {
-        expr.accept(self);
-    }
-    fn visit_program(&mut self, program: &ast::Program) {
-        program.expression_list.accept(self);
-    }
-    fn visit_range(&mut self, _start: &ast::Expression, _end: &ast::Expression) {
-        
-    }
-    fn visit_unary_op(&mut self, _unary_op: &ast::expressions::unaryoperation::UnaryOp) {
-        
+        forr.body.accept(self);
+        self.exit_scope();
     }
 
     fn visit_letin(&mut self, letin: &crate::ast::expressions::letin::LetIn) {
@@ -167,11 +139,21 @@ impl Visitor for TypeChecker {
         }
         whilee.body.accept(self);
     }
-    fn visit_function_call(&mut self, _call: &ast::expressions::functioncall::FunctionCall) {
-        
+    fn visit_function_def(&mut self, def: &ast::expressions::functiondeclaration::FunctionDef) {
+        self.enter_scope();
+        for param in &def.params {
+            self.declare_var(&param.name.name, param.signature.clone());
+        }
+        def.body.accept(self);
+        self.exit_scope();
     }
-    fn visit_function_def(&mut self, _def: &ast::expressions::functiondeclaration::FunctionDef) {
-        
+    fn visit_lambda(&mut self, lambda: &ast::expressions::functiondeclaration::Lambda) {
+        self.enter_scope();
+        for param in &lambda.params {
+            self.declare_var(&param.name.name, param.signature.clone());
+        }
+        lambda.body.accept(self);
+        self.exit_scope();
     }
 
     // ...
@@ -1,3 +1,5 @@
+use std::fmt;
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum Type {
     Number,
@@ -5,6 +7,10 @@ pub enum Type {
     String,
     Object,
     Custom(String), // Para type(Ident)
+    /// Función como valor: tipos de los parámetros y del resultado
+    Function(Vec<Type>, Box<Type>),
+    /// Rango `a..b`; solo existe como iterable de un `for`
+    Range,
     Unknown,
 }
 
@@ -18,4 +24,26 @@ impl Type {
             _ => Type::Unknown,
         }
     }
-}
\ No newline at end of file
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Type::Number => write!(f, "Number"),
+            Type::Boolean => write!(f, "Boolean"),
+            Type::String => write!(f, "String"),
+            Type::Object => write!(f, "Object"),
+            Type::Custom(name) => write!(f, "{}", name),
+            Type::Function(params, result) => {
+                let params = params
+                    .iter()
+                    .map(|param| param.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                write!(f, "({}) -> {}", params, result)
+            }
+            Type::Range => write!(f, "Range"),
+            Type::Unknown => write!(f, "Unknown"),
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use crate::ast::atoms::atom::Atom;
+use crate::ast::atoms::group::Group;
+use crate::ast::expressions::binoperation::BinaryOp;
+use crate::ast::expressions::block::Block;
+use crate::ast::expressions::expressions::Expression;
+use crate::ast::expressions::forr::For;
+use crate::ast::expressions::functioncall::FunctionCall;
+use crate::ast::expressions::functiondeclaration::{FunctionDef, Lambda};
+use crate::ast::expressions::ifelse::IfElse;
+use crate::ast::expressions::letin::{Assignment, LetIn};
+use crate::ast::expressions::unaryoperation::UnaryOp;
+use crate::ast::expressions::whilee::While;
+use crate::ast::{ExpressionList, Program};
+use crate::tokens::{Identifier, Literal};
+
+/// Recorrido del AST en el que cada nodo devuelve un resultado.
+///
+/// A diferencia de `Visitor`, el resultado de un hijo llega a su padre como
+/// valor de retorno: el chequeo de tipos devuelve el `Type` de cada
+/// expresión, el generador de LLVM el valor donde quedó el resultado y el
+/// intérprete el valor calculado. Como no hay forma general de combinar los
+/// resultados de los hijos, solo tienen implementación por defecto los
+/// métodos que despachan a otro: `visit_expression`, `visit_atom` y
+/// `visit_group`.
+pub trait ValueVisitor {
+    type Output;
+
+    fn visit_program(&mut self, program: &Program) -> Self::Output;
+    fn visit_expression_list(&mut self, expr_list: &ExpressionList) -> Self::Output;
+    fn visit_expression(&mut self, expr: &Expression) -> Self::Output {
+        walk_expression(self, expr)
+    }
+    fn visit_atom(&mut self, atom: &Atom) -> Self::Output {
+        walk_atom(self, atom)
+    }
+    fn visit_binary_op(&mut self, binop: &BinaryOp) -> Self::Output;
+    fn visit_letin(&mut self, letin: &LetIn) -> Self::Output;
+    /// Resultado del valor ligado a la variable.
+    fn visit_assignment(&mut self, assign: &Assignment) -> Self::Output;
+    fn visit_block(&mut self, block: &Block) -> Self::Output;
+    fn visit_literal(&mut self, literal: &Literal) -> Self::Output;
+    fn visit_identifier(&mut self, identifier: &Identifier) -> Self::Output;
+    fn visit_print(&mut self, expr: &Expression) -> Self::Output;
+    fn visit_while(&mut self, whilee: &While) -> Self::Output;
+    fn visit_ifelse(&mut self, ifelse: &IfElse) -> Self::Output;
+    fn visit_group(&mut self, group: &Group) -> Self::Output {
+        self.visit_expression(&group.expression)
+    }
+    fn visit_unary_op(&mut self, unary_op: &UnaryOp) -> Self::Output;
+    fn visit_for(&mut self, forr: &For) -> Self::Output;
+    fn visit_range(&mut self, start: &Expression, end: &Expression) -> Self::Output;
+    fn visit_function_call(&mut self, call: &FunctionCall) -> Self::Output;
+    fn visit_function_def(&mut self, def: &FunctionDef) -> Self::Output;
+    fn visit_lambda(&mut self, lambda: &Lambda) -> Self::Output;
+}
+
+pub fn walk_expression<V: ValueVisitor + ?Sized>(visitor: &mut V, expr: &Expression) -> V::Output {
+    match expr {
+        Expression::BinaryOp(binop) => visitor.visit_binary_op(binop),
+        Expression::Atom(atom) => visitor.visit_atom(atom),
+        Expression::IfElse(ifelse) => visitor.visit_ifelse(ifelse),
+        Expression::LetIn(letin) => visitor.visit_letin(letin),
+        Expression::For(forr) => visitor.visit_for(forr),
+        Expression::Print(expr, _) => visitor.visit_print(expr),
+        Expression::While(whilee) => visitor.visit_while(whilee),
+        Expression::Block(block) => visitor.visit_block(block),
+        Expression::UnaryOp(unary_op) => visitor.visit_unary_op(unary_op),
+        Expression::Range(start, end) => visitor.visit_range(start, end),
+        Expression::FunctionCall(call) => visitor.visit_function_call(call),
+        Expression::FunctionDef(def) => visitor.visit_function_def(def),
+        Expression::Lambda(lambda) => visitor.visit_lambda(lambda),
+    }
+}
+
+pub fn walk_atom<V: ValueVisitor + ?Sized>(visitor: &mut V, atom: &Atom) -> V::Output {
+    match atom {
+        Atom::NumberLiteral(literal)
+        | Atom::BooleanLiteral(literal)
+        | Atom::StringLiteral(literal) => visitor.visit_literal(literal),
+        Atom::Variable(identifier) => visitor.visit_identifier(identifier),
+        Atom::Group(group) => visitor.visit_group(group),
+    }
+}
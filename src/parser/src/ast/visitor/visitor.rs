@@ -1,29 +1,189 @@
 use crate::ast;
+use crate::forr;
 use crate::tokens;
 use crate::whilee;
-use crate::forr;
+
+/// Recorrido de solo lectura del AST.
+///
+/// Cada método tiene una implementación por defecto que visita los hijos del
+/// nodo con la función `walk_*` correspondiente, así que una pasada solo
+/// sobreescribe los nodos que le interesan. Quien sobreescribe un método y
+/// quiere seguir bajando por el árbol llama al `walk_*` del mismo nombre.
 pub trait Visitor {
-    fn visit_program(&mut self, program: &ast::Program);
-    fn visit_expression_list(&mut self, expr_list: &ast::ExpressionList);
-    fn visit_expression(&mut self, expr: &ast::Expression);
-    fn visit_atom(&mut self, atom: &ast::atoms::atom::Atom);
-    fn visit_binary_op(&mut self, binop: &ast::expressions::binoperation::BinaryOp);
-    fn visit_letin(&mut self, letin: &ast::expressions::letin::LetIn);
-    fn visit_assignment(&mut self, assign: &ast::expressions::letin::Assignment);
-    fn visit_block(&mut self, block: &ast::expressions::block::Block);
-    fn visit_literal(&mut self, literal: &tokens::Literal);
-    fn visit_identifier(&mut self, identifier: &tokens::Identifier);
-    fn visit_print(&mut self, expr: &ast::Expression);
-    fn visit_while(&mut self, whilee: &whilee::While);
-    fn visit_ifelse(&mut self, ifelse: &ast::expressions::ifelse::IfElse);
-    fn visit_group(&mut self, group: &ast::atoms::group::Group);
-    fn visit_unary_op(&mut self, unary_op: &ast::expressions::unaryoperation::UnaryOp);
-    fn visit_for(&mut self, forr: &forr::For);
-    fn visit_range(&mut self, start: &ast::Expression, end: &ast::Expression);
-    fn visit_function_call(&mut self, call: &ast::expressions::functioncall::FunctionCall);
-    fn visit_function_def(&mut self, def: &ast::expressions::functiondeclaration::FunctionDef);
+    fn visit_program(&mut self, program: &ast::Program) {
+        walk_program(self, program);
+    }
+    fn visit_expression_list(&mut self, expr_list: &ast::ExpressionList) {
+        walk_expression_list(self, expr_list);
+    }
+    fn visit_expression(&mut self, expr: &ast::Expression) {
+        walk_expression(self, expr);
+    }
+    fn visit_atom(&mut self, atom: &ast::atoms::atom::Atom) {
+        walk_atom(self, atom);
+    }
+    fn visit_binary_op(&mut self, binop: &ast::expressions::binoperation::BinaryOp) {
+        walk_binary_op(self, binop);
+    }
+    fn visit_letin(&mut self, letin: &ast::expressions::letin::LetIn) {
+        walk_letin(self, letin);
+    }
+    fn visit_assignment(&mut self, assign: &ast::expressions::letin::Assignment) {
+        walk_assignment(self, assign);
+    }
+    fn visit_block(&mut self, block: &ast::expressions::block::Block) {
+        walk_block(self, block);
+    }
+    fn visit_literal(&mut self, _literal: &tokens::Literal) {}
+    fn visit_identifier(&mut self, _identifier: &tokens::Identifier) {}
+    fn visit_print(&mut self, expr: &ast::Expression) {
+        self.visit_expression(expr);
+    }
+    fn visit_while(&mut self, whilee: &whilee::While) {
+        walk_while(self, whilee);
+    }
+    fn visit_ifelse(&mut self, ifelse: &ast::expressions::ifelse::IfElse) {
+        walk_ifelse(self, ifelse);
+    }
+    fn visit_group(&mut self, group: &ast::atoms::group::Group) {
+        self.visit_expression(&group.expression);
+    }
+    fn visit_unary_op(&mut self, unary_op: &ast::expressions::unaryoperation::UnaryOp) {
+        self.visit_expression(&unary_op.expr);
+    }
+    fn visit_for(&mut self, forr: &forr::For) {
+        walk_for(self, forr);
+    }
+    fn visit_range(&mut self, start: &ast::Expression, end: &ast::Expression) {
+        self.visit_expression(start);
+        self.visit_expression(end);
+    }
+    fn visit_function_call(&mut self, call: &ast::expressions::functioncall::FunctionCall) {
+        walk_function_call(self, call);
+    }
+    fn visit_function_def(&mut self, def: &ast::expressions::functiondeclaration::FunctionDef) {
+        walk_function_def(self, def);
+    }
+    fn visit_lambda(&mut self, lambda: &ast::expressions::functiondeclaration::Lambda) {
+        self.visit_expression(&lambda.body);
+    }
 }
 
 pub trait Visitable {
     fn accept<V: Visitor>(&self, visitor: &mut V);
 }
+
+pub fn walk_program<V: Visitor + ?Sized>(visitor: &mut V, program: &ast::Program) {
+    visitor.visit_expression_list(&program.expression_list);
+}
+
+pub fn walk_expression_list<V: Visitor + ?Sized>(visitor: &mut V, expr_list: &ast::ExpressionList) {
+    for expr in &expr_list.expressions {
+        visitor.visit_expression(expr);
+    }
+}
+
+pub fn walk_expression<V: Visitor + ?Sized>(visitor: &mut V, expr: &ast::Expression) {
+    match expr {
+        ast::Expression::BinaryOp(binop) => visitor.visit_binary_op(binop),
+        ast::Expression::Atom(atom) => visitor.visit_atom(atom),
+        ast::Expression::IfElse(ifelse) => visitor.visit_ifelse(ifelse),
+        ast::Expression::LetIn(letin) => visitor.visit_letin(letin),
+        ast::Expression::For(forr) => visitor.visit_for(forr),
+        ast::Expression::Print(expr, _) => visitor.visit_print(expr),
+        ast::Expression::While(whilee) => visitor.visit_while(whilee),
+        ast::Expression::Block(block) => visitor.visit_block(block),
+        ast::Expression::UnaryOp(unary_op) => visitor.visit_unary_op(unary_op),
+        ast::Expression::Range(start, end) => visitor.visit_range(start, end),
+        ast::Expression::FunctionCall(call) => visitor.visit_function_call(call),
+        ast::Expression::FunctionDef(def) => visitor.visit_function_def(def),
+        ast::Expression::Lambda(lambda) => visitor.visit_lambda(lambda),
+    }
+}
+
+pub fn walk_atom<V: Visitor + ?Sized>(visitor: &mut V, atom: &ast::atoms::atom::Atom) {
+    use ast::atoms::atom::Atom;
+    match atom {
+        Atom::NumberLiteral(literal)
+        | Atom::BooleanLiteral(literal)
+        | Atom::StringLiteral(literal) => visitor.visit_literal(literal),
+        Atom::Variable(identifier) => visitor.visit_identifier(identifier),
+        Atom::Group(group) => visitor.visit_group(group),
+    }
+}
+
+pub fn walk_binary_op<V: Visitor + ?Sized>(
+    visitor: &mut V,
+    binop: &ast::expressions::binoperation::BinaryOp,
+) {
+    visitor.visit_expression(&binop.left);
+    visitor.visit_expression(&binop.right);
+}
+
+pub fn walk_letin<V: Visitor + ?Sized>(visitor: &mut V, letin: &ast::expressions::letin::LetIn) {
+    for assign in &letin.bindings {
+        visitor.visit_assignment(assign);
+    }
+    visitor.visit_expression(&letin.body);
+}
+
+pub fn walk_assignment<V: Visitor + ?Sized>(
+    visitor: &mut V,
+    assign: &ast::expressions::letin::Assignment,
+) {
+    if let ast::atoms::atom::Atom::Variable(identifier) = &assign.variable {
+        visitor.visit_identifier(identifier);
+    }
+    visitor.visit_expression(&assign.body);
+}
+
+pub fn walk_block<V: Visitor + ?Sized>(visitor: &mut V, block: &ast::expressions::block::Block) {
+    visitor.visit_expression_list(&block.expression_list);
+}
+
+pub fn walk_while<V: Visitor + ?Sized>(visitor: &mut V, whilee: &whilee::While) {
+    visitor.visit_expression(&whilee.cond);
+    visitor.visit_expression(&whilee.body);
+}
+
+pub fn walk_ifelse<V: Visitor + ?Sized>(
+    visitor: &mut V,
+    ifelse: &ast::expressions::ifelse::IfElse,
+) {
+    visitor.visit_expression(&ifelse.condition);
+    visitor.visit_expression(&ifelse.then_branch);
+    for (_, condition, branch) in &ifelse.elif_branches {
+        visitor.visit_expression(condition);
+        visitor.visit_expression(branch);
+    }
+    if let Some(else_branch) = &ifelse.else_branch {
+        visitor.visit_expression(else_branch);
+    }
+}
+
+pub fn walk_for<V: Visitor + ?Sized>(visitor: &mut V, forr: &forr::For) {
+    visitor.visit_expression(&forr.var);
+    visitor.visit_expression(&forr.iterable);
+    visitor.visit_expression(&forr.body);
+}
+
+pub fn walk_function_call<V: Visitor + ?Sized>(
+    visitor: &mut V,
+    call: &ast::expressions::functioncall::FunctionCall,
+) {
+    visitor.visit_identifier(&call.funct_name);
+    for arg in &call.arguments {
+        visitor.visit_expression(arg);
+    }
+}
+
+pub fn walk_function_def<V: Visitor + ?Sized>(
+    visitor: &mut V,
+    def: &ast::expressions::functiondeclaration::FunctionDef,
+) {
+    visitor.visit_identifier(&def.name);
+    for param in &def.params {
+        visitor.visit_identifier(&param.name);
+    }
+    visitor.visit_expression(&def.body);
+}
@@ -0,0 +1,189 @@
+use crate::ast::atoms::atom::Atom;
+use crate::ast::atoms::group::Group;
+use crate::ast::expressions::binoperation::BinaryOp;
+use crate::ast::expressions::block::Block;
+use crate::ast::expressions::expressions::Expression;
+use crate::ast::expressions::forr::For;
+use crate::ast::expressions::functioncall::FunctionCall;
+use crate::ast::expressions::functiondeclaration::{FunctionDef, Lambda};
+use crate::ast::expressions::ifelse::IfElse;
+use crate::ast::expressions::letin::{Assignment, LetIn};
+use crate::ast::expressions::unaryoperation::UnaryOp;
+use crate::ast::expressions::whilee::While;
+use crate::ast::{ExpressionList, Program};
+use crate::tokens::{Identifier, Literal};
+
+/// Recorrido que edita el AST en el lugar.
+///
+/// Sirve para las pasadas que cambian datos de los nodos sin cambiar su
+/// forma, como renombrar variables o ajustar posiciones. Para reemplazar un
+/// nodo por otro de distinta clase conviene `Folder`. Por defecto cada
+/// método visita los hijos con la función `walk_*_mut` del mismo nombre.
+pub trait VisitorMut {
+    fn visit_program_mut(&mut self, program: &mut Program) {
+        walk_program_mut(self, program);
+    }
+    fn visit_expression_list_mut(&mut self, expr_list: &mut ExpressionList) {
+        walk_expression_list_mut(self, expr_list);
+    }
+    fn visit_expression_mut(&mut self, expr: &mut Expression) {
+        walk_expression_mut(self, expr);
+    }
+    fn visit_atom_mut(&mut self, atom: &mut Atom) {
+        walk_atom_mut(self, atom);
+    }
+    fn visit_binary_op_mut(&mut self, binop: &mut BinaryOp) {
+        walk_binary_op_mut(self, binop);
+    }
+    fn visit_letin_mut(&mut self, letin: &mut LetIn) {
+        walk_letin_mut(self, letin);
+    }
+    fn visit_assignment_mut(&mut self, assign: &mut Assignment) {
+        walk_assignment_mut(self, assign);
+    }
+    fn visit_block_mut(&mut self, block: &mut Block) {
+        walk_block_mut(self, block);
+    }
+    fn visit_literal_mut(&mut self, _literal: &mut Literal) {}
+    fn visit_identifier_mut(&mut self, _identifier: &mut Identifier) {}
+    fn visit_print_mut(&mut self, expr: &mut Expression) {
+        self.visit_expression_mut(expr);
+    }
+    fn visit_while_mut(&mut self, whilee: &mut While) {
+        walk_while_mut(self, whilee);
+    }
+    fn visit_ifelse_mut(&mut self, ifelse: &mut IfElse) {
+        walk_ifelse_mut(self, ifelse);
+    }
+    fn visit_group_mut(&mut self, group: &mut Group) {
+        self.visit_expression_mut(&mut group.expression);
+    }
+    fn visit_unary_op_mut(&mut self, unary_op: &mut UnaryOp) {
+        self.visit_expression_mut(&mut unary_op.expr);
+    }
+    fn visit_for_mut(&mut self, forr: &mut For) {
+        walk_for_mut(self, forr);
+    }
+    fn visit_range_mut(&mut self, start: &mut Expression, end: &mut Expression) {
+        self.visit_expression_mut(start);
+        self.visit_expression_mut(end);
+    }
+    fn visit_function_call_mut(&mut self, call: &mut FunctionCall) {
+        walk_function_call_mut(self, call);
+    }
+    fn visit_function_def_mut(&mut self, def: &mut FunctionDef) {
+        walk_function_def_mut(self, def);
+    }
+    fn visit_lambda_mut(&mut self, lambda: &mut Lambda) {
+        walk_lambda_mut(self, lambda);
+    }
+}
+
+pub fn walk_program_mut<V: VisitorMut + ?Sized>(visitor: &mut V, program: &mut Program) {
+    visitor.visit_expression_list_mut(&mut program.expression_list);
+}
+
+pub fn walk_expression_list_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    expr_list: &mut ExpressionList,
+) {
+    for expr in &mut expr_list.expressions {
+        visitor.visit_expression_mut(expr);
+    }
+}
+
+pub fn walk_expression_mut<V: VisitorMut + ?Sized>(visitor: &mut V, expr: &mut Expression) {
+    match expr {
+        Expression::BinaryOp(binop) => visitor.visit_binary_op_mut(binop),
+        Expression::Atom(atom) => visitor.visit_atom_mut(atom),
+        Expression::IfElse(ifelse) => visitor.visit_ifelse_mut(ifelse),
+        Expression::LetIn(letin) => visitor.visit_letin_mut(letin),
+        Expression::For(forr) => visitor.visit_for_mut(forr),
+        Expression::Print(expr, _) => visitor.visit_print_mut(expr),
+        Expression::While(whilee) => visitor.visit_while_mut(whilee),
+        Expression::Block(block) => visitor.visit_block_mut(block),
+        Expression::UnaryOp(unary_op) => visitor.visit_unary_op_mut(unary_op),
+        Expression::Range(start, end) => visitor.visit_range_mut(start, end),
+        Expression::FunctionCall(call) => visitor.visit_function_call_mut(call),
+        Expression::FunctionDef(def) => visitor.visit_function_def_mut(def),
+        Expression::Lambda(lambda) => visitor.visit_lambda_mut(lambda),
+    }
+}
+
+pub fn walk_atom_mut<V: VisitorMut + ?Sized>(visitor: &mut V, atom: &mut Atom) {
+    match atom {
+        Atom::NumberLiteral(literal)
+        | Atom::BooleanLiteral(literal)
+        | Atom::StringLiteral(literal) => visitor.visit_literal_mut(literal),
+        Atom::Variable(identifier) => visitor.visit_identifier_mut(identifier),
+        Atom::Group(group) => visitor.visit_group_mut(group),
+    }
+}
+
+pub fn walk_binary_op_mut<V: VisitorMut + ?Sized>(visitor: &mut V, binop: &mut BinaryOp) {
+    visitor.visit_expression_mut(&mut binop.left);
+    visitor.visit_expression_mut(&mut binop.right);
+}
+
+pub fn walk_letin_mut<V: VisitorMut + ?Sized>(visitor: &mut V, letin: &mut LetIn) {
+    for assign in &mut letin.bindings {
+        visitor.visit_assignment_mut(assign);
+    }
+    visitor.visit_expression_mut(&mut letin.body);
+}
+
+pub fn walk_assignment_mut<V: VisitorMut + ?Sized>(visitor: &mut V, assign: &mut Assignment) {
+    if let Atom::Variable(identifier) = &mut assign.variable {
+        visitor.visit_identifier_mut(identifier);
+    }
+    visitor.visit_expression_mut(&mut assign.body);
+}
+
+pub fn walk_block_mut<V: VisitorMut + ?Sized>(visitor: &mut V, block: &mut Block) {
+    visitor.visit_expression_list_mut(&mut block.expression_list);
+}
+
+pub fn walk_while_mut<V: VisitorMut + ?Sized>(visitor: &mut V, whilee: &mut While) {
+    visitor.visit_expression_mut(&mut whilee.cond);
+    visitor.visit_expression_mut(&mut whilee.body);
+}
+
+pub fn walk_ifelse_mut<V: VisitorMut + ?Sized>(visitor: &mut V, ifelse: &mut IfElse) {
+    visitor.visit_expression_mut(&mut ifelse.condition);
+    visitor.visit_expression_mut(&mut ifelse.then_branch);
+    for (_, condition, branch) in &mut ifelse.elif_branches {
+        visitor.visit_expression_mut(condition);
+        visitor.visit_expression_mut(branch);
+    }
+    if let Some(else_branch) = &mut ifelse.else_branch {
+        visitor.visit_expression_mut(else_branch);
+    }
+}
+
+pub fn walk_for_mut<V: VisitorMut + ?Sized>(visitor: &mut V, forr: &mut For) {
+    visitor.visit_expression_mut(&mut forr.var);
+    visitor.visit_expression_mut(&mut forr.iterable);
+    visitor.visit_expression_mut(&mut forr.body);
+}
+
+pub fn walk_function_call_mut<V: VisitorMut + ?Sized>(visitor: &mut V, call: &mut FunctionCall) {
+    visitor.visit_identifier_mut(&mut call.funct_name);
+    for arg in &mut call.arguments {
+        visitor.visit_expression_mut(arg);
+    }
+}
+
+pub fn walk_function_def_mut<V: VisitorMut + ?Sized>(visitor: &mut V, def: &mut FunctionDef) {
+    visitor.visit_identifier_mut(&mut def.name);
+    for param in &mut def.params {
+        visitor.visit_identifier_mut(&mut param.name);
+    }
+    visitor.visit_expression_mut(&mut def.body);
+}
+
+pub fn walk_lambda_mut<V: VisitorMut + ?Sized>(visitor: &mut V, lambda: &mut Lambda) {
+    for param in &mut lambda.params {
+        visitor.visit_identifier_mut(&mut param.name);
+    }
+    visitor.visit_expression_mut(&mut lambda.body);
+}
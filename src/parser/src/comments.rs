@@ -0,0 +1,126 @@
+use crate::tokens::Position;
+
+/// Comentario del código fuente (`// ...` o `/* ... */`).
+#[derive(Debug, Clone)]
+pub struct Comment {
+    /// Texto completo, delimitadores incluidos
+    pub text: String,
+    pub position: Position,
+    /// `true` si el comentario ocupa su propia línea; `false` si sigue a
+    /// código en la misma línea
+    pub own_line: bool,
+}
+
+/// Reemplaza los comentarios por espacios, conservando los saltos de línea,
+/// y los devuelve aparte. Como las demás posiciones no se mueven, los errores
+/// y el AST siguen apuntando al texto original.
+pub fn strip_comments(source: &str) -> Result<(String, Vec<Comment>), String> {
+    let bytes = source.as_bytes();
+    let mut result = String::with_capacity(source.len());
+    let mut comments = Vec::new();
+    let mut in_string = false;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let c = bytes[i];
+        if in_string {
+            if c == b'\\' && i + 1 < bytes.len() {
+                result.push_str(&source[i..i + 2]);
+                i += 2;
+                continue;
+            }
+            if c == b'"' {
+                in_string = false;
+            }
+        } else if c == b'"' {
+            in_string = true;
+        } else if c == b'/' && bytes.get(i + 1) == Some(&b'/') {
+            let end = source[i..].find('\n').map_or(source.len(), |n| i + n);
+            comments.push(comment(source, i, end));
+            blank(&mut result, &source[i..end]);
+            i = end;
+            continue;
+        } else if c == b'/' && bytes.get(i + 1) == Some(&b'*') {
+            let body = &source[i + 2..];
+            let close = body.find("*/");
+            let open = body.find("/*");
+            match (close, open) {
+                (Some(close), Some(open)) if open < close => {
+                    return Err("No se permiten comentarios multilínea anidados".to_string());
+                }
+                (Some(close), _) => {
+                    let end = i + 2 + close + 2;
+                    comments.push(comment(source, i, end));
+                    blank(&mut result, &source[i..end]);
+                    i = end;
+                    continue;
+                }
+                (None, _) => return Err("Comentario multilínea sin cerrar".to_string()),
+            }
+        }
+        // Se copia el carácter completo, que puede ocupar varios bytes
+        let len = source[i..].chars().next().unwrap().len_utf8();
+        result.push_str(&source[i..i + len]);
+        i += len;
+    }
+
+    Ok((result, comments))
+}
+
+fn comment(source: &str, start: usize, end: usize) -> Comment {
+    let line_start = source[..start].rfind('\n').map_or(0, |n| n + 1);
+    Comment {
+        text: source[start..end].to_string(),
+        position: Position::new(start, end),
+        own_line: source[line_start..start].trim().is_empty(),
+    }
+}
+
+/// Agrega a `out` tantos bytes de espacio como ocupa `text`, salvo los saltos de línea.
+fn blank(out: &mut String, text: &str) {
+    for c in text.chars() {
+        if c == '\n' {
+            out.push('\n');
+        } else {
+            out.extend(std::iter::repeat_n(' ', c.len_utf8()));
+        }
+    }
+}
+
+/// Traduce posiciones del texto original a las del texto preprocesado, que
+/// solo difiere en las `@` que `preprocess_functions` agrega antes de cada llamada.
+pub fn map_offsets(original: &str, preprocessed: &str) -> Vec<usize> {
+    let original = original.as_bytes();
+    let preprocessed = preprocessed.as_bytes();
+    let mut map = Vec::with_capacity(original.len() + 1);
+    let mut j = 0;
+    for &byte in original {
+        while j < preprocessed.len() && preprocessed[j] != byte {
+            j += 1;
+        }
+        map.push(j);
+        j += 1;
+    }
+    map.push(preprocessed.len());
+    map
+}
+
+/// Inversa de `map_offsets`: para cada posición del texto preprocesado, la
+/// del texto original. Las `@` agregadas toman la posición del nombre que
+/// las sigue.
+pub fn unmap_offsets(original: &str, preprocessed: &str) -> Vec<usize> {
+    let map = map_offsets(original, preprocessed);
+    let mut inverse = vec![None; preprocessed.len() + 1];
+    for (i, &j) in map.iter().enumerate() {
+        if j < inverse.len() && inverse[j].is_none() {
+            inverse[j] = Some(i);
+        }
+    }
+    let mut result = vec![original.len(); inverse.len()];
+    let mut next = original.len();
+    for (slot, offset) in result.iter_mut().zip(&inverse).rev() {
+        next = offset.unwrap_or(next);
+        *slot = next;
+    }
+    result
+}
@@ -0,0 +1,99 @@
+use crate::tokens::Position;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "advertencia"),
+        }
+    }
+}
+
+/// Mensaje del compilador para el usuario, con notas adicionales opcionales.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub notes: Vec<String>,
+    /// Fragmento del código al que se refiere, si corresponde a uno
+    pub position: Option<Position>,
+    /// Otros fragmentos que ayudan a entender el mensaje, como una
+    /// declaración anterior, cada uno con su nota
+    pub related: Vec<(String, Position)>,
+}
+
+impl Diagnostic {
+    pub fn error<S: Into<String>>(message: S) -> Self {
+        Diagnostic {
+            severity: Severity::Error,
+            message: message.into(),
+            notes: Vec::new(),
+            position: None,
+            related: Vec::new(),
+        }
+    }
+
+    pub fn warning<S: Into<String>>(message: S) -> Self {
+        Diagnostic {
+            severity: Severity::Warning,
+            message: message.into(),
+            notes: Vec::new(),
+            position: None,
+            related: Vec::new(),
+        }
+    }
+
+    pub fn with_note<S: Into<String>>(mut self, note: S) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    pub fn at(mut self, position: Position) -> Self {
+        self.position = Some(position);
+        self
+    }
+
+    /// Agrega una nota que se refiere a otro fragmento del código.
+    pub fn with_related<S: Into<String>>(mut self, note: S, position: Position) -> Self {
+        self.related.push((note.into(), position));
+        self
+    }
+
+    /// Agrega una nota con la línea de `source` donde está `position`, y
+    /// convierte las notas de `related` en notas con su línea.
+    pub fn with_line(mut self, source: &str) -> Self {
+        if let Some(position) = self.position {
+            let line = position.start_line(source);
+            self = self.with_note(format!("en la línea {}", line));
+        }
+        for (note, position) in std::mem::take(&mut self.related) {
+            let line = position.start_line(source);
+            self = self.with_note(format!("{} (línea {})", note, line));
+        }
+        self
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.severity, self.message)?;
+        let related = self.related.iter().map(|(note, _)| note);
+        for note in self.notes.iter().chain(related) {
+            for (i, line) in note.lines().enumerate() {
+                if i == 0 {
+                    write!(f, "\n  = nota: {}", line)?;
+                } else {
+                    write!(f, "\n          {}", line)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -7,6 +7,8 @@ use lalrpop_util::ParseError as LalrpopError;
 pub struct ParseError {
     pub message: String,
     pub line: Option<usize>, // Solo la línea donde ocurre el error
+    /// Fragmento del texto parseado donde ocurre el error, si se conoce
+    pub position: Option<Position>,
 }
 
 impl ParseError {
@@ -14,8 +16,14 @@ impl ParseError {
         Self {
             message: message.into(),
             line,
+            position: None,
         }
     }
+
+    pub fn at(mut self, position: Position) -> Self {
+        self.position = Some(position);
+        self
+    }
 }
 
 /// Punto de entrada del parser con manejo de errores
@@ -47,7 +55,7 @@ fn map_lalrpop_error(err: LalrpopError<usize, Token, &str>, input: &str) -> Pars
     match err {
         InvalidToken { location } => {
             let pos = Position::new(location, location);
-            ParseError::new("Token inválido", Some(adjusted_line(pos, input)))
+            ParseError::new("Token inválido", Some(adjusted_line(pos, input))).at(pos)
         }
         UnrecognizedToken { token: (start, _, end), expected } => {
             let pos = Position::new(start, end);
@@ -55,10 +63,11 @@ fn map_lalrpop_error(err: LalrpopError<usize, Token, &str>, input: &str) -> Pars
                 format!("Token no reconocido, se esperaba uno de: {:?}", expected),
                 Some(adjusted_line(pos, input)),
             )
+            .at(pos)
         }
         ExtraToken { token: (start, _, end) } => {
             let pos = Position::new(start, end);
-            ParseError::new("Token extra", Some(adjusted_line(pos, input)))
+            ParseError::new("Token extra", Some(adjusted_line(pos, input))).at(pos)
         }
         UnrecognizedEof { location, expected } => {
             let pos = Position::new(location, location);
@@ -66,6 +75,7 @@ fn map_lalrpop_error(err: LalrpopError<usize, Token, &str>, input: &str) -> Pars
                 format!("EOF inesperado, se esperaba: {:?}", expected),
                 Some(adjusted_line(pos, input)),
             )
+            .at(pos)
         }
         User { error } => {
             ParseError::new(error, None)
@@ -0,0 +1,649 @@
+use super::{Capture, Expr, ExprKind, Function, FunctionId, Local, LocalId, Program};
+use crate::ast::atoms::atom::Atom;
+use crate::ast::expressions::expressions::Expression;
+use crate::ast::expressions::forr::For;
+use crate::ast::expressions::functiondeclaration::{FunctionDef, Lambda};
+use crate::ast::expressions::ifelse::IfElse;
+use crate::ast::visitor::semantic_type_checker::{SemanticTypeChecker, TypeAnnotations};
+use crate::ast::visitor::types::Type;
+use crate::ast::{self, ExpressionList};
+use crate::diagnostics::Diagnostic;
+use crate::tokens::{BinOp, Identifier, Literal, Position, UnaryOp};
+use std::collections::HashMap;
+
+/// Baja un programa ya chequeado al HIR. `expr_types` son las anotaciones
+/// que dejó `SemanticTypeChecker` sobre ese mismo `program`.
+///
+/// Las funciones del nivel superior se llaman directamente; las anidadas
+/// son variables que guardan una clausura con lo que capturan.
+pub fn lower(program: &ast::Program, expr_types: &TypeAnnotations) -> Result<Program, Diagnostic> {
+    let mut lowering = Lowering {
+        expr_types,
+        functions: Vec::new(),
+        declared: HashMap::new(),
+        current: None,
+        locals: Vec::new(),
+        scopes: vec![HashMap::new()],
+        captures: Vec::new(),
+        enclosing: Vec::new(),
+    };
+    let body = lowering.top_level(&program.expression_list)?;
+    Ok(Program {
+        functions: lowering.functions,
+        locals: lowering.locals,
+        body,
+    })
+}
+
+/// A qué declaración se refiere un nombre.
+#[derive(Debug, Clone, Copy)]
+enum Binding {
+    Local(LocalId),
+    Function(FunctionId),
+}
+
+struct Lowering<'a> {
+    expr_types: &'a TypeAnnotations,
+    functions: Vec<Function>,
+    /// Función reservada para cada declaración ya vista, por la posición de
+    /// su nombre, para no bajarla dos veces cuando se la adelanta al inicio
+    /// de su lista
+    declared: HashMap<usize, FunctionId>,
+    /// Función que se está bajando; `None` en el nivel superior
+    current: Option<FunctionId>,
+    /// Variables de la función que se está bajando
+    locals: Vec<Local>,
+    /// El primer scope de una función guarda sus capturas y su clausura
+    scopes: Vec<HashMap<String, Binding>>,
+    captures: Vec<Capture>,
+    /// Estado de las funciones que contienen a la actual, desde el nivel
+    /// superior hacia adentro
+    enclosing: Vec<Frame>,
+}
+
+/// Lo que se guarda de una función mientras se baja otra anidada en ella.
+struct Frame {
+    function: Option<FunctionId>,
+    locals: Vec<Local>,
+    scopes: Vec<HashMap<String, Binding>>,
+    captures: Vec<Capture>,
+}
+
+/// Posición que cubre todas las expresiones de una lista.
+fn list_span(list: &ExpressionList) -> Position {
+    match (list.expressions.first(), list.expressions.last()) {
+        (Some(first), Some(last)) => Position::new(first.position().start, last.position().end),
+        _ => Position::new(0, 0),
+    }
+}
+
+impl Lowering<'_> {
+    /// Tipo que el chequeo anotó para `expr`, o `fallback` si no lo conoce.
+    fn type_of(&self, expr: &Expression, fallback: Type) -> Type {
+        match self.expr_types.get(&expr.id()) {
+            None | Some(Type::Unknown) => fallback,
+            Some(ty) => ty.clone(),
+        }
+    }
+
+    /// Declaración a la que se refiere `name`. Si es una variable de una
+    /// función exterior, la captura la actual y cada una de las intermedias.
+    fn lookup(&mut self, name: &str) -> Option<Binding> {
+        if let Some(binding) = find(&self.scopes, name) {
+            return Some(binding);
+        }
+        let current = self.save_frame(Vec::new());
+        self.enclosing.push(current);
+        let binding = self.capture(name, self.enclosing.len() - 1);
+        let current = self.enclosing.pop().unwrap();
+        self.restore_frame(current);
+        binding
+    }
+
+    /// Busca `name` en `enclosing[frame]` y, si no está, en los de afuera.
+    fn capture(&mut self, name: &str, frame: usize) -> Option<Binding> {
+        if let Some(binding) = find(&self.enclosing[frame].scopes, name) {
+            return Some(binding);
+        }
+        if frame == 0 {
+            return None;
+        }
+        let outer = match self.capture(name, frame - 1)? {
+            // Las funciones del nivel superior se llaman sin capturarlas
+            Binding::Function(id) => return Some(Binding::Function(id)),
+            Binding::Local(outer) => outer,
+        };
+        let local = self.enclosing[frame - 1].locals[outer].clone();
+        let frame = &mut self.enclosing[frame];
+        frame.locals.push(local);
+        let id = frame.locals.len() - 1;
+        frame.captures.push(Capture { outer, local: id });
+        frame.scopes[0].insert(name.to_string(), Binding::Local(id));
+        Some(Binding::Local(id))
+    }
+
+    /// Cambia el estado de la función actual por uno que empieza con
+    /// `scopes` y devuelve el anterior.
+    fn save_frame(&mut self, scopes: Vec<HashMap<String, Binding>>) -> Frame {
+        Frame {
+            function: self.current,
+            locals: std::mem::take(&mut self.locals),
+            scopes: std::mem::replace(&mut self.scopes, scopes),
+            captures: std::mem::take(&mut self.captures),
+        }
+    }
+
+    /// Vuelve al estado `frame` y devuelve las variables y capturas de la
+    /// función que se estaba bajando.
+    fn restore_frame(&mut self, frame: Frame) -> (Vec<Local>, Vec<Capture>) {
+        self.current = frame.function;
+        self.scopes = frame.scopes;
+        (
+            std::mem::replace(&mut self.locals, frame.locals),
+            std::mem::replace(&mut self.captures, frame.captures),
+        )
+    }
+
+    fn declare_local(&mut self, name: &str, ty: Type, span: Position) -> LocalId {
+        let id = self.locals.len();
+        self.locals.push(Local {
+            name: name.to_string(),
+            ty,
+            span,
+        });
+        self.scopes
+            .last_mut()
+            .unwrap()
+            .insert(name.to_string(), Binding::Local(id));
+        id
+    }
+
+    /// Reserva el lugar de la función; el cuerpo se completa en
+    /// `function_body`.
+    fn reserve_function(&mut self, def: &FunctionDef) -> FunctionId {
+        if let Some(&id) = self.declared.get(&def.name.position.start) {
+            return id;
+        }
+        let id = self.new_function(def);
+        self.declared.insert(def.name.position.start, id);
+        id
+    }
+
+    /// Agrega una función vacía para `def`, sin recordar la declaración.
+    fn new_function(&mut self, def: &FunctionDef) -> FunctionId {
+        let id = self.functions.len();
+        self.functions.push(Function {
+            name: def.name.name.clone(),
+            params: Vec::new(),
+            return_type: def.return_type.clone(),
+            locals: Vec::new(),
+            body: Expr::number(0, def.name.position),
+            span: def.name.position,
+            captures: Vec::new(),
+            parent: self.current,
+            closure: None,
+            as_value: false,
+        });
+        id
+    }
+
+    /// Baja el cuerpo de la función. Dentro se ven sus parámetros y, a
+    /// través de `lookup`, todo lo visible donde se declara. Si es anidada,
+    /// su nombre se refiere a su propia clausura.
+    fn function_body(
+        &mut self,
+        id: FunctionId,
+        def: &FunctionDef,
+        nested: bool,
+    ) -> Result<(), Diagnostic> {
+        let outer = self.save_frame(vec![HashMap::new()]);
+        self.enclosing.push(outer);
+        self.current = Some(id);
+
+        self.scopes.push(HashMap::new());
+        let params = def
+            .params
+            .iter()
+            .map(|param| {
+                self.declare_local(
+                    &param.name.name,
+                    param.signature.clone(),
+                    param.name.position,
+                )
+            })
+            .collect();
+        let closure = nested.then(|| {
+            let local = self.hidden_local(&def.name.name, function_type(def), def.name.position);
+            self.scopes[0].insert(def.name.name.clone(), Binding::Local(local));
+            local
+        });
+        let body = self.expression(&def.body);
+
+        let outer = self.enclosing.pop().unwrap();
+        let (locals, captures) = self.restore_frame(outer);
+        let function = &mut self.functions[id];
+        function.params = params;
+        function.locals = locals;
+        function.captures = captures;
+        function.closure = closure;
+        function.body = body?;
+        Ok(())
+    }
+
+    /// Una función anidada es una variable que se declara después de bajar
+    /// su cuerpo y cuyo valor es la clausura. La declaración vale 0, como en
+    /// el intérprete.
+    fn nested_function(&mut self, def: &FunctionDef, span: Position) -> Result<Expr, Diagnostic> {
+        let id = self.reserve_function(def);
+        self.function_body(id, def, true)?;
+        let ty = function_type(def);
+        let local = self.declare_local(&def.name.name, ty.clone(), def.name.position);
+        let closure = Expr::new(ExprKind::Closure(id), ty.clone(), span);
+        Ok(block(
+            vec![
+                Expr::new(ExprKind::Let(local, Box::new(closure)), ty, span),
+                Expr::number(0, span),
+            ],
+            span,
+        ))
+    }
+
+    /// Una lambda es una función anidada sin nombre que vale su clausura.
+    /// Cada lambda se baja una sola vez, así que no pasa por `declared`.
+    fn lambda(&mut self, lambda: &Lambda, span: Position) -> Result<Expr, Diagnostic> {
+        let def = lambda.as_function(self.type_of(&lambda.body, Type::Unknown));
+        let id = self.new_function(&def);
+        self.function_body(id, &def, true)?;
+        Ok(Expr::new(ExprKind::Closure(id), function_type(&def), span))
+    }
+
+    /// El nivel superior vale su última expresión. Sus funciones se declaran
+    /// antes de recorrerlo, así pueden llamarse desde antes de su
+    /// definición, y no aportan expresiones: quedan en `Program::functions`.
+    fn top_level(&mut self, list: &ExpressionList) -> Result<Expr, Diagnostic> {
+        for expr in &list.expressions {
+            if let Expression::FunctionDef(def) = expr {
+                let id = self.reserve_function(def);
+                self.scopes[0].insert(def.name.name.clone(), Binding::Function(id));
+            }
+        }
+        let mut exprs = Vec::new();
+        for expr in &list.expressions {
+            match expr {
+                Expression::FunctionDef(def) => {
+                    let id = self.reserve_function(def);
+                    self.function_body(id, def, false)?;
+                }
+                _ => exprs.push(self.expression(expr)?),
+            }
+        }
+        Ok(block(exprs, list_span(list)))
+    }
+
+    /// Una lista vale su última expresión.
+    fn expression_list(
+        &mut self,
+        list: &ExpressionList,
+        span: Position,
+    ) -> Result<Expr, Diagnostic> {
+        let exprs = list
+            .expressions
+            .iter()
+            .map(|expr| self.expression(expr))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(block(exprs, span))
+    }
+
+    fn expression(&mut self, expr: &Expression) -> Result<Expr, Diagnostic> {
+        let span = expr.position();
+        let lowered = match expr {
+            Expression::Atom(atom) => match &**atom {
+                Atom::NumberLiteral(literal)
+                | Atom::BooleanLiteral(literal)
+                | Atom::StringLiteral(literal) => {
+                    let (kind, ty) = match literal {
+                        Literal::Number(n, _) => (ExprKind::Number(*n), Type::Number),
+                        Literal::Bool(b, _) => (ExprKind::Boolean(*b), Type::Boolean),
+                        Literal::Str(s, _) => (ExprKind::String(s.clone()), Type::String),
+                    };
+                    Expr::new(kind, ty, span)
+                }
+                Atom::Variable(identifier) => match self.lookup(&identifier.name) {
+                    Some(Binding::Local(id)) => {
+                        let ty = self.type_of(expr, self.locals[id].ty.clone());
+                        Expr::local(id, ty, span)
+                    }
+                    Some(Binding::Function(id)) => {
+                        self.functions[id].as_value = true;
+                        let function = &self.functions[id];
+                        let ty = Type::Function(
+                            function
+                                .params
+                                .iter()
+                                .map(|&param| function.locals[param].ty.clone())
+                                .collect(),
+                            Box::new(function.return_type.clone()),
+                        );
+                        Expr::new(ExprKind::Function(id), self.type_of(expr, ty), span)
+                    }
+                    None => return Err(not_visible(&identifier.name, span)),
+                },
+                Atom::Group(group) => self.expression(&group.expression)?,
+            },
+            Expression::BinaryOp(binop) => {
+                if let BinOp::Assign(_) = binop.operator {
+                    let Some(target) = variable_name(&binop.left) else {
+                        return Err(Diagnostic::error(
+                            "El lado izquierdo de := debe ser una variable",
+                        )
+                        .at(span));
+                    };
+                    let id = self.variable(&target.name, span)?;
+                    let value = self.expression(&binop.right)?;
+                    let ty = value.ty.clone();
+                    Expr::new(ExprKind::Assign(id, Box::new(value)), ty, span)
+                } else {
+                    let left = self.expression(&binop.left)?;
+                    let right = self.expression(&binop.right)?;
+                    let ty = self.type_of(expr, binary_type(&binop.operator));
+                    Expr::new(
+                        ExprKind::Binary(binop.operator.clone(), Box::new(left), Box::new(right)),
+                        ty,
+                        span,
+                    )
+                }
+            }
+            Expression::UnaryOp(unary_op) => {
+                let operand = self.expression(&unary_op.expr)?;
+                match unary_op.op {
+                    // `+x` es `x`
+                    UnaryOp::Plus(_) => operand,
+                    UnaryOp::Not(_) => Expr::new(
+                        ExprKind::Unary(unary_op.op.clone(), Box::new(operand)),
+                        self.type_of(expr, Type::Boolean),
+                        span,
+                    ),
+                    UnaryOp::Minus(_) => Expr::new(
+                        ExprKind::Unary(unary_op.op.clone(), Box::new(operand)),
+                        self.type_of(expr, Type::Number),
+                        span,
+                    ),
+                }
+            }
+            Expression::IfElse(ifelse) => self.ifelse(ifelse, span)?,
+            Expression::LetIn(letin) => {
+                self.scopes.push(HashMap::new());
+                let mut exprs = Vec::new();
+                for assign in &letin.bindings {
+                    // El valor se baja antes de declarar: `let x = x + 1` usa la `x` exterior
+                    let value = self.expression(&assign.body)?;
+                    let (name, name_span) = match &assign.variable {
+                        Atom::Variable(identifier) => (&identifier.name, identifier.position),
+                        _ => {
+                            return Err(
+                                Diagnostic::error("Se esperaba una variable en el let").at(span)
+                            );
+                        }
+                    };
+                    let ty = value.ty.clone();
+                    let id = self.declare_local(name, ty.clone(), name_span);
+                    exprs.push(Expr::new(ExprKind::Let(id, Box::new(value)), ty, name_span));
+                }
+                let body = self.expression(&letin.body);
+                self.scopes.pop();
+                exprs.push(body?);
+                block(exprs, span)
+            }
+            Expression::Block(block) => {
+                self.scopes.push(HashMap::new());
+                let list = self.expression_list(&block.expression_list, span);
+                self.scopes.pop();
+                list?
+            }
+            Expression::Print(value, _) => {
+                let value = self.expression(value)?;
+                let ty = value.ty.clone();
+                Expr::new(ExprKind::Print(Box::new(value)), ty, span)
+            }
+            Expression::While(whilee) => {
+                let condition = self.expression(&whilee.cond)?;
+                let body = self.expression(&whilee.body)?;
+                let ty = self.type_of(expr, body.ty.clone());
+                Expr::new(
+                    ExprKind::While(Box::new(condition), Box::new(body)),
+                    ty,
+                    span,
+                )
+            }
+            Expression::For(forr) => self.for_loop(forr, span)?,
+            // Fuera de un `for` un rango vale su extremo final
+            Expression::Range(start, end) => {
+                let start = self.expression(start)?;
+                let end = self.expression(end)?;
+                block(vec![start, end], span)
+            }
+            Expression::FunctionCall(call) => {
+                let name = &call.funct_name;
+                let binding = self.lookup(&name.name);
+                let args = call
+                    .arguments
+                    .iter()
+                    .map(|arg| self.expression(arg))
+                    .collect::<Result<Vec<_>, _>>()?;
+                match binding {
+                    Some(Binding::Function(id)) => {
+                        let ty = self.type_of(expr, self.functions[id].return_type.clone());
+                        Expr::new(ExprKind::Call(id, args), ty, span)
+                    }
+                    // Una variable que guarda una función
+                    Some(Binding::Local(local)) => {
+                        let callee_ty = self.locals[local].ty.clone();
+                        let result = match &callee_ty {
+                            Type::Function(_, result) => (**result).clone(),
+                            _ => Type::Unknown,
+                        };
+                        let callee = Expr::local(local, callee_ty, name.position);
+                        Expr::new(
+                            ExprKind::CallValue(Box::new(callee), args),
+                            self.type_of(expr, result),
+                            span,
+                        )
+                    }
+                    None => {
+                        return Err(Diagnostic::error(format!(
+                            "'{}' no es una función visible aquí",
+                            name.name
+                        ))
+                        .at(name.position));
+                    }
+                }
+            }
+            Expression::FunctionDef(def) => self.nested_function(def, span)?,
+            Expression::Lambda(lambda) => self.lambda(lambda, span)?,
+        };
+        Ok(lowered)
+    }
+
+    /// Variable a la que se refiere `name`.
+    fn variable(&mut self, name: &str, span: Position) -> Result<LocalId, Diagnostic> {
+        match self.lookup(name) {
+            Some(Binding::Local(id)) => Ok(id),
+            _ => Err(not_visible(name, span)),
+        }
+    }
+
+    /// `if c1 a elif c2 b else d` queda como `if c1 a else (if c2 b else d)`.
+    /// Sin `else`, la última rama vale 0.
+    fn ifelse(&mut self, ifelse: &IfElse, span: Position) -> Result<Expr, Diagnostic> {
+        let mut branches = vec![(
+            self.expression(&ifelse.condition)?,
+            self.expression(&ifelse.then_branch)?,
+        )];
+        for (_, condition, branch) in &ifelse.elif_branches {
+            branches.push((self.expression(condition)?, self.expression(branch)?));
+        }
+        // El 0 que reemplaza al `else` ausente no participa del tipo del if
+        let (mut result, mut ty) = match &ifelse.else_branch {
+            Some(branch) => {
+                let branch = self.expression(branch)?;
+                let ty = branch.ty.clone();
+                (branch, ty)
+            }
+            None => (Expr::number(0, span), Type::Unknown),
+        };
+        for (condition, branch) in branches.into_iter().rev() {
+            ty = SemanticTypeChecker::unify_types(vec![branch.ty.clone(), ty]);
+            let branch_span =
+                Position::new(condition.span.start, result.span.end.max(branch.span.end));
+            result = Expr::new(
+                ExprKind::If(Box::new(condition), Box::new(branch), Box::new(result)),
+                ty.clone(),
+                branch_span,
+            );
+        }
+        result.span = span;
+        Ok(result)
+    }
+
+    /// `for (x in range(a, b)) cuerpo` queda como
+    ///
+    /// ```text
+    /// {
+    ///     let x = a - 1; let x.fin = b;
+    ///     while (x + 1 < x.fin) { x := x + 1; cuerpo }
+    /// }
+    /// ```
+    ///
+    /// Los extremos se evalúan una sola vez y antes de declarar `x`, y el
+    /// cuerpo puede reasignar `x` como en el bucle original. El cuerpo queda
+    /// al final de la iteración para que el bucle valga directamente su
+    /// valor, sin guardarlo en una variable.
+    fn for_loop(&mut self, forr: &For, span: Position) -> Result<Expr, Diagnostic> {
+        let Expression::Range(start, end) = &*forr.iterable else {
+            return Err(Diagnostic::error("El iterable de un for debe ser un rango")
+                .at(forr.iterable.position()));
+        };
+        let Some(identifier) = variable_name(&forr.var) else {
+            return Err(
+                Diagnostic::error("La variable de un for debe ser un identificador")
+                    .at(forr.var.position()),
+            );
+        };
+        let (name, name_span) = (&identifier.name, identifier.position);
+        let start = self.expression(start)?;
+        let end = self.expression(end)?;
+
+        self.scopes.push(HashMap::new());
+        let var = self.declare_local(name, Type::Number, name_span);
+        let body = self.expression(&forr.body);
+        self.scopes.pop();
+        let body = body?;
+        let ty = body.ty.clone();
+        // El temporal no se registra en ningún scope: el código no puede nombrarlo
+        let limit = self.hidden_local(&format!("{}.fin", name), Type::Number, span);
+
+        let arithmetic = |op, left, right| {
+            Expr::new(
+                ExprKind::Binary(op, Box::new(left), Box::new(right)),
+                Type::Number,
+                span,
+            )
+        };
+        let number = |id| Expr::local(id, Type::Number, span);
+        let first = arithmetic(BinOp::Minus(span), start, Expr::number(1, span));
+        let next = arithmetic(BinOp::Plus(span), number(var), Expr::number(1, span));
+        let condition = Expr::new(
+            ExprKind::Binary(
+                BinOp::Less(span),
+                Box::new(next.clone()),
+                Box::new(number(limit)),
+            ),
+            Type::Boolean,
+            span,
+        );
+        let iteration = block(
+            vec![
+                Expr::new(ExprKind::Assign(var, Box::new(next)), Type::Number, span),
+                body,
+            ],
+            span,
+        );
+        Ok(block(
+            vec![
+                Expr::new(ExprKind::Let(var, Box::new(first)), Type::Number, span),
+                Expr::new(ExprKind::Let(limit, Box::new(end)), Type::Number, span),
+                Expr::new(
+                    ExprKind::While(Box::new(condition), Box::new(iteration)),
+                    ty,
+                    span,
+                ),
+            ],
+            span,
+        ))
+    }
+
+    fn hidden_local(&mut self, name: &str, ty: Type, span: Position) -> LocalId {
+        self.locals.push(Local {
+            name: name.to_string(),
+            ty,
+            span,
+        });
+        self.locals.len() - 1
+    }
+}
+
+fn find(scopes: &[HashMap<String, Binding>], name: &str) -> Option<Binding> {
+    scopes
+        .iter()
+        .rev()
+        .find_map(|scope| scope.get(name).copied())
+}
+
+/// Tipo del resultado de un operador binario, para las expresiones que el
+/// optimizador armó de nuevo y no tienen anotación.
+fn binary_type(op: &BinOp) -> Type {
+    match op {
+        BinOp::ConcatString(_) => Type::String,
+        BinOp::EqualEqual(_)
+        | BinOp::NotEqual(_)
+        | BinOp::Greater(_)
+        | BinOp::Less(_)
+        | BinOp::GreaterEqual(_)
+        | BinOp::LessEqual(_)
+        | BinOp::AndAnd(_)
+        | BinOp::OrOr(_) => Type::Boolean,
+        BinOp::Plus(_) | BinOp::Minus(_) | BinOp::Mul(_) | BinOp::Div(_) | BinOp::Mod(_) => {
+            Type::Number
+        }
+        _ => Type::Unknown,
+    }
+}
+
+fn not_visible(name: &str, span: Position) -> Diagnostic {
+    Diagnostic::error(format!("'{}' no es una variable visible aquí", name)).at(span)
+}
+
+/// Tipo de la función declarada como valor.
+fn function_type(def: &FunctionDef) -> Type {
+    Type::Function(
+        def.params.iter().map(|p| p.signature.clone()).collect(),
+        Box::new(def.return_type.clone()),
+    )
+}
+
+/// Identificador de `expr` si es una variable sola.
+fn variable_name(expr: &Expression) -> Option<&Identifier> {
+    match expr {
+        Expression::Atom(atom) => match &**atom {
+            Atom::Variable(identifier) => Some(identifier),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Bloque con el tipo de su última expresión.
+fn block(exprs: Vec<Expr>, span: Position) -> Expr {
+    let ty = exprs.last().map_or(Type::Number, |last| last.ty.clone());
+    Expr::new(ExprKind::Block(exprs), ty, span)
+}
@@ -0,0 +1,235 @@
+//! Representación intermedia de alto nivel (HIR).
+//!
+//! Es el resultado del análisis semántico que consumen los backends: cada
+//! expresión lleva su `Type` resuelto, cada nombre es un índice a su
+//! declaración y ya no hay azúcar sintáctica (`elif` se convierte en `if`
+//! anidados, `for` en un `while` y los paréntesis desaparecen). Las
+//! funciones, incluidas las anidadas, se sacan al nivel del programa; las
+//! anidadas guardan en `captures` qué variables de afuera usan.
+
+pub mod lower;
+
+pub use lower::lower;
+
+use crate::ast::visitor::types::Type;
+use crate::tokens::{BinOp, Position, UnaryOp};
+use std::fmt;
+
+/// Índice de una variable en `locals` de la función que la declara.
+pub type LocalId = usize;
+/// Índice de una función en `Program::functions`.
+pub type FunctionId = usize;
+
+#[derive(Debug, Clone)]
+pub struct Program {
+    pub functions: Vec<Function>,
+    /// Variables del código de nivel superior
+    pub locals: Vec<Local>,
+    pub body: Expr,
+}
+
+#[derive(Debug, Clone)]
+pub struct Function {
+    pub name: String,
+    /// Los parámetros son las primeras variables de `locals`, en orden
+    pub params: Vec<LocalId>,
+    pub return_type: Type,
+    pub locals: Vec<Local>,
+    pub body: Expr,
+    pub span: Position,
+    /// Variables de quien declara la función que usa su cuerpo. Se copian al
+    /// crear la clausura; el chequeo no deja asignarlas adentro.
+    pub captures: Vec<Capture>,
+    /// Función donde se declara una función anidada; `None` si se declara en
+    /// el nivel superior
+    pub parent: Option<FunctionId>,
+    /// En una función anidada, la variable que guarda su propia clausura y
+    /// con la que el cuerpo se nombra a sí mismo
+    pub closure: Option<LocalId>,
+    /// Una función del nivel superior que además se usa como valor
+    pub as_value: bool,
+}
+
+impl Function {
+    /// Las funciones anidadas y las que se usan como valor reciben su
+    /// clausura como primer argumento, antes de los parámetros.
+    pub fn takes_closure(&self) -> bool {
+        self.closure.is_some() || self.as_value
+    }
+}
+
+/// Variable capturada: `outer` en las variables de quien declara la función
+/// y `local` en las de la función.
+#[derive(Debug, Clone, Copy)]
+pub struct Capture {
+    pub outer: LocalId,
+    pub local: LocalId,
+}
+
+/// Declaración de una variable: un parámetro, un binding de `let`, la
+/// variable de un `for` o un temporal creado al quitar el azúcar.
+#[derive(Debug, Clone)]
+pub struct Local {
+    pub name: String,
+    pub ty: Type,
+    pub span: Position,
+}
+
+#[derive(Debug, Clone)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub ty: Type,
+    pub span: Position,
+}
+
+#[derive(Debug, Clone)]
+pub enum ExprKind {
+    Number(i32),
+    Boolean(bool),
+    String(String),
+    Local(LocalId),
+    /// Declara la variable y le da su valor inicial; vale ese valor
+    Let(LocalId, Box<Expr>),
+    /// `:=`; vale el valor asignado
+    Assign(LocalId, Box<Expr>),
+    Binary(BinOp, Box<Expr>, Box<Expr>),
+    Unary(UnaryOp, Box<Expr>),
+    /// Siempre tiene rama `else`: si el código no la tenía, vale 0
+    If(Box<Expr>, Box<Expr>, Box<Expr>),
+    /// Vale lo que valió su última iteración
+    While(Box<Expr>, Box<Expr>),
+    /// Vale su última expresión, o 0 si está vacío
+    Block(Vec<Expr>),
+    Print(Box<Expr>),
+    Call(FunctionId, Vec<Expr>),
+    /// Función del nivel superior usada como valor; no captura nada
+    Function(FunctionId),
+    /// Crea la clausura de una función anidada con el valor actual de sus
+    /// capturas
+    Closure(FunctionId),
+    /// Llama a la función que vale la primera expresión
+    CallValue(Box<Expr>, Vec<Expr>),
+}
+
+impl Expr {
+    pub fn new(kind: ExprKind, ty: Type, span: Position) -> Self {
+        Expr { kind, ty, span }
+    }
+
+    pub fn number(value: i32, span: Position) -> Self {
+        Expr::new(ExprKind::Number(value), Type::Number, span)
+    }
+
+    pub fn local(id: LocalId, ty: Type, span: Position) -> Self {
+        Expr::new(ExprKind::Local(id), ty, span)
+    }
+}
+
+impl Program {
+    /// Variables visibles en `function`, o en el nivel superior si es `None`.
+    pub fn locals_of(&self, function: Option<FunctionId>) -> &[Local] {
+        match function {
+            Some(id) => &self.functions[id].locals,
+            None => &self.locals,
+        }
+    }
+}
+
+/// Volcado legible del HIR (`--emit=hir`): un nodo por línea con su tipo.
+/// Las variables se muestran como `nombre#índice`.
+impl fmt::Display for Program {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (id, function) in self.functions.iter().enumerate() {
+            let params = function
+                .params
+                .iter()
+                .map(|&param| {
+                    let local = &function.locals[param];
+                    format!("{}#{}: {}", local.name, param, local.ty)
+                })
+                .collect::<Vec<_>>()
+                .join(", ");
+            writeln!(
+                f,
+                "function {}#{}({}): {}",
+                function.name, id, params, function.return_type
+            )?;
+            let outer = self.locals_of(function.parent);
+            for capture in &function.captures {
+                let local = &function.locals[capture.local];
+                writeln!(
+                    f,
+                    "  capture {}#{} <- {}#{}",
+                    local.name, capture.local, outer[capture.outer].name, capture.outer
+                )?;
+            }
+            self.write_expr(f, Some(id), &function.body, 1)?;
+            writeln!(f)?;
+        }
+        writeln!(f, "main")?;
+        self.write_expr(f, None, &self.body, 1)
+    }
+}
+
+impl Program {
+    fn write_expr(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        function: Option<FunctionId>,
+        expr: &Expr,
+        depth: usize,
+    ) -> fmt::Result {
+        let locals = self.locals_of(function);
+        let local_name = |id: &LocalId| format!("{}#{}", locals[*id].name, id);
+        let label = match &expr.kind {
+            ExprKind::Number(n) => format!("number {}", n),
+            ExprKind::Boolean(b) => format!("boolean {}", b),
+            ExprKind::String(s) => format!("string {:?}", s),
+            ExprKind::Local(id) => format!("local {}", local_name(id)),
+            ExprKind::Let(id, _) => format!("let {}", local_name(id)),
+            ExprKind::Assign(id, _) => format!("assign {}", local_name(id)),
+            ExprKind::Binary(op, ..) => format!("binary {}", op),
+            ExprKind::Unary(op, _) => match op {
+                UnaryOp::Plus(_) => "unary +".to_string(),
+                UnaryOp::Minus(_) => "unary -".to_string(),
+                UnaryOp::Not(_) => "unary !".to_string(),
+            },
+            ExprKind::If(..) => "if".to_string(),
+            ExprKind::While(..) => "while".to_string(),
+            ExprKind::Block(_) => "block".to_string(),
+            ExprKind::Print(_) => "print".to_string(),
+            ExprKind::Call(id, _) => format!("call {}#{}", self.functions[*id].name, id),
+            ExprKind::Function(id) => format!("function {}#{}", self.functions[*id].name, id),
+            ExprKind::Closure(id) => format!("closure {}#{}", self.functions[*id].name, id),
+            ExprKind::CallValue(..) => "call value".to_string(),
+        };
+        writeln!(f, "{}{} : {}", "  ".repeat(depth), label, expr.ty)?;
+
+        let children: Vec<&Expr> = match &expr.kind {
+            ExprKind::Number(_)
+            | ExprKind::Boolean(_)
+            | ExprKind::String(_)
+            | ExprKind::Local(_)
+            | ExprKind::Function(_)
+            | ExprKind::Closure(_) => Vec::new(),
+            ExprKind::Let(_, value)
+            | ExprKind::Assign(_, value)
+            | ExprKind::Unary(_, value)
+            | ExprKind::Print(value) => vec![value],
+            ExprKind::Binary(_, left, right) | ExprKind::While(left, right) => {
+                vec![left, right]
+            }
+            ExprKind::If(condition, then_branch, else_branch) => {
+                vec![condition, then_branch, else_branch]
+            }
+            ExprKind::Block(exprs) | ExprKind::Call(_, exprs) => exprs.iter().collect(),
+            ExprKind::CallValue(callee, args) => {
+                std::iter::once(&**callee).chain(args.iter()).collect()
+            }
+        };
+        for child in children {
+            self.write_expr(f, function, child, depth + 1)?;
+        }
+        Ok(())
+    }
+}
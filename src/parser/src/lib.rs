@@ -10,6 +10,13 @@ pub use tokens::*;
 pub mod errors;
 pub use errors::{ParseError, parse_program};
 
+pub mod comments;
+pub mod diagnostics;
+pub mod hir;
+pub mod mir;
+pub mod vm;
+pub use diagnostics::{Diagnostic, Severity};
+
 pub use grammar::ProgramParser;
 
 pub use ast::expressions::functioncall;
@@ -0,0 +1,193 @@
+use super::{
+    BinaryOp, Block, BlockId, Function, FunctionId, Inst, InstKind, Operand, Phi, Slot, SlotId,
+    Terminator, Ty, UnaryOp, ValueId,
+};
+
+/// Construye una función del MIR bloque a bloque.
+///
+/// Las instrucciones se agregan al bloque actual, que empieza siendo el de
+/// entrada y se cambia con `switch_to`. Cada método que produce un valor
+/// devuelve el `Operand` con que usarlo y calcula su tipo.
+pub struct FunctionBuilder {
+    function: Function,
+    current: BlockId,
+}
+
+impl FunctionBuilder {
+    pub fn new(name: &str, params: Vec<Ty>, return_ty: Ty) -> Self {
+        FunctionBuilder {
+            function: Function {
+                name: name.to_string(),
+                values: params.clone(),
+                params,
+                return_ty,
+                captures: Vec::new(),
+                slots: Vec::new(),
+                blocks: vec![Block::default()],
+            },
+            current: 0,
+        }
+    }
+
+    /// Tipos de las variables que guarda la clausura de la función.
+    pub fn set_captures(&mut self, captures: Vec<Ty>) {
+        self.function.captures = captures;
+    }
+
+    pub fn param(&self, index: usize) -> Operand {
+        assert!(index < self.function.params.len());
+        Operand::Value(index)
+    }
+
+    pub fn new_block(&mut self) -> BlockId {
+        self.function.blocks.push(Block::default());
+        self.function.blocks.len() - 1
+    }
+
+    pub fn switch_to(&mut self, block: BlockId) {
+        self.current = block;
+    }
+
+    pub fn current_block(&self) -> BlockId {
+        self.current
+    }
+
+    /// Indica si el bloque actual ya tiene terminador.
+    pub fn is_terminated(&self) -> bool {
+        self.function.blocks[self.current].terminator.is_some()
+    }
+
+    pub fn operand_ty(&self, operand: &Operand) -> Ty {
+        self.function.operand_ty(operand)
+    }
+
+    pub fn slot(&mut self, name: &str, ty: Ty) -> SlotId {
+        self.function.slots.push(Slot {
+            name: name.to_string(),
+            ty,
+        });
+        self.function.slots.len() - 1
+    }
+
+    pub fn slot_ty(&self, slot: SlotId) -> Ty {
+        self.function.slots[slot].ty
+    }
+
+    fn new_value(&mut self, ty: Ty) -> ValueId {
+        self.function.values.push(ty);
+        self.function.values.len() - 1
+    }
+
+    fn push(&mut self, dest: Option<ValueId>, kind: InstKind) {
+        let block = &mut self.function.blocks[self.current];
+        assert!(
+            block.terminator.is_none(),
+            "instrucción después del terminador de bb{}",
+            self.current
+        );
+        block.insts.push(Inst { dest, kind });
+    }
+
+    fn push_value(&mut self, ty: Ty, kind: InstKind) -> Operand {
+        let dest = self.new_value(ty);
+        self.push(Some(dest), kind);
+        Operand::Value(dest)
+    }
+
+    pub fn binary(&mut self, op: BinaryOp, lhs: Operand, rhs: Operand) -> Operand {
+        self.push_value(op.result_ty(), InstKind::Binary(op, lhs, rhs))
+    }
+
+    pub fn unary(&mut self, op: UnaryOp, value: Operand) -> Operand {
+        let ty = match op {
+            UnaryOp::Neg => Ty::Int,
+            UnaryOp::Not => Ty::Bool,
+        };
+        self.push_value(ty, InstKind::Unary(op, value))
+    }
+
+    pub fn load(&mut self, slot: SlotId) -> Operand {
+        let ty = self.slot_ty(slot);
+        self.push_value(ty, InstKind::Load(slot))
+    }
+
+    pub fn store(&mut self, slot: SlotId, value: Operand) {
+        self.push(None, InstKind::Store(slot, value));
+    }
+
+    /// Llamada a `function`, que devuelve un valor de tipo `return_ty`.
+    pub fn call(&mut self, function: FunctionId, args: Vec<Operand>, return_ty: Ty) -> Operand {
+        self.push_value(return_ty, InstKind::Call(function, args))
+    }
+
+    pub fn print(&mut self, value: Operand) {
+        self.push(None, InstKind::Print(value));
+    }
+
+    pub fn make_closure(&mut self, function: FunctionId) -> Operand {
+        self.push_value(Ty::Ptr, InstKind::MakeClosure(function))
+    }
+
+    pub fn set_capture(
+        &mut self,
+        closure: Operand,
+        function: FunctionId,
+        index: usize,
+        value: Operand,
+    ) {
+        self.push(None, InstKind::SetCapture(closure, function, index, value));
+    }
+
+    /// Lee la captura `index` de una clausura de `function`, que es de tipo `ty`.
+    pub fn get_capture(
+        &mut self,
+        closure: Operand,
+        function: FunctionId,
+        index: usize,
+        ty: Ty,
+    ) -> Operand {
+        self.push_value(ty, InstKind::GetCapture(closure, function, index))
+    }
+
+    /// Llamada a través de una clausura a una función que devuelve `return_ty`.
+    pub fn call_closure(&mut self, closure: Operand, args: Vec<Operand>, return_ty: Ty) -> Operand {
+        self.push_value(return_ty, InstKind::CallClosure(closure, args))
+    }
+
+    /// Agrega un `phi` al inicio de `block`. Puede llamarse después de haber
+    /// llenado el bloque, cuando ya se conoce el valor que llega por cada
+    /// predecesor, como en la cabecera de un bucle.
+    pub fn phi(&mut self, block: BlockId, ty: Ty, incoming: Vec<(BlockId, Operand)>) -> Operand {
+        let dest = self.new_value(ty);
+        self.function.blocks[block]
+            .phis
+            .push(Phi { dest, incoming });
+        Operand::Value(dest)
+    }
+
+    fn terminate(&mut self, terminator: Terminator) {
+        let block = &mut self.function.blocks[self.current];
+        assert!(
+            block.terminator.is_none(),
+            "bb{} ya tiene terminador",
+            self.current
+        );
+        block.terminator = Some(terminator);
+    }
+
+    pub fn jump(&mut self, target: BlockId) {
+        self.terminate(Terminator::Jump(target));
+    }
+
+    pub fn branch(&mut self, cond: Operand, then_block: BlockId, else_block: BlockId) {
+        self.terminate(Terminator::Branch(cond, then_block, else_block));
+    }
+
+    pub fn ret(&mut self, value: Operand) {
+        self.terminate(Terminator::Return(value));
+    }
+
+    pub fn finish(self) -> Function {
+        self.function
+    }
+}
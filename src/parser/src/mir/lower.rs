@@ -0,0 +1,307 @@
+use super::{
+    BinaryOp, Const, ENTRY, FunctionBuilder, Module, Operand, SYMBOL_PREFIX, SlotId, Ty, UnaryOp,
+};
+use crate::ast::expressions::functiondeclaration::LAMBDA_NAME;
+use crate::ast::visitor::types::Type;
+use crate::hir::{self, Expr, ExprKind, LocalId};
+use crate::tokens::{self, BinOp};
+use std::collections::HashMap;
+
+/// Baja el HIR al MIR. Las funciones conservan su índice del HIR y el
+/// código de nivel superior queda en `ENTRY`, al final.
+///
+/// Una función que recibe su clausura la tiene en `%0`, antes de los
+/// parámetros del código; al entrar copia las capturas a variables.
+pub fn lower(program: &hir::Program) -> Module {
+    let mut module = Module::default();
+    for (id, function) in program.functions.iter().enumerate() {
+        let closure_params = usize::from(function.takes_closure());
+        let params = std::iter::repeat_n(Ty::Ptr, closure_params)
+            .chain(
+                function
+                    .params
+                    .iter()
+                    .map(|&param| repr(&function.locals[param].ty)),
+            )
+            .collect();
+        // Funciones anidadas de distintos scopes pueden llamarse igual, y las
+        // lambdas se llaman todas igual
+        let repeated = program
+            .functions
+            .iter()
+            .filter(|other| other.name == function.name)
+            .count()
+            > 1;
+        let name = match repeated || function.name == LAMBDA_NAME {
+            true => format!("{}{}.{}", SYMBOL_PREFIX, symbol(&function.name), id),
+            false => format!("{}{}", SYMBOL_PREFIX, function.name),
+        };
+        let mut lowering = Lowering::new(
+            program,
+            &function.locals,
+            FunctionBuilder::new(&name, params, repr(&function.return_type)),
+        );
+        // Los parámetros se copian a variables para poder reasignarlos
+        for (index, &param) in function.params.iter().enumerate() {
+            let value = lowering.builder.param(index + closure_params);
+            lowering.declare(param, &value);
+        }
+        if let Some(local) = function.closure {
+            let closure = lowering.builder.param(0);
+            lowering.declare(local, &closure);
+        }
+        let captures = function
+            .captures
+            .iter()
+            .map(|capture| repr(&function.locals[capture.local].ty))
+            .collect::<Vec<_>>();
+        lowering.builder.set_captures(captures.clone());
+        for (index, (capture, ty)) in function.captures.iter().zip(captures).enumerate() {
+            let closure = lowering.builder.param(0);
+            let value = lowering.builder.get_capture(closure, id, index, ty);
+            lowering.declare(capture.local, &value);
+        }
+        let value = lowering.expression(&function.body);
+        lowering.builder.ret(value);
+        module.functions.push(lowering.builder.finish());
+    }
+
+    let mut lowering = Lowering::new(
+        program,
+        &program.locals,
+        FunctionBuilder::new(ENTRY, Vec::new(), Ty::Int),
+    );
+    lowering.expression(&program.body);
+    lowering.builder.ret(Operand::Const(Const::Int(0)));
+    module.functions.push(lowering.builder.finish());
+    module
+}
+
+/// Representación de un tipo de HULK. Los objetos se manejan por referencia.
+pub fn repr(ty: &Type) -> Ty {
+    match ty {
+        Type::Number => Ty::Int,
+        Type::Boolean => Ty::Bool,
+        Type::String | Type::Object | Type::Custom(_) | Type::Function(..) => Ty::Ptr,
+        Type::Range => panic!("Un rango fuera de un for no llega al MIR"),
+        Type::Unknown => panic!("Tipo desconocido sin representación en el MIR"),
+    }
+}
+
+struct Lowering<'a> {
+    program: &'a hir::Program,
+    locals: &'a [hir::Local],
+    builder: FunctionBuilder,
+    /// Variable de cada local del HIR ya declarado
+    slots: HashMap<LocalId, SlotId>,
+}
+
+impl<'a> Lowering<'a> {
+    fn new(program: &'a hir::Program, locals: &'a [hir::Local], builder: FunctionBuilder) -> Self {
+        Lowering {
+            program,
+            locals,
+            builder,
+            slots: HashMap::new(),
+        }
+    }
+
+    /// Crea la variable del local y guarda en ella su valor inicial.
+    fn declare(&mut self, id: LocalId, value: &Operand) {
+        let local = &self.locals[id];
+        // Si el chequeo no llegó a conocer el tipo, vale el del valor
+        let ty = match local.ty {
+            Type::Unknown => self.builder.operand_ty(value),
+            ref ty => repr(ty),
+        };
+        let slot = self.builder.slot(symbol(&local.name), ty);
+        self.builder.store(slot, value.clone());
+        self.slots.insert(id, slot);
+    }
+
+    fn slot(&self, id: LocalId) -> SlotId {
+        *self.slots.get(&id).unwrap_or_else(|| {
+            panic!(
+                "Variable {} usada antes de declararla",
+                self.locals[id].name
+            )
+        })
+    }
+
+    fn expression(&mut self, expr: &Expr) -> Operand {
+        match &expr.kind {
+            ExprKind::Number(n) => Operand::Const(Const::Int(*n)),
+            ExprKind::Boolean(b) => Operand::Const(Const::Bool(*b)),
+            ExprKind::String(s) => Operand::Const(Const::Str(s.clone())),
+            ExprKind::Local(id) => {
+                let slot = self.slot(*id);
+                self.builder.load(slot)
+            }
+            ExprKind::Let(id, value) => {
+                let value = self.expression(value);
+                self.declare(*id, &value);
+                value
+            }
+            // := vale el valor asignado
+            ExprKind::Assign(id, value) => {
+                let value = self.expression(value);
+                let slot = self.slot(*id);
+                self.builder.store(slot, value.clone());
+                value
+            }
+            ExprKind::Binary(op, left, right) => {
+                let left = self.expression(left);
+                let right = self.expression(right);
+                self.builder.binary(binary_op(op), left, right)
+            }
+            ExprKind::Unary(op, operand) => {
+                let operand = self.expression(operand);
+                let op = match op {
+                    tokens::UnaryOp::Not(_) => UnaryOp::Not,
+                    _ => UnaryOp::Neg,
+                };
+                self.builder.unary(op, operand)
+            }
+            ExprKind::If(condition, then_branch, else_branch) => {
+                self.ifelse(condition, then_branch, else_branch)
+            }
+            ExprKind::While(condition, body) => self.while_loop(condition, body),
+            ExprKind::Block(exprs) => {
+                let mut value = Operand::Const(Const::Int(0));
+                for expr in exprs {
+                    value = self.expression(expr);
+                }
+                value
+            }
+            // print devuelve el valor impreso
+            ExprKind::Print(value) => {
+                let value = self.expression(value);
+                self.builder.print(value.clone());
+                value
+            }
+            ExprKind::Call(id, args) => {
+                let function = &self.program.functions[*id];
+                // Llamada por el nombre: no hay clausura que pasarle
+                let closure = function
+                    .takes_closure()
+                    .then_some(Operand::Const(Const::Null));
+                let args = closure
+                    .into_iter()
+                    .chain(args.iter().map(|arg| self.expression(arg)))
+                    .collect();
+                let return_ty = repr(&function.return_type);
+                self.builder.call(*id, args, return_ty)
+            }
+            ExprKind::Function(id) => self.builder.make_closure(*id),
+            ExprKind::Closure(id) => {
+                let closure = self.builder.make_closure(*id);
+                for (index, capture) in self.program.functions[*id].captures.iter().enumerate() {
+                    let slot = self.slot(capture.outer);
+                    let value = self.builder.load(slot);
+                    self.builder.set_capture(closure.clone(), *id, index, value);
+                }
+                closure
+            }
+            ExprKind::CallValue(callee, args) => {
+                let closure = self.expression(callee);
+                let args = args.iter().map(|arg| self.expression(arg)).collect();
+                self.builder.call_closure(closure, args, repr(&expr.ty))
+            }
+        }
+    }
+
+    /// Cada rama termina saltando al bloque de unión, donde un `phi` elige el
+    /// valor. Si las ramas tienen tipos distintos el chequeo ya garantiza
+    /// que el valor se descarta, y no hay `phi`.
+    fn ifelse(&mut self, condition: &Expr, then_branch: &Expr, else_branch: &Expr) -> Operand {
+        let condition = self.expression(condition);
+        let then_block = self.builder.new_block();
+        let else_block = self.builder.new_block();
+        let merge_block = self.builder.new_block();
+        self.builder.branch(condition, then_block, else_block);
+
+        // Una rama puede terminar en otro bloque si tiene control de flujo anidado
+        self.builder.switch_to(then_block);
+        let then_value = self.expression(then_branch);
+        let then_end = self.builder.current_block();
+        self.builder.jump(merge_block);
+
+        self.builder.switch_to(else_block);
+        let else_value = self.expression(else_branch);
+        let else_end = self.builder.current_block();
+        self.builder.jump(merge_block);
+
+        self.builder.switch_to(merge_block);
+        let ty = self.builder.operand_ty(&then_value);
+        if ty != self.builder.operand_ty(&else_value) {
+            return Operand::Const(Const::Int(0));
+        }
+        self.builder.phi(
+            merge_block,
+            ty,
+            vec![(then_end, then_value), (else_end, else_value)],
+        )
+    }
+
+    /// El bucle vale lo que valió su última iteración. Ese valor llega a la
+    /// cabecera por un `phi` que se agrega al cerrar el cuerpo, cuando ya se
+    /// conoce su tipo; antes de la primera iteración vale `Const::default_of`.
+    fn while_loop(&mut self, condition: &Expr, body: &Expr) -> Operand {
+        let preheader = self.builder.current_block();
+        let header = self.builder.new_block();
+        let body_block = self.builder.new_block();
+        let exit = self.builder.new_block();
+        self.builder.jump(header);
+
+        self.builder.switch_to(header);
+        let condition = self.expression(condition);
+        self.builder.branch(condition, body_block, exit);
+
+        self.builder.switch_to(body_block);
+        let value = self.expression(body);
+        let latch = self.builder.current_block();
+        self.builder.jump(header);
+
+        let ty = self.builder.operand_ty(&value);
+        let result = self.builder.phi(
+            header,
+            ty,
+            vec![
+                (preheader, Operand::Const(Const::default_of(ty))),
+                (latch, value),
+            ],
+        );
+        self.builder.switch_to(exit);
+        result
+    }
+}
+
+fn binary_op(op: &BinOp) -> BinaryOp {
+    match op {
+        BinOp::Plus(_) => BinaryOp::Add,
+        BinOp::Minus(_) => BinaryOp::Sub,
+        BinOp::Mul(_) => BinaryOp::Mul,
+        BinOp::Div(_) => BinaryOp::Div,
+        BinOp::Mod(_) => BinaryOp::Rem,
+        BinOp::EqualEqual(_) => BinaryOp::Eq,
+        BinOp::NotEqual(_) => BinaryOp::Ne,
+        BinOp::Less(_) => BinaryOp::Lt,
+        BinOp::LessEqual(_) => BinaryOp::Le,
+        BinOp::Greater(_) => BinaryOp::Gt,
+        BinOp::GreaterEqual(_) => BinaryOp::Ge,
+        BinOp::AndAnd(_) => BinaryOp::And,
+        BinOp::OrOr(_) => BinaryOp::Or,
+        // La gramática no produce estos operadores y `:=` ya es `Assign` en el HIR
+        BinOp::Pow(_) | BinOp::Equal(_) | BinOp::Assign(_) | BinOp::ConcatString(_) => {
+            panic!("Operador '{}' sin traducción al MIR", op)
+        }
+    }
+}
+
+/// Nombre para el MIR: el de las lambdas no es un identificador de LLVM.
+fn symbol(name: &str) -> &str {
+    match name {
+        LAMBDA_NAME => "lambda",
+        name => name,
+    }
+}
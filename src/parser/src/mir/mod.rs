@@ -0,0 +1,387 @@
+//! Representación intermedia de nivel medio (MIR).
+//!
+//! Cada función es un grafo de bloques básicos. Los valores están en forma
+//! SSA: cada uno se define una sola vez, por un parámetro, una instrucción o
+//! un `phi` al inicio de un bloque. Las variables del programa viven en
+//! `slots` a los que se accede con `load` y `store`, así que los únicos `phi`
+//! son los que unen el valor de un `if` o de un bucle. Se construye desde el
+//! HIR con `lower`, se comprueba con `verify` y el generador de LLVM la
+//! traduce instrucción por instrucción.
+
+pub mod builder;
+pub mod lower;
+pub mod verify;
+
+pub use builder::FunctionBuilder;
+pub use lower::lower;
+pub use verify::verify;
+
+use std::fmt;
+
+/// Índice de un valor en `Function::values`.
+pub type ValueId = usize;
+/// Índice de un bloque en `Function::blocks`.
+pub type BlockId = usize;
+/// Índice de una variable en `Function::slots`.
+pub type SlotId = usize;
+/// Índice de una función en `Module::functions`.
+pub type FunctionId = usize;
+
+/// Símbolo del punto de entrada. Las funciones del programa llevan el
+/// prefijo `SYMBOL_PREFIX`, así ninguna choca con él ni con las de la
+/// biblioteca de C o del runtime (`write`, `main`...).
+pub const ENTRY: &str = "main";
+pub const SYMBOL_PREFIX: &str = "hulk.fn.";
+
+/// Representación de un valor en la máquina.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ty {
+    Int,
+    Bool,
+    /// Puntero: strings y objetos
+    Ptr,
+}
+
+impl fmt::Display for Ty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Ty::Int => write!(f, "int"),
+            Ty::Bool => write!(f, "bool"),
+            Ty::Ptr => write!(f, "ptr"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Const {
+    Int(i32),
+    Bool(bool),
+    /// String constante; el backend decide dónde guardarlo
+    Str(String),
+    /// Puntero nulo: la clausura que recibe una función llamada por su nombre
+    Null,
+}
+
+impl Const {
+    pub fn ty(&self) -> Ty {
+        match self {
+            Const::Int(_) => Ty::Int,
+            Const::Bool(_) => Ty::Bool,
+            Const::Str(_) | Const::Null => Ty::Ptr,
+        }
+    }
+
+    /// Valor con que arranca un bucle que todavía no ejecutó su cuerpo.
+    pub fn default_of(ty: Ty) -> Self {
+        match ty {
+            Ty::Int => Const::Int(0),
+            Ty::Bool => Const::Bool(false),
+            Ty::Ptr => Const::Str(String::new()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operand {
+    Value(ValueId),
+    Const(Const),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    And,
+    Or,
+}
+
+impl BinaryOp {
+    pub fn is_comparison(self) -> bool {
+        matches!(
+            self,
+            BinaryOp::Eq | BinaryOp::Ne | BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge
+        )
+    }
+
+    pub fn is_logical(self) -> bool {
+        matches!(self, BinaryOp::And | BinaryOp::Or)
+    }
+
+    /// Tipo del resultado.
+    pub fn result_ty(self) -> Ty {
+        if self.is_comparison() || self.is_logical() {
+            Ty::Bool
+        } else {
+            Ty::Int
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            BinaryOp::Add => "add",
+            BinaryOp::Sub => "sub",
+            BinaryOp::Mul => "mul",
+            BinaryOp::Div => "div",
+            BinaryOp::Rem => "rem",
+            BinaryOp::Eq => "eq",
+            BinaryOp::Ne => "ne",
+            BinaryOp::Lt => "lt",
+            BinaryOp::Le => "le",
+            BinaryOp::Gt => "gt",
+            BinaryOp::Ge => "ge",
+            BinaryOp::And => "and",
+            BinaryOp::Or => "or",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOp {
+    Neg,
+    Not,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum InstKind {
+    Binary(BinaryOp, Operand, Operand),
+    Unary(UnaryOp, Operand),
+    Load(SlotId),
+    Store(SlotId, Operand),
+    Call(FunctionId, Vec<Operand>),
+    Print(Operand),
+    /// Reserva la clausura de la función, con el puntero a su código. Las
+    /// capturas se llenan después con `SetCapture`.
+    MakeClosure(FunctionId),
+    /// Guarda el valor de la captura `usize` en una clausura de la función
+    SetCapture(Operand, FunctionId, usize, Operand),
+    /// Lee la captura `usize` de una clausura de la función
+    GetCapture(Operand, FunctionId, usize),
+    /// Llama a la función de la clausura, que recibe la clausura como
+    /// primer argumento
+    CallClosure(Operand, Vec<Operand>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Inst {
+    /// Valor que define, si produce uno
+    pub dest: Option<ValueId>,
+    pub kind: InstKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Phi {
+    pub dest: ValueId,
+    /// Valor que llega desde cada predecesor
+    pub incoming: Vec<(BlockId, Operand)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Terminator {
+    Jump(BlockId),
+    Branch(Operand, BlockId, BlockId),
+    Return(Operand),
+}
+
+impl Terminator {
+    pub fn successors(&self) -> Vec<BlockId> {
+        match self {
+            Terminator::Jump(target) => vec![*target],
+            Terminator::Branch(_, then_block, else_block) => vec![*then_block, *else_block],
+            Terminator::Return(_) => Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Block {
+    pub phis: Vec<Phi>,
+    pub insts: Vec<Inst>,
+    /// `None` solo mientras el bloque se está construyendo
+    pub terminator: Option<Terminator>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Slot {
+    pub name: String,
+    pub ty: Ty,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Function {
+    pub name: String,
+    /// Los parámetros son los primeros valores: `%0`, `%1`, ...
+    pub params: Vec<Ty>,
+    pub return_ty: Ty,
+    /// Tipo de cada variable que guarda su clausura. Si la función recibe
+    /// una clausura, es su primer parámetro.
+    pub captures: Vec<Ty>,
+    pub slots: Vec<Slot>,
+    /// Tipo de cada valor
+    pub values: Vec<Ty>,
+    /// El primero es el bloque de entrada
+    pub blocks: Vec<Block>,
+}
+
+impl Function {
+    pub fn operand_ty(&self, operand: &Operand) -> Ty {
+        match operand {
+            Operand::Value(id) => self.values[*id],
+            Operand::Const(constant) => constant.ty(),
+        }
+    }
+
+    /// Predecesores de cada bloque.
+    pub fn predecessors(&self) -> Vec<Vec<BlockId>> {
+        let mut preds = vec![Vec::new(); self.blocks.len()];
+        for (id, block) in self.blocks.iter().enumerate() {
+            if let Some(terminator) = &block.terminator {
+                for succ in terminator.successors() {
+                    if succ < preds.len() && !preds[succ].contains(&id) {
+                        preds[succ].push(id);
+                    }
+                }
+            }
+        }
+        preds
+    }
+}
+
+/// Programa completo. El punto de entrada, `ENTRY`, es la última función.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Module {
+    pub functions: Vec<Function>,
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operand::Value(id) => write!(f, "%{}", id),
+            Operand::Const(Const::Int(n)) => write!(f, "{}", n),
+            Operand::Const(Const::Bool(b)) => write!(f, "{}", b),
+            Operand::Const(Const::Str(s)) => write!(f, "{:?}", s),
+            Operand::Const(Const::Null) => write!(f, "null"),
+        }
+    }
+}
+
+/// Volcado textual (`--emit=mir`).
+impl fmt::Display for Module {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, function) in self.functions.iter().enumerate() {
+            if index > 0 {
+                writeln!(f)?;
+            }
+            self.write_function(f, function)?;
+        }
+        Ok(())
+    }
+}
+
+impl Module {
+    fn write_function(&self, f: &mut fmt::Formatter<'_>, function: &Function) -> fmt::Result {
+        let params = function
+            .params
+            .iter()
+            .enumerate()
+            .map(|(i, ty)| format!("%{}: {}", i, ty))
+            .collect::<Vec<_>>()
+            .join(", ");
+        write!(
+            f,
+            "fn {}({}) -> {}",
+            function.name, params, function.return_ty
+        )?;
+        if !function.captures.is_empty() {
+            let captures = function
+                .captures
+                .iter()
+                .map(|ty| ty.to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+            write!(f, " captures [{}]", captures)?;
+        }
+        writeln!(f, " {{")?;
+        for (id, slot) in function.slots.iter().enumerate() {
+            writeln!(f, "  ${} {}: {}", id, slot.name, slot.ty)?;
+        }
+        for (id, block) in function.blocks.iter().enumerate() {
+            writeln!(f, "bb{}:", id)?;
+            for phi in &block.phis {
+                let incoming = phi
+                    .incoming
+                    .iter()
+                    .map(|(pred, value)| format!("[bb{}: {}]", pred, value))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                writeln!(
+                    f,
+                    "  %{}: {} = phi {}",
+                    phi.dest, function.values[phi.dest], incoming
+                )?;
+            }
+            for inst in &block.insts {
+                write!(f, "  ")?;
+                if let Some(dest) = inst.dest {
+                    write!(f, "%{}: {} = ", dest, function.values[dest])?;
+                }
+                match &inst.kind {
+                    InstKind::Binary(op, lhs, rhs) => {
+                        writeln!(f, "{} {}, {}", op.name(), lhs, rhs)?
+                    }
+                    InstKind::Unary(UnaryOp::Neg, value) => writeln!(f, "neg {}", value)?,
+                    InstKind::Unary(UnaryOp::Not, value) => writeln!(f, "not {}", value)?,
+                    InstKind::Load(slot) => writeln!(f, "load ${}", slot)?,
+                    InstKind::Store(slot, value) => writeln!(f, "store ${}, {}", slot, value)?,
+                    InstKind::Call(callee, args) => {
+                        let args = args
+                            .iter()
+                            .map(|arg| arg.to_string())
+                            .collect::<Vec<_>>()
+                            .join(", ");
+                        writeln!(f, "call @{}({})", self.functions[*callee].name, args)?
+                    }
+                    InstKind::Print(value) => writeln!(f, "print {}", value)?,
+                    InstKind::MakeClosure(function) => {
+                        writeln!(f, "closure @{}", self.functions[*function].name)?
+                    }
+                    InstKind::SetCapture(closure, function, index, value) => writeln!(
+                        f,
+                        "set_capture {}, @{}[{}], {}",
+                        closure, self.functions[*function].name, index, value
+                    )?,
+                    InstKind::GetCapture(closure, function, index) => writeln!(
+                        f,
+                        "get_capture {}, @{}[{}]",
+                        closure, self.functions[*function].name, index
+                    )?,
+                    InstKind::CallClosure(closure, args) => {
+                        let args = args
+                            .iter()
+                            .map(|arg| arg.to_string())
+                            .collect::<Vec<_>>()
+                            .join(", ");
+                        writeln!(f, "call_closure {}({})", closure, args)?
+                    }
+                }
+            }
+            match &block.terminator {
+                Some(Terminator::Jump(target)) => writeln!(f, "  jump bb{}", target)?,
+                Some(Terminator::Branch(cond, then_block, else_block)) => {
+                    writeln!(f, "  branch {}, bb{}, bb{}", cond, then_block, else_block)?
+                }
+                Some(Terminator::Return(value)) => writeln!(f, "  return {}", value)?,
+                None => writeln!(f, "  <sin terminador>")?,
+            }
+        }
+        writeln!(f, "}}")
+    }
+}
@@ -0,0 +1,511 @@
+use super::{
+    BlockId, Function, FunctionId, InstKind, Module, Operand, Terminator, Ty, UnaryOp, ValueId,
+};
+
+/// Comprueba que el módulo esté bien formado: cada bloque termina en un
+/// terminador, los `phi` tienen una entrada por predecesor, cada valor se
+/// define una vez y antes de todos sus usos, y los tipos de operandos,
+/// variables, llamadas y retornos coinciden. Devuelve todos los problemas
+/// encontrados.
+pub fn verify(module: &Module) -> Result<(), Vec<String>> {
+    let mut errors = Vec::new();
+    for function in &module.functions {
+        Verifier {
+            module,
+            function,
+            errors: &mut errors,
+            defs: Vec::new(),
+            dominators: Vec::new(),
+        }
+        .run();
+    }
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+/// Dónde se define un valor: bloque y posición dentro del bloque. Los
+/// parámetros y los `phi` están en la posición 0 y la instrucción `i` en la
+/// `i + 1`.
+type Site = (BlockId, usize);
+
+struct Verifier<'a> {
+    module: &'a Module,
+    function: &'a Function,
+    errors: &'a mut Vec<String>,
+    defs: Vec<Option<Site>>,
+    /// Conjunto de dominadores de cada bloque; vacío si es inalcanzable
+    dominators: Vec<Vec<bool>>,
+}
+
+impl<'a> Verifier<'a> {
+    fn error(&mut self, block: BlockId, message: String) {
+        self.errors
+            .push(format!("{}: bb{}: {}", self.function.name, block, message));
+    }
+
+    fn run(&mut self) {
+        let function = self.function;
+        if function.blocks.is_empty() {
+            self.errors
+                .push(format!("{}: la función no tiene bloques", function.name));
+            return;
+        }
+        if !self.check_structure() {
+            return;
+        }
+        self.collect_defs();
+        self.compute_dominators();
+        for (block_id, block) in function.blocks.iter().enumerate() {
+            for phi in &block.phis {
+                let ty = function.values[phi.dest];
+                for (pred, value) in &phi.incoming {
+                    self.check_operand(*pred, usize::MAX, value, ty, "entrada del phi");
+                }
+            }
+            for (index, inst) in block.insts.iter().enumerate() {
+                self.check_inst(block_id, index + 1, &inst.kind, inst.dest);
+            }
+            let end = block.insts.len() + 1;
+            match &block.terminator {
+                Some(Terminator::Branch(cond, ..)) => {
+                    self.check_operand(block_id, end, cond, Ty::Bool, "condición")
+                }
+                Some(Terminator::Return(value)) => {
+                    self.check_operand(block_id, end, value, function.return_ty, "retorno")
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Terminadores, destinos de los saltos y entradas de los `phi`. Si algo
+    /// de esto falla, el resto de las comprobaciones no tiene sentido.
+    fn check_structure(&mut self) -> bool {
+        let function = self.function;
+        let before = self.errors.len();
+        for (block_id, block) in function.blocks.iter().enumerate() {
+            match &block.terminator {
+                None => self.error(block_id, "el bloque no tiene terminador".to_string()),
+                Some(terminator) => {
+                    for succ in terminator.successors() {
+                        if succ >= function.blocks.len() {
+                            self.error(block_id, format!("salto a bb{} inexistente", succ));
+                        }
+                    }
+                }
+            }
+        }
+        if self.errors.len() > before {
+            return false;
+        }
+
+        let preds = function.predecessors();
+        if !preds[0].is_empty() {
+            self.error(
+                0,
+                "el bloque de entrada no puede tener predecesores".to_string(),
+            );
+        }
+        for (block_id, block) in function.blocks.iter().enumerate() {
+            for phi in &block.phis {
+                let mut sources: Vec<BlockId> =
+                    phi.incoming.iter().map(|(pred, _)| *pred).collect();
+                sources.sort_unstable();
+                let mut expected = preds[block_id].clone();
+                expected.sort_unstable();
+                if sources != expected {
+                    self.error(
+                        block_id,
+                        format!(
+                            "el phi %{} tiene entradas de {:?} pero los predecesores son {:?}",
+                            phi.dest, sources, expected
+                        ),
+                    );
+                }
+            }
+        }
+        self.errors.len() == before
+    }
+
+    fn collect_defs(&mut self) {
+        let function = self.function;
+        self.defs = vec![None; function.values.len()];
+        for param in 0..function.params.len() {
+            self.defs[param] = Some((0, 0));
+        }
+        for (block_id, block) in function.blocks.iter().enumerate() {
+            let dests = block.phis.iter().map(|phi| (phi.dest, 0)).chain(
+                block
+                    .insts
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(index, inst)| inst.dest.map(|dest| (dest, index + 1))),
+            );
+            for (dest, position) in dests.collect::<Vec<_>>() {
+                match self.defs.get(dest) {
+                    None => self.error(block_id, format!("%{} no tiene tipo declarado", dest)),
+                    Some(Some(_)) => {
+                        self.error(block_id, format!("%{} se define más de una vez", dest))
+                    }
+                    Some(None) => self.defs[dest] = Some((block_id, position)),
+                }
+            }
+        }
+    }
+
+    /// Dominadores por el método iterativo clásico sobre conjuntos.
+    fn compute_dominators(&mut self) {
+        let function = self.function;
+        let count = function.blocks.len();
+        let preds = function.predecessors();
+
+        let mut reachable = vec![false; count];
+        let mut stack = vec![0];
+        while let Some(block) = stack.pop() {
+            if reachable[block] {
+                continue;
+            }
+            reachable[block] = true;
+            if let Some(terminator) = &function.blocks[block].terminator {
+                stack.extend(terminator.successors());
+            }
+        }
+
+        let mut dominators: Vec<Vec<bool>> = (0..count)
+            .map(|block| match block {
+                0 => (0..count).map(|other| other == 0).collect(),
+                _ if reachable[block] => reachable.clone(),
+                _ => Vec::new(),
+            })
+            .collect();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for block in 1..count {
+                if !reachable[block] {
+                    continue;
+                }
+                let mut dom = reachable.clone();
+                for &pred in preds[block].iter().filter(|&&pred| reachable[pred]) {
+                    for (other, is_dom) in dom.iter_mut().enumerate() {
+                        *is_dom &= dominators[pred][other];
+                    }
+                }
+                dom[block] = true;
+                if dom != dominators[block] {
+                    dominators[block] = dom;
+                    changed = true;
+                }
+            }
+        }
+        self.dominators = dominators;
+    }
+
+    /// Comprueba que `operand` tenga tipo `expected` y, si es un valor, que
+    /// su definición esté antes de la posición `position` de `block`.
+    fn check_operand(
+        &mut self,
+        block: BlockId,
+        position: usize,
+        operand: &Operand,
+        expected: Ty,
+        context: &str,
+    ) {
+        if let Operand::Value(id) = operand
+            && !self.defined_before(*id, block, position)
+        {
+            self.error(
+                block,
+                format!("{}: %{} se usa sin estar definido antes", context, id),
+            );
+            return;
+        }
+        let ty = self.function.operand_ty(operand);
+        if ty != expected {
+            self.error(
+                block,
+                format!(
+                    "{}: {} es {}, se esperaba {}",
+                    context, operand, ty, expected
+                ),
+            );
+        }
+    }
+
+    fn defined_before(&self, value: ValueId, block: BlockId, position: usize) -> bool {
+        let Some(Some((def_block, def_position))) = self.defs.get(value).copied() else {
+            return false;
+        };
+        if def_block == block {
+            return def_position < position;
+        }
+        self.dominators[block]
+            .get(def_block)
+            .copied()
+            .unwrap_or(false)
+    }
+
+    fn check_inst(
+        &mut self,
+        block: BlockId,
+        position: usize,
+        kind: &InstKind,
+        dest: Option<ValueId>,
+    ) {
+        let function = self.function;
+        let dest_ty = dest.map(|dest| function.values[dest]);
+        match kind {
+            InstKind::Binary(op, lhs, rhs) => {
+                let operand_ty = if op.is_logical() {
+                    Ty::Bool
+                } else if op.is_comparison() {
+                    // Se comparan valores del mismo tipo, cualquiera sea
+                    function.operand_ty(lhs)
+                } else {
+                    Ty::Int
+                };
+                self.check_operand(block, position, lhs, operand_ty, op.name());
+                self.check_operand(block, position, rhs, operand_ty, op.name());
+                self.check_result(block, dest_ty, Some(op.result_ty()), op.name());
+            }
+            InstKind::Unary(op, value) => {
+                let (ty, name) = match op {
+                    UnaryOp::Neg => (Ty::Int, "neg"),
+                    UnaryOp::Not => (Ty::Bool, "not"),
+                };
+                self.check_operand(block, position, value, ty, name);
+                self.check_result(block, dest_ty, Some(ty), name);
+            }
+            InstKind::Load(slot) => match function.slots.get(*slot) {
+                None => self.error(block, format!("load de la variable ${} inexistente", slot)),
+                Some(slot) => self.check_result(block, dest_ty, Some(slot.ty), "load"),
+            },
+            InstKind::Store(slot, value) => match function.slots.get(*slot) {
+                None => self.error(block, format!("store en la variable ${} inexistente", slot)),
+                Some(slot) => {
+                    self.check_operand(block, position, value, slot.ty, "store");
+                    self.check_result(block, dest_ty, None, "store");
+                }
+            },
+            InstKind::Call(callee, args) => {
+                let Some(callee) = self.module.functions.get(*callee) else {
+                    self.error(
+                        block,
+                        format!("llamada a la función @{} inexistente", callee),
+                    );
+                    return;
+                };
+                if args.len() != callee.params.len() {
+                    self.error(
+                        block,
+                        format!(
+                            "{} espera {} argumentos, recibe {}",
+                            callee.name,
+                            callee.params.len(),
+                            args.len()
+                        ),
+                    );
+                }
+                for (arg, &ty) in args.iter().zip(&callee.params) {
+                    self.check_operand(block, position, arg, ty, &callee.name);
+                }
+                self.check_result(block, dest_ty, Some(callee.return_ty), &callee.name);
+            }
+            InstKind::Print(value) => {
+                let ty = function.operand_ty(value);
+                self.check_operand(block, position, value, ty, "print");
+                self.check_result(block, dest_ty, None, "print");
+            }
+            InstKind::MakeClosure(callee) => {
+                if self.closure_function(block, *callee).is_some() {
+                    self.check_result(block, dest_ty, Some(Ty::Ptr), "closure");
+                }
+            }
+            InstKind::SetCapture(closure, callee, index, value) => {
+                self.check_operand(block, position, closure, Ty::Ptr, "set_capture");
+                if let Some(ty) = self.capture_ty(block, *callee, *index) {
+                    self.check_operand(block, position, value, ty, "set_capture");
+                }
+                self.check_result(block, dest_ty, None, "set_capture");
+            }
+            InstKind::GetCapture(closure, callee, index) => {
+                self.check_operand(block, position, closure, Ty::Ptr, "get_capture");
+                if let Some(ty) = self.capture_ty(block, *callee, *index) {
+                    self.check_result(block, dest_ty, Some(ty), "get_capture");
+                }
+            }
+            // La función no se conoce hasta ejecutar: solo se comprueba que
+            // los argumentos estén definidos
+            InstKind::CallClosure(closure, args) => {
+                self.check_operand(block, position, closure, Ty::Ptr, "call_closure");
+                for arg in args {
+                    let ty = function.operand_ty(arg);
+                    self.check_operand(block, position, arg, ty, "call_closure");
+                }
+                if dest.is_none() {
+                    self.error(block, "call_closure: falta el destino".to_string());
+                }
+            }
+        }
+    }
+
+    /// Función de una clausura, que recibe la clausura como primer parámetro.
+    fn closure_function(&mut self, block: BlockId, id: FunctionId) -> Option<&'a Function> {
+        let module = self.module;
+        match module.functions.get(id) {
+            None => {
+                self.error(block, format!("clausura de la función @{} inexistente", id));
+                None
+            }
+            Some(callee) if callee.params.first() != Some(&Ty::Ptr) => {
+                self.error(
+                    block,
+                    format!(
+                        "{} no recibe una clausura como primer parámetro",
+                        callee.name
+                    ),
+                );
+                None
+            }
+            Some(callee) => Some(callee),
+        }
+    }
+
+    /// Tipo de la captura `index` de las clausuras de la función `id`.
+    fn capture_ty(&mut self, block: BlockId, id: FunctionId, index: usize) -> Option<Ty> {
+        let callee = self.closure_function(block, id)?;
+        match callee.captures.get(index).copied() {
+            Some(ty) => Some(ty),
+            None => {
+                self.error(
+                    block,
+                    format!("{} no tiene la captura {}", callee.name, index),
+                );
+                None
+            }
+        }
+    }
+
+    /// El destino de una instrucción existe si y solo si produce un valor, y
+    /// tiene el tipo de ese valor.
+    fn check_result(
+        &mut self,
+        block: BlockId,
+        dest_ty: Option<Ty>,
+        expected: Option<Ty>,
+        context: &str,
+    ) {
+        match (dest_ty, expected) {
+            (Some(ty), Some(expected)) if ty != expected => self.error(
+                block,
+                format!(
+                    "{}: el resultado es {}, se esperaba {}",
+                    context, ty, expected
+                ),
+            ),
+            (Some(_), None) => self.error(block, format!("{} no produce un valor", context)),
+            (None, Some(_)) => self.error(block, format!("{}: falta el destino", context)),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mir::{BinaryOp, Const, FunctionBuilder};
+
+    fn module(function: Function) -> Module {
+        Module {
+            functions: vec![function],
+        }
+    }
+
+    /// `f(c) = if c then 1 else 2`, con el valor unido por un `phi`.
+    fn diamond() -> FunctionBuilder {
+        let mut builder = FunctionBuilder::new("f", vec![Ty::Bool], Ty::Int);
+        let then_block = builder.new_block();
+        let else_block = builder.new_block();
+        let merge = builder.new_block();
+        let cond = builder.param(0);
+        builder.branch(cond, then_block, else_block);
+        builder.switch_to(then_block);
+        builder.jump(merge);
+        builder.switch_to(else_block);
+        builder.jump(merge);
+        builder.switch_to(merge);
+        builder
+    }
+
+    #[test]
+    fn accepts_a_well_formed_function() {
+        let mut builder = diamond();
+        let value = builder.phi(
+            3,
+            Ty::Int,
+            vec![
+                (1, Operand::Const(Const::Int(1))),
+                (2, Operand::Const(Const::Int(2))),
+            ],
+        );
+        builder.ret(value);
+        assert_eq!(verify(&module(builder.finish())), Ok(()));
+    }
+
+    #[test]
+    fn rejects_a_return_of_the_wrong_type() {
+        let mut builder = FunctionBuilder::new("f", Vec::new(), Ty::Int);
+        builder.ret(Operand::Const(Const::Bool(true)));
+        let errors = verify(&module(builder.finish())).unwrap_err();
+        assert_eq!(
+            errors,
+            vec!["f: bb0: retorno: true es bool, se esperaba int"]
+        );
+    }
+
+    #[test]
+    fn rejects_a_block_without_terminator() {
+        let mut builder = FunctionBuilder::new("f", Vec::new(), Ty::Int);
+        let next = builder.new_block();
+        builder.jump(next);
+        let errors = verify(&module(builder.finish())).unwrap_err();
+        assert_eq!(errors, vec!["f: bb1: el bloque no tiene terminador"]);
+    }
+
+    #[test]
+    fn rejects_a_phi_missing_a_predecessor() {
+        let mut builder = diamond();
+        let value = builder.phi(3, Ty::Int, vec![(1, Operand::Const(Const::Int(1)))]);
+        builder.ret(value);
+        let errors = verify(&module(builder.finish())).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].starts_with("f: bb3: el phi"), "{:?}", errors);
+    }
+
+    #[test]
+    fn rejects_a_value_used_where_it_is_not_defined() {
+        // El valor se calcula en una sola rama y se usa después de unirlas
+        let mut builder = FunctionBuilder::new("f", vec![Ty::Bool], Ty::Int);
+        let then_block = builder.new_block();
+        let merge = builder.new_block();
+        let cond = builder.param(0);
+        builder.branch(cond, then_block, merge);
+        builder.switch_to(then_block);
+        let sum = builder.binary(
+            BinaryOp::Add,
+            Operand::Const(Const::Int(1)),
+            Operand::Const(Const::Int(2)),
+        );
+        builder.jump(merge);
+        builder.switch_to(merge);
+        builder.ret(sum);
+        let errors = verify(&module(builder.finish())).unwrap_err();
+        assert_eq!(
+            errors,
+            vec!["f: bb2: retorno: %1 se usa sin estar definido antes"]
+        );
+    }
+}
@@ -15,6 +15,22 @@ pub enum Keyword {
     
 }
 
+impl Keyword {
+    pub fn position(&self) -> Position {
+        match self {
+            Keyword::Let(p)
+            | Keyword::In(p)
+            | Keyword::If(p)
+            | Keyword::Else(p)
+            | Keyword::Elif(p)
+            | Keyword::Print(p)
+            | Keyword::While(p)
+            | Keyword::For(p)
+            | Keyword::Function(p) => *p,
+        }
+    }
+}
+
 impl Display for Keyword {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
        let s = match self {
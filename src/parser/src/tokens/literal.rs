@@ -10,6 +10,14 @@ pub enum Literal {
     Bool(bool, Position),
 }
 
+impl Literal {
+    pub fn position(&self) -> Position {
+        match self {
+            Literal::Number(_, p) | Literal::Str(_, p) | Literal::Bool(_, p) => *p,
+        }
+    }
+}
+
 impl fmt::Display for Literal {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
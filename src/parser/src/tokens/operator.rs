@@ -27,6 +27,30 @@ pub enum BinOp {
 }
 
 
+impl BinOp {
+    pub fn position(&self) -> Position {
+        match self {
+            BinOp::Mul(p)
+            | BinOp::Div(p)
+            | BinOp::Mod(p)
+            | BinOp::Pow(p)
+            | BinOp::Plus(p)
+            | BinOp::Minus(p)
+            | BinOp::EqualEqual(p)
+            | BinOp::NotEqual(p)
+            | BinOp::Less(p)
+            | BinOp::LessEqual(p)
+            | BinOp::Greater(p)
+            | BinOp::GreaterEqual(p)
+            | BinOp::AndAnd(p)
+            | BinOp::OrOr(p)
+            | BinOp::Equal(p)
+            | BinOp::Assign(p)
+            | BinOp::ConcatString(p) => *p,
+        }
+    }
+}
+
 impl fmt::Display for BinOp {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let s = match self {
@@ -98,6 +122,17 @@ pub enum GroupingOperator {
     CloseBrace(Position),
 }
 
+impl GroupingOperator {
+    pub fn position(&self) -> Position {
+        match self {
+            GroupingOperator::OpenParen(p)
+            | GroupingOperator::CloseParen(p)
+            | GroupingOperator::OpenBrace(p)
+            | GroupingOperator::CloseBrace(p) => *p,
+        }
+    }
+}
+
 impl fmt::Display for GroupingOperator {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let s = match self {
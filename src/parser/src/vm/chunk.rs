@@ -0,0 +1,166 @@
+use super::value::Value;
+use crate::tokens::Position;
+use std::fmt::Write;
+
+/// Instrucción de la máquina virtual. Los operandos van dentro de la variante:
+/// índices de constantes, de slots locales, de globales o destinos de salto.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OpCode {
+    /// Apila la constante con ese índice
+    Constant(usize),
+    /// Descarta el valor del tope
+    Pop,
+    /// Apila el valor del slot local (relativo a la base del marco)
+    GetLocal(usize),
+    /// Guarda el tope en el slot local sin desapilarlo
+    SetLocal(usize),
+    /// Apila el valor capturado por la clausura en curso
+    GetUpvalue(usize),
+    /// Apila la clausura en curso, con la que una función anidada se nombra
+    /// a sí misma
+    Callee,
+    GetGlobal(usize),
+    /// Guarda el tope en la global sin desapilarlo
+    SetGlobal(usize),
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    Pow,
+    Negate,
+    Not,
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    And,
+    Or,
+    Concat,
+    /// Salto incondicional a la instrucción con ese índice
+    Jump(usize),
+    /// Desapila la condición y salta si es `false`
+    JumpIfFalse(usize),
+    /// Llama a la clausura que está debajo de los n argumentos
+    Call(usize),
+    /// Crea una clausura de la función constante con ese índice
+    Closure(usize),
+    /// Termina con un error cuyo mensaje es la constante con ese índice. Se
+    /// emite para lo que el chequeo rechaza y falla donde fallaría el
+    /// intérprete.
+    Fail(usize),
+    /// Imprime el tope sin desapilarlo
+    Print,
+    /// Devuelve el tope al marco que llamó
+    Return,
+}
+
+/// Bytecode de una función: instrucciones, constantes y la posición del
+/// código fuente de cada instrucción para los errores en tiempo de ejecución.
+#[derive(Debug, Clone, Default)]
+pub struct Chunk {
+    pub code: Vec<OpCode>,
+    pub constants: Vec<Value>,
+    pub positions: Vec<Position>,
+}
+
+impl Chunk {
+    pub fn new() -> Self {
+        Chunk::default()
+    }
+
+    /// Agrega una instrucción y devuelve su índice.
+    pub fn write(&mut self, op: OpCode, position: Position) -> usize {
+        self.code.push(op);
+        self.positions.push(position);
+        self.code.len() - 1
+    }
+
+    pub fn add_constant(&mut self, value: Value) -> usize {
+        self.constants.push(value);
+        self.constants.len() - 1
+    }
+
+    /// Completa el destino de un salto emitido antes de conocerlo.
+    pub fn patch_jump(&mut self, at: usize, target: usize) {
+        self.code[at] = match self.code[at] {
+            OpCode::Jump(_) => OpCode::Jump(target),
+            OpCode::JumpIfFalse(_) => OpCode::JumpIfFalse(target),
+            other => panic!("{:?} no es un salto", other),
+        };
+    }
+
+    /// Texto de una instrucción: índice, operandos y, si los usa, la constante.
+    pub fn disassemble_instruction(&self, offset: usize) -> String {
+        let op = self.code[offset];
+        let detail = match op {
+            OpCode::Constant(index) | OpCode::Closure(index) | OpCode::Fail(index) => {
+                format!(
+                    "{:<14} {:4} '{}'",
+                    op_name(op),
+                    index,
+                    self.constants[index]
+                )
+            }
+            OpCode::GetLocal(n)
+            | OpCode::SetLocal(n)
+            | OpCode::GetUpvalue(n)
+            | OpCode::GetGlobal(n)
+            | OpCode::SetGlobal(n)
+            | OpCode::Call(n) => format!("{:<14} {:4}", op_name(op), n),
+            OpCode::Jump(target) | OpCode::JumpIfFalse(target) => {
+                format!("{:<14} {:4} -> {:04}", op_name(op), offset, target)
+            }
+            _ => op_name(op).to_string(),
+        };
+        format!("{:04}  {}", offset, detail)
+    }
+
+    /// Listado legible de todo el chunk, con `name` como encabezado.
+    pub fn disassemble(&self, name: &str) -> String {
+        let mut out = format!("== {} ==\n", name);
+        for offset in 0..self.code.len() {
+            let _ = writeln!(out, "{}", self.disassemble_instruction(offset));
+        }
+        out
+    }
+}
+
+fn op_name(op: OpCode) -> &'static str {
+    match op {
+        OpCode::Constant(_) => "CONSTANT",
+        OpCode::Pop => "POP",
+        OpCode::GetLocal(_) => "GET_LOCAL",
+        OpCode::SetLocal(_) => "SET_LOCAL",
+        OpCode::GetUpvalue(_) => "GET_UPVALUE",
+        OpCode::Callee => "CALLEE",
+        OpCode::GetGlobal(_) => "GET_GLOBAL",
+        OpCode::SetGlobal(_) => "SET_GLOBAL",
+        OpCode::Add => "ADD",
+        OpCode::Sub => "SUB",
+        OpCode::Mul => "MUL",
+        OpCode::Div => "DIV",
+        OpCode::Mod => "MOD",
+        OpCode::Pow => "POW",
+        OpCode::Negate => "NEGATE",
+        OpCode::Not => "NOT",
+        OpCode::Equal => "EQUAL",
+        OpCode::NotEqual => "NOT_EQUAL",
+        OpCode::Less => "LESS",
+        OpCode::LessEqual => "LESS_EQUAL",
+        OpCode::Greater => "GREATER",
+        OpCode::GreaterEqual => "GREATER_EQUAL",
+        OpCode::And => "AND",
+        OpCode::Or => "OR",
+        OpCode::Concat => "CONCAT",
+        OpCode::Jump(_) => "JUMP",
+        OpCode::JumpIfFalse(_) => "JUMP_IF_FALSE",
+        OpCode::Call(_) => "CALL",
+        OpCode::Closure(_) => "CLOSURE",
+        OpCode::Fail(_) => "FAIL",
+        OpCode::Print => "PRINT",
+        OpCode::Return => "RETURN",
+    }
+}
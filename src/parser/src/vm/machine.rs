@@ -0,0 +1,313 @@
+use super::chunk::OpCode;
+use super::value::{Closure, Function, Value};
+use crate::ast::visitor::interpreter::{MAX_CALL_DEPTH, RuntimeError};
+use crate::tokens::Position;
+use std::cmp::Ordering;
+use std::io::{self, Write};
+use std::rc::Rc;
+
+/// Marco de una llamada en curso.
+struct CallFrame {
+    closure: Rc<Closure>,
+    ip: usize,
+    /// Índice de la pila donde empiezan los slots locales del marco
+    base: usize,
+}
+
+/// Máquina virtual de pila que ejecuta el bytecode del `BytecodeCompiler`.
+pub struct Vm {
+    stack: Vec<Value>,
+    frames: Vec<CallFrame>,
+    globals: Vec<Option<Value>>,
+    global_names: Vec<String>,
+    output: Box<dyn Write>,
+}
+
+impl Vm {
+    pub fn new(global_names: Vec<String>) -> Self {
+        Vm::with_output(global_names, Box::new(io::stdout()))
+    }
+
+    /// Máquina que escribe lo que imprime el programa en `output`.
+    pub fn with_output(global_names: Vec<String>, output: Box<dyn Write>) -> Self {
+        Vm {
+            stack: Vec::new(),
+            frames: Vec::new(),
+            globals: vec![None; global_names.len()],
+            global_names,
+            output,
+        }
+    }
+
+    /// Ejecuta el script y devuelve el valor con que termina.
+    pub fn run(&mut self, script: Rc<Function>) -> Result<Value, RuntimeError> {
+        let closure = Rc::new(Closure {
+            function: script,
+            upvalues: Vec::new(),
+        });
+        self.stack.push(Value::Closure(Rc::clone(&closure)));
+        self.call(closure, 0)?;
+        let result = self.execute();
+        let _ = self.output.flush();
+        if result.is_err() {
+            self.stack.clear();
+            self.frames.clear();
+        }
+        result
+    }
+
+    fn error(&self, message: String) -> RuntimeError {
+        RuntimeError::new(message, self.position())
+    }
+
+    /// Posición del código de la instrucción en curso.
+    fn position(&self) -> Position {
+        let frame = self.frames.last().unwrap();
+        let chunk = &frame.closure.function.chunk;
+        // `ip` ya apunta a la instrucción siguiente a la que falló
+        chunk.positions[frame.ip - 1]
+    }
+
+    /// Abre el marco de `closure`, cuyos `argc` argumentos ya están en la pila.
+    fn call(&mut self, closure: Rc<Closure>, argc: usize) -> Result<(), RuntimeError> {
+        let function = &closure.function;
+        if argc != function.arity {
+            return Err(self.error(format!(
+                "Función '{}' espera {} argumentos, pero se pasaron {}",
+                function.name, function.arity, argc
+            )));
+        }
+        if self.frames.len() >= MAX_CALL_DEPTH {
+            return Err(self.error(format!(
+                "Desbordamiento de pila: más de {} llamadas anidadas",
+                MAX_CALL_DEPTH
+            )));
+        }
+        let base = self.stack.len() - argc;
+        // Los slots locales que no son parámetros empiezan en 0
+        self.stack
+            .resize(base + function.local_count, Value::Number(0));
+        self.frames.push(CallFrame {
+            closure,
+            ip: 0,
+            base,
+        });
+        Ok(())
+    }
+
+    fn pop(&mut self) -> Value {
+        self.stack.pop().expect("pila de la VM vacía")
+    }
+
+    fn pop_number(&mut self, op: &str) -> Result<i32, RuntimeError> {
+        match self.pop() {
+            Value::Number(n) => Ok(n),
+            other => Err(self.error(format!(
+                "Operandos inválidos para el operador '{}': {}",
+                op,
+                other.get_type()
+            ))),
+        }
+    }
+
+    fn pop_bool(&mut self, op: &str) -> Result<bool, RuntimeError> {
+        match self.pop() {
+            Value::Boolean(b) => Ok(b),
+            other => Err(self.error(format!(
+                "Operandos inválidos para el operador '{}': {}",
+                op,
+                other.get_type()
+            ))),
+        }
+    }
+
+    /// Desapila dos números y apila el resultado de `f`.
+    fn binary_number(
+        &mut self,
+        op: &str,
+        f: impl Fn(i32, i32) -> Value,
+    ) -> Result<(), RuntimeError> {
+        let right = self.pop_number(op)?;
+        let left = self.pop_number(op)?;
+        self.stack.push(f(left, right));
+        Ok(())
+    }
+
+    /// Desapila dos números o dos strings y apila si su orden cumple `f`.
+    fn compare(&mut self, op: &str, f: impl Fn(Ordering) -> bool) -> Result<(), RuntimeError> {
+        let right = self.pop();
+        let left = self.pop();
+        let ordering = match (&left, &right) {
+            (Value::Number(l), Value::Number(r)) => l.cmp(r),
+            // Por bytes, igual que el runtime nativo
+            (Value::String(l), Value::String(r)) => l.as_bytes().cmp(r.as_bytes()),
+            _ => {
+                return Err(self.error(format!(
+                    "Operandos inválidos para el operador '{}': {} y {}",
+                    op,
+                    left.get_type(),
+                    right.get_type()
+                )));
+            }
+        };
+        self.stack.push(Value::Boolean(f(ordering)));
+        Ok(())
+    }
+
+    fn execute(&mut self) -> Result<Value, RuntimeError> {
+        loop {
+            let frame = self.frames.last_mut().unwrap();
+            let op = frame.closure.function.chunk.code[frame.ip];
+            frame.ip += 1;
+            let base = frame.base;
+
+            match op {
+                OpCode::Constant(index) => {
+                    let value = frame.closure.function.chunk.constants[index].clone();
+                    self.stack.push(value);
+                }
+                OpCode::Pop => {
+                    self.pop();
+                }
+                OpCode::GetLocal(slot) => {
+                    let value = self.stack[base + slot].clone();
+                    self.stack.push(value);
+                }
+                OpCode::SetLocal(slot) => {
+                    self.stack[base + slot] = self.stack.last().unwrap().clone();
+                }
+                OpCode::GetUpvalue(index) => {
+                    let value = frame.closure.upvalues[index].clone();
+                    self.stack.push(value);
+                }
+                OpCode::Callee => {
+                    let closure = Rc::clone(&frame.closure);
+                    self.stack.push(Value::Closure(closure));
+                }
+                OpCode::GetGlobal(index) => match &self.globals[index] {
+                    Some(value) => self.stack.push(value.clone()),
+                    None => {
+                        return Err(self.error(format!(
+                            "Función '{}' no definida",
+                            self.global_names[index]
+                        )));
+                    }
+                },
+                OpCode::SetGlobal(index) => {
+                    self.globals[index] = Some(self.stack.last().unwrap().clone());
+                }
+                OpCode::Add => self.binary_number("+", |l, r| Value::Number(l.wrapping_add(r)))?,
+                OpCode::Sub => self.binary_number("-", |l, r| Value::Number(l.wrapping_sub(r)))?,
+                OpCode::Mul => self.binary_number("*", |l, r| Value::Number(l.wrapping_mul(r)))?,
+                OpCode::Div | OpCode::Mod => {
+                    let symbol = if op == OpCode::Div { "/" } else { "%" };
+                    let right = self.pop_number(symbol)?;
+                    let left = self.pop_number(symbol)?;
+                    if right == 0 {
+                        return Err(RuntimeError::native(
+                            hulk_runtime::RuntimeError::DivisionByZero,
+                            self.position(),
+                        ));
+                    }
+                    self.stack.push(Value::Number(if op == OpCode::Div {
+                        left.wrapping_div(right)
+                    } else {
+                        left.wrapping_rem(right)
+                    }));
+                }
+                OpCode::Pow => {
+                    let right = self.pop_number("^")?;
+                    let left = self.pop_number("^")?;
+                    if right < 0 {
+                        return Err(
+                            self.error(format!("Exponente negativo en potencia: {}", right))
+                        );
+                    }
+                    self.stack
+                        .push(Value::Number(left.wrapping_pow(right as u32)));
+                }
+                OpCode::Negate => {
+                    let value = self.pop_number("-")?;
+                    self.stack.push(Value::Number(value.wrapping_neg()));
+                }
+                OpCode::Not => {
+                    let value = self.pop_bool("!")?;
+                    self.stack.push(Value::Boolean(!value));
+                }
+                OpCode::Equal | OpCode::NotEqual => {
+                    let right = self.pop();
+                    let left = self.pop();
+                    self.stack
+                        .push(Value::Boolean((left == right) == (op == OpCode::Equal)));
+                }
+                OpCode::Less => self.compare("<", Ordering::is_lt)?,
+                OpCode::LessEqual => self.compare("<=", Ordering::is_le)?,
+                OpCode::Greater => self.compare(">", Ordering::is_gt)?,
+                OpCode::GreaterEqual => self.compare(">=", Ordering::is_ge)?,
+                OpCode::And | OpCode::Or => {
+                    let symbol = if op == OpCode::And { "&&" } else { "||" };
+                    let right = self.pop_bool(symbol)?;
+                    let left = self.pop_bool(symbol)?;
+                    self.stack.push(Value::Boolean(if op == OpCode::And {
+                        left && right
+                    } else {
+                        left || right
+                    }));
+                }
+                OpCode::Concat => {
+                    let right = self.pop();
+                    let left = self.pop();
+                    self.stack
+                        .push(Value::String(Rc::from(format!("{}{}", left, right))));
+                }
+                OpCode::Jump(target) => frame.ip = target,
+                OpCode::JumpIfFalse(target) => {
+                    if !self.pop_bool("if")? {
+                        self.frames.last_mut().unwrap().ip = target;
+                    }
+                }
+                OpCode::Call(argc) => {
+                    let callee = self.stack[self.stack.len() - 1 - argc].clone();
+                    match callee {
+                        Value::Closure(closure) => self.call(closure, argc)?,
+                        other => {
+                            return Err(self.error(format!("'{}' no es una función", other)));
+                        }
+                    }
+                }
+                OpCode::Closure(index) => {
+                    let function = match &frame.closure.function.chunk.constants[index] {
+                        Value::Function(function) => Rc::clone(function),
+                        other => panic!("CLOSURE sobre una constante que no es función: {}", other),
+                    };
+                    let upvalues = function
+                        .upvalues
+                        .iter()
+                        .map(|slot| self.stack[base + slot].clone())
+                        .collect();
+                    self.stack
+                        .push(Value::Closure(Rc::new(Closure { function, upvalues })));
+                }
+                OpCode::Fail(index) => {
+                    let message = frame.closure.function.chunk.constants[index].to_string();
+                    return Err(self.error(message));
+                }
+                OpCode::Print => {
+                    let value = self.stack.last().unwrap();
+                    // Un fallo al escribir (p. ej. una tubería cerrada) no detiene el programa
+                    let _ = writeln!(self.output, "{}", value);
+                }
+                OpCode::Return => {
+                    let result = self.pop();
+                    let frame = self.frames.pop().unwrap();
+                    // Descarta los slots del marco y la clausura llamada
+                    self.stack.truncate(frame.base - 1);
+                    if self.frames.is_empty() {
+                        return Ok(result);
+                    }
+                    self.stack.push(result);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,7 @@
+pub mod chunk;
+pub mod machine;
+pub mod value;
+
+pub use chunk::{Chunk, OpCode};
+pub use machine::Vm;
+pub use value::{Closure, Function, Value};
@@ -0,0 +1,90 @@
+use super::chunk::Chunk;
+use crate::ast::visitor::types::Type;
+use std::fmt;
+use std::rc::Rc;
+
+/// Función ya compilada a bytecode.
+#[derive(Debug, Clone)]
+pub struct Function {
+    pub name: String,
+    pub arity: usize,
+    /// Slots locales del marco, parámetros incluidos
+    pub local_count: usize,
+    /// Slots del marco que crea la clausura que se copian al crearla
+    pub upvalues: Vec<usize>,
+    pub chunk: Chunk,
+}
+
+impl Function {
+    pub fn new(name: &str, arity: usize) -> Self {
+        Function {
+            name: name.to_string(),
+            arity,
+            local_count: arity,
+            upvalues: Vec::new(),
+            chunk: Chunk::new(),
+        }
+    }
+}
+
+/// Función junto con los valores que capturó al crearse.
+#[derive(Debug)]
+pub struct Closure {
+    pub function: Rc<Function>,
+    pub upvalues: Vec<Value>,
+}
+
+/// Valor de la máquina virtual.
+#[derive(Debug, Clone)]
+pub enum Value {
+    Number(i32),
+    Boolean(bool),
+    String(Rc<str>),
+    Function(Rc<Function>),
+    Closure(Rc<Closure>),
+}
+
+impl Value {
+    pub fn get_type(&self) -> Type {
+        match self {
+            Value::Number(_) => Type::Number,
+            Value::Boolean(_) => Type::Boolean,
+            Value::String(_) => Type::String,
+            Value::Function(_) | Value::Closure(_) => Type::Object,
+        }
+    }
+
+    /// Valor de un bucle que no llega a ejecutar su cuerpo, igual que en LLVM.
+    pub fn default_for(ty: &Type) -> Value {
+        match ty {
+            Type::Boolean => Value::Boolean(false),
+            Type::String => Value::String(Rc::from("")),
+            _ => Value::Number(0),
+        }
+    }
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::Number(a), Value::Number(b)) => a == b,
+            (Value::Boolean(a), Value::Boolean(b)) => a == b,
+            (Value::String(a), Value::String(b)) => a == b,
+            (Value::Function(a), Value::Function(b)) => Rc::ptr_eq(a, b),
+            (Value::Closure(a), Value::Closure(b)) => Rc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Number(n) => write!(f, "{}", n),
+            Value::Boolean(b) => write!(f, "{}", b),
+            Value::String(s) => write!(f, "{}", s),
+            Value::Function(function) => write!(f, "<función {}>", function.name),
+            Value::Closure(closure) => write!(f, "<función {}>", closure.function.name),
+        }
+    }
+}
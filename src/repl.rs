@@ -0,0 +1,219 @@
+use crate::driver;
+use parser::visitor::ast_optimizer;
+use parser::visitor::semantic_type_checker::SemanticTypeChecker;
+use parser::visitor::{Interpreter, Value, ValueVisitor};
+use parser::{Atom, Expression, Program};
+use std::io::{self, BufRead, Write};
+
+const PROMPT: &str = "hulk> ";
+const CONTINUATION_PROMPT: &str = "...   ";
+
+/// Sesión interactiva: el chequeo y el intérprete se conservan entre
+/// entradas, así las funciones y variables definidas siguen disponibles.
+struct Repl {
+    checker: SemanticTypeChecker,
+    interpreter: Interpreter,
+    /// Largo del texto de la sesión. Cada entrada se parsea detrás de un
+    /// relleno de ese largo, así sus posiciones, y con ellas los `ExprId` de
+    /// sus anotaciones, no se repiten con las de entradas anteriores.
+    offset: usize,
+}
+
+/// `true` si `source` tiene llaves o paréntesis sin cerrar fuera de los strings.
+fn is_incomplete(source: &str) -> bool {
+    let mut depth = 0i32;
+    let mut in_string = false;
+    let mut escaped = false;
+    for c in source.chars() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '{' | '(' => depth += 1,
+            '}' | ')' => depth -= 1,
+            _ => {}
+        }
+    }
+    depth > 0 || in_string
+}
+
+/// Muestra un valor como se escribiría en HULK.
+fn show_value(value: &Value) -> String {
+    match value {
+        Value::String(s) => format!("{:?}", s),
+        other => other.to_string(),
+    }
+}
+
+impl Repl {
+    fn new() -> Self {
+        Repl {
+            checker: SemanticTypeChecker::new(),
+            interpreter: Interpreter::new(Default::default()),
+            offset: 0,
+        }
+    }
+
+    /// Parsea una entrada. Se acepta sin el `;` final y, además, `let x = e`
+    /// sin `in`, que define `x` para el resto de la sesión.
+    fn parse(&self, input: &str) -> Result<(Program, String, bool), String> {
+        let trimmed = input.trim().trim_end_matches(';');
+        let attempts = [
+            (input.to_string(), false),
+            (format!("{};", trimmed), false),
+            (format!("{} in 0;", trimmed), true),
+        ];
+        let mut first_error = None;
+        for (source, is_definition) in attempts {
+            if is_definition && !trimmed.starts_with("let ") {
+                continue;
+            }
+            let source = " ".repeat(self.offset) + &ast_optimizer::preprocess_functions(&source);
+            match parser::parse_program(&source) {
+                Ok(program) => return Ok((program, source, is_definition)),
+                Err(err) if first_error.is_none() => first_error = Some(err),
+                Err(_) => {}
+            }
+        }
+        let err = first_error.unwrap();
+        Err(match err.line {
+            Some(line) => format!("Error en la línea {}: {}", line, err.message),
+            None => format!("Error: {}", err.message),
+        })
+    }
+
+    /// Chequea el programa; si hay errores, la tabla de símbolos vuelve a
+    /// como estaba antes de la entrada.
+    fn check(&mut self, program: &Program, is_definition: bool) -> Result<(), Vec<String>> {
+        let saved = self.checker.symbol_table.clone();
+        self.checker.expr_types.clear();
+        // En la sesión, definir otra vez una función reemplaza la anterior
+        for expr in &program.expression_list.expressions {
+            if let Expression::FunctionDef(def) = expr {
+                self.checker.symbol_table.remove(&def.name.name);
+            }
+        }
+        match (is_definition, program.expression_list.expressions.first()) {
+            // Los bindings de la definición van al scope global de la sesión
+            (true, Some(Expression::LetIn(letin))) => {
+                for assign in &letin.bindings {
+                    self.checker.visit_assignment(assign);
+                }
+            }
+            _ => self.checker.check(program),
+        }
+        if self.checker.errors.is_empty() {
+            self.interpreter
+                .expr_types
+                .extend(self.checker.expr_types.iter().map(|(k, v)| (*k, v.clone())));
+            Ok(())
+        } else {
+            self.checker.symbol_table = saved;
+            self.checker.error_positions.clear();
+            self.checker.error_related.clear();
+            Err(std::mem::take(&mut self.checker.errors))
+        }
+    }
+
+    fn eval(&mut self, input: &str) {
+        let (program, source, is_definition) = match self.parse(input) {
+            Ok(parsed) => parsed,
+            Err(message) => {
+                eprintln!("{}", message);
+                return;
+            }
+        };
+        if let Err(errors) = self.check(&program, is_definition) {
+            for err in errors {
+                eprintln!("Type error: {}", err);
+            }
+            return;
+        }
+
+        if is_definition {
+            if let Some(Expression::LetIn(letin)) = program.expression_list.expressions.first() {
+                for assign in &letin.bindings {
+                    let name = match &assign.variable {
+                        Atom::Variable(identifier) => identifier.name.clone(),
+                        _ => continue,
+                    };
+                    match self.interpreter.eval(&assign.body) {
+                        Ok(value) => {
+                            let ty = self.checker.type_of(&assign.body);
+                            println!("{} = {} : {}", name, show_value(&value), ty);
+                            self.interpreter.declare_var(&name, value);
+                        }
+                        Err(err) => {
+                            eprintln!("{}", driver::runtime_diagnostic(&err, &source));
+                            break;
+                        }
+                    }
+                }
+            }
+        } else {
+            match self.interpreter.run(&program) {
+                Ok(value) => match program.expression_list.expressions.last() {
+                    // Las declaraciones no tienen valor y print ya mostró el suyo
+                    None | Some(Expression::FunctionDef(_)) | Some(Expression::Print(..)) => {}
+                    Some(last) => {
+                        println!("{} : {}", show_value(&value), self.checker.type_of(last))
+                    }
+                },
+                Err(err) => eprintln!("{}", driver::runtime_diagnostic(&err, &source)),
+            }
+        }
+        self.offset = source.len();
+    }
+}
+
+/// Lee entradas de stdin hasta `:salir` o fin de archivo.
+pub fn run() -> i32 {
+    println!("HULK REPL. Escriba :salir para terminar.");
+    let mut repl = Repl::new();
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    let mut buffer = String::new();
+    loop {
+        print!(
+            "{}",
+            if buffer.is_empty() {
+                PROMPT
+            } else {
+                CONTINUATION_PROMPT
+            }
+        );
+        let _ = io::stdout().flush();
+        let line = match lines.next() {
+            Some(Ok(line)) => line,
+            Some(Err(err)) => {
+                eprintln!("Error: {}", err);
+                return 1;
+            }
+            None => {
+                println!();
+                return 0;
+            }
+        };
+        if buffer.is_empty() {
+            match line.trim() {
+                "" => continue,
+                ":salir" | ":q" => return 0,
+                _ => {}
+            }
+        }
+        buffer.push_str(&line);
+        buffer.push('\n');
+        if is_incomplete(&buffer) {
+            continue;
+        }
+        let input = std::mem::take(&mut buffer);
+        repl.eval(&input);
+    }
+}
@@ -0,0 +1,42 @@
+//! Errores que cortan la ejecución de un programa compilado.
+
+use crate::print;
+use std::fmt;
+use std::process;
+
+/// Error de tiempo de ejecución. Cada uno termina el proceso con su propio
+/// código de salida, distinto del 1 de los fallos del compilador.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuntimeError {
+    DivisionByZero,
+    OutOfMemory,
+    /// `hulk_gc_alloc` recibió una etiqueta de tipo que no existe
+    InvalidTag(i32),
+}
+
+impl RuntimeError {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            RuntimeError::DivisionByZero => 3,
+            RuntimeError::OutOfMemory => 4,
+            RuntimeError::InvalidTag(_) => 5,
+        }
+    }
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuntimeError::DivisionByZero => write!(f, "División por cero"),
+            RuntimeError::OutOfMemory => write!(f, "Memoria agotada"),
+            RuntimeError::InvalidTag(tag) => write!(f, "Etiqueta de tipo inválida: {}", tag),
+        }
+    }
+}
+
+/// Vacía la salida pendiente, informa `error` y termina el proceso.
+pub fn fail(error: RuntimeError) -> ! {
+    print::flush();
+    eprintln!("error: {}", error);
+    process::exit(error.exit_code())
+}
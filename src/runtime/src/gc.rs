@@ -0,0 +1,395 @@
+//! Recolector mark-and-sweep que no mueve objetos.
+//!
+//! El código generado reserva cada objeto con `hulk_gc_alloc`, indicando su
+//! etiqueta de tipo y en qué desplazamientos del objeto hay punteros. Las
+//! raíces son los punteros de los marcos de la pila sombra: cada función que
+//! maneja punteros apila un marco al entrar y lo desapila antes de volver.
+//!
+//! Un puntero que no es de un objeto del recolector (un string constante,
+//! por ejemplo) se ignora al marcar.
+
+use crate::error::{self, RuntimeError};
+use crate::tags::TypeTag;
+use std::alloc::{self, Layout};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::fmt;
+use std::ptr;
+use std::slice;
+
+/// Bytes reservados desde la última recolección que disparan la siguiente
+const INITIAL_THRESHOLD: usize = 1 << 20;
+
+/// Marco de la pila sombra, con la forma que emite el generador de LLVM:
+/// `{ i8*, i64, [N x i8*] }`.
+#[repr(C)]
+pub struct Frame {
+    pub previous: *mut Frame,
+    pub count: i64,
+    pub roots: [*mut u8; 0],
+}
+
+/// Objeto vivo del recolector, guardado aparte de su memoria.
+struct Object {
+    tag: TypeTag,
+    layout: Layout,
+    /// Desplazamientos de los campos que son punteros
+    pointers: *const i64,
+    count: usize,
+    marked: bool,
+}
+
+/// Contadores de `--gc-stats`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Stats {
+    pub collections: usize,
+    pub allocated: usize,
+    pub allocated_bytes: usize,
+    pub freed: usize,
+}
+
+impl Stats {
+    pub fn live(&self) -> usize {
+        self.allocated - self.freed
+    }
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "gc: {} recolecciones, {} objetos reservados ({} bytes), {} liberados, {} vivos al terminar",
+            self.collections,
+            self.allocated,
+            self.allocated_bytes,
+            self.freed,
+            self.live()
+        )
+    }
+}
+
+/// Objetos del recolector, indexados por la dirección que recibe el programa.
+pub struct Heap {
+    objects: HashMap<usize, Object>,
+    threshold: usize,
+    since_last: usize,
+    pub stats: Stats,
+}
+
+impl Default for Heap {
+    fn default() -> Self {
+        Heap::new()
+    }
+}
+
+impl Heap {
+    pub fn new() -> Heap {
+        Heap {
+            objects: HashMap::new(),
+            threshold: INITIAL_THRESHOLD,
+            since_last: 0,
+            stats: Stats::default(),
+        }
+    }
+
+    /// Ya se reservó lo suficiente para que convenga recolectar.
+    pub fn should_collect(&self) -> bool {
+        self.since_last >= self.threshold
+    }
+
+    /// Reserva `size` bytes en cero. `pointers` son los desplazamientos de
+    /// sus campos de puntero y debe seguir vivo mientras viva el objeto: el
+    /// código generado pasa un global constante.
+    pub fn alloc(&mut self, tag: TypeTag, size: usize, pointers: &'static [i64]) -> *mut u8 {
+        let layout = Layout::from_size_align(size.max(1), 16)
+            .unwrap_or_else(|_| error::fail(RuntimeError::OutOfMemory));
+        // SAFETY: el layout nunca tiene tamaño cero
+        let object = unsafe { alloc::alloc_zeroed(layout) };
+        if object.is_null() {
+            error::fail(RuntimeError::OutOfMemory);
+        }
+        self.objects.insert(
+            object as usize,
+            Object {
+                tag,
+                layout,
+                pointers: pointers.as_ptr(),
+                count: pointers.len(),
+                marked: false,
+            },
+        );
+        self.since_last += size;
+        self.stats.allocated += 1;
+        self.stats.allocated_bytes += size;
+        object
+    }
+
+    pub fn tag_of(&self, object: *const u8) -> Option<TypeTag> {
+        self.objects
+            .get(&(object as usize))
+            .map(|object| object.tag)
+    }
+
+    /// Cantidad de objetos que siguen reservados.
+    pub fn len(&self) -> usize {
+        self.objects.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+
+    /// Marca lo alcanzable desde `roots` y libera el resto.
+    ///
+    /// # Safety
+    /// Los campos de puntero de cada objeto vivo tienen que estar dentro de
+    /// su memoria.
+    pub unsafe fn collect(&mut self, roots: impl IntoIterator<Item = *const u8>) {
+        self.stats.collections += 1;
+        let mut pending = Vec::new();
+        for root in roots {
+            self.mark(root, &mut pending);
+        }
+        while let Some(address) = pending.pop() {
+            let object = &self.objects[&address];
+            let offsets = match object.count {
+                0 => &[][..],
+                count => slice::from_raw_parts(object.pointers, count),
+            };
+            for &offset in offsets {
+                let field = (address as *const u8).offset(offset as isize);
+                let child = ptr::read_unaligned(field as *const *const u8);
+                self.mark(child, &mut pending);
+            }
+        }
+
+        let mut live_bytes = 0;
+        let mut freed = 0;
+        self.objects.retain(|&address, object| {
+            if object.marked {
+                object.marked = false;
+                live_bytes += object.layout.size();
+                true
+            } else {
+                alloc::dealloc(address as *mut u8, object.layout);
+                freed += 1;
+                false
+            }
+        });
+        self.stats.freed += freed;
+        self.since_last = 0;
+        self.threshold = (live_bytes * 2).max(INITIAL_THRESHOLD);
+    }
+
+    fn mark(&mut self, address: *const u8, pending: &mut Vec<usize>) {
+        if let Some(object) = self.objects.get_mut(&(address as usize)) {
+            if !object.marked {
+                object.marked = true;
+                pending.push(address as usize);
+            }
+        }
+    }
+}
+
+impl Drop for Heap {
+    fn drop(&mut self) {
+        for (address, object) in self.objects.drain() {
+            // SAFETY: cada dirección se reservó con su layout
+            unsafe { alloc::dealloc(address as *mut u8, object.layout) };
+        }
+    }
+}
+
+thread_local! {
+    static HEAP: RefCell<Heap> = RefCell::new(Heap::new());
+    /// Marco más reciente de la pila sombra
+    static STACK: Cell<*mut Frame> = const { Cell::new(ptr::null_mut()) };
+}
+
+/// Punteros de todos los marcos apilados.
+///
+/// # Safety
+/// Cada marco de la pila tiene `count` raíces.
+unsafe fn stack_roots() -> Vec<*const u8> {
+    let mut roots = Vec::new();
+    let mut frame = STACK.with(Cell::get);
+    while !frame.is_null() {
+        let count = (*frame).count as usize;
+        let first = ptr::addr_of!((*frame).roots) as *const *const u8;
+        roots.extend_from_slice(slice::from_raw_parts(first, count));
+        frame = (*frame).previous;
+    }
+    roots
+}
+
+/// Reserva un objeto del heap global, recolectando antes si corresponde.
+pub fn alloc(tag: TypeTag, size: usize, pointers: &'static [i64]) -> *mut u8 {
+    HEAP.with(|heap| {
+        let mut heap = heap.borrow_mut();
+        if heap.should_collect() {
+            // SAFETY: los marcos los arma el código generado con su
+            // cantidad de raíces y los mapas de punteros de sus objetos
+            unsafe { heap.collect(stack_roots()) };
+        }
+        heap.alloc(tag, size, pointers)
+    })
+}
+
+pub fn stats() -> Stats {
+    HEAP.with(|heap| heap.borrow().stats)
+}
+
+/// # Safety
+/// `frame` vive en la pila de la función que lo apila hasta que lo desapila.
+#[no_mangle]
+pub unsafe extern "C" fn hulk_gc_push_frame(frame: *mut Frame) {
+    (*frame).previous = STACK.with(Cell::get);
+    STACK.with(|stack| stack.set(frame));
+}
+
+/// # Safety
+/// `frame` es el último marco apilado.
+#[no_mangle]
+pub unsafe extern "C" fn hulk_gc_pop_frame(frame: *mut Frame) {
+    STACK.with(|stack| stack.set((*frame).previous));
+}
+
+/// # Safety
+/// `pointers` apunta a `count` desplazamientos que viven lo que el programa.
+#[no_mangle]
+pub unsafe extern "C" fn hulk_gc_alloc(
+    tag: i32,
+    size: i64,
+    pointers: *const i64,
+    count: i64,
+) -> *mut u8 {
+    let tag = TypeTag::from_raw(tag).unwrap_or_else(|| error::fail(RuntimeError::InvalidTag(tag)));
+    let pointers = match count {
+        0 => &[][..],
+        count => slice::from_raw_parts(pointers, count as usize),
+    };
+    alloc(tag, size as usize, pointers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Clausura con un solo campo de puntero, después del de la función
+    static CLOSURE_POINTERS: [i64; 1] = [8];
+
+    /// Guarda `child` en el campo de puntero de `closure`.
+    fn link(closure: *mut u8, child: *const u8) {
+        // SAFETY: las clausuras de estas pruebas miden 16 bytes
+        unsafe { ptr::write_unaligned(closure.add(8) as *mut *const u8, child) };
+    }
+
+    #[test]
+    fn frees_unreachable_objects() {
+        let mut heap = Heap::new();
+        let kept = heap.alloc(TypeTag::Closure, 16, &[]);
+        let lost = heap.alloc(TypeTag::Closure, 16, &[]);
+        // SAFETY: los objetos no tienen campos de puntero
+        unsafe { heap.collect([kept as *const u8]) };
+        assert_eq!(heap.len(), 1);
+        assert_eq!(heap.tag_of(kept), Some(TypeTag::Closure));
+        assert_eq!(heap.tag_of(lost), None);
+    }
+
+    #[test]
+    fn keeps_objects_reachable_through_a_closure() {
+        let mut heap = Heap::new();
+        let closure = heap.alloc(TypeTag::Closure, 16, &CLOSURE_POINTERS);
+        let captured = heap.alloc(TypeTag::Closure, 16, &CLOSURE_POINTERS);
+        let inner = heap.alloc(TypeTag::Closure, 16, &[]);
+        link(closure, captured);
+        link(captured, inner);
+        // SAFETY: los campos de puntero están dentro de cada objeto
+        unsafe { heap.collect([closure as *const u8]) };
+        assert_eq!(heap.len(), 3);
+
+        // Sin raíces se libera toda la cadena
+        unsafe { heap.collect([]) };
+        assert!(heap.is_empty());
+    }
+
+    #[test]
+    fn ignores_pointers_that_are_not_objects() {
+        let mut heap = Heap::new();
+        let closure = heap.alloc(TypeTag::Closure, 16, &CLOSURE_POINTERS);
+        link(closure, c"constante".as_ptr().cast());
+        // SAFETY: el campo de puntero está dentro del objeto
+        unsafe { heap.collect([closure as *const u8, ptr::null()]) };
+        assert_eq!(heap.len(), 1);
+    }
+
+    #[test]
+    fn counts_stats() {
+        let mut heap = Heap::new();
+        let kept = heap.alloc(TypeTag::Closure, 16, &[]);
+        heap.alloc(TypeTag::Closure, 24, &[]);
+        heap.alloc(TypeTag::Closure, 8, &[]);
+        // SAFETY: los objetos no tienen campos de puntero
+        unsafe { heap.collect([kept as *const u8]) };
+        unsafe { heap.collect([kept as *const u8]) };
+        assert_eq!(
+            heap.stats,
+            Stats {
+                collections: 2,
+                allocated: 3,
+                allocated_bytes: 48,
+                freed: 2,
+            }
+        );
+        assert_eq!(heap.stats.live(), 1);
+    }
+
+    /// Marco de la pila sombra con lugar para dos raíces, como el que arma
+    /// una función generada.
+    #[repr(C)]
+    struct TestFrame {
+        frame: Frame,
+        roots: [*mut u8; 2],
+    }
+
+    #[test]
+    fn collects_closures_built_in_a_loop() {
+        let mut storage = TestFrame {
+            frame: Frame {
+                previous: ptr::null_mut(),
+                count: 2,
+                roots: [],
+            },
+            roots: [ptr::null_mut(); 2],
+        };
+        // Todo acceso al marco pasa por este puntero, como en el código generado
+        let frame = ptr::addr_of_mut!(storage);
+        // SAFETY: el marco vive en esta función hasta que se desapila, y las
+        // clausuras de la prueba miden 16 bytes
+        unsafe {
+            hulk_gc_push_frame(frame.cast());
+            // Cada vuelta crea una clausura que captura un objeto nuevo;
+            // solo la última queda en el marco
+            for _ in 0..100_000 {
+                let captured = alloc(TypeTag::Closure, 16, &[]);
+                (*frame).roots[1] = captured;
+                let closure = alloc(TypeTag::Closure, 16, &CLOSURE_POINTERS);
+                link(closure, captured);
+                (*frame).roots = [closure, ptr::null_mut()];
+            }
+            let closure = (*frame).roots[0];
+            let captured = ptr::read_unaligned(closure.add(8) as *const *const u8);
+            HEAP.with(|heap| {
+                let heap = heap.borrow();
+                assert_eq!(heap.tag_of(closure), Some(TypeTag::Closure));
+                assert_eq!(heap.tag_of(captured), Some(TypeTag::Closure));
+            });
+            hulk_gc_pop_frame(frame.cast());
+        }
+        let stats = stats();
+        assert!(stats.collections > 0);
+        assert_eq!(stats.allocated, 200_000);
+        assert!(stats.freed > 0);
+        assert!(stats.live() < stats.allocated);
+    }
+}
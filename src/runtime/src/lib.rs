@@ -0,0 +1,42 @@
+//! Runtime de los ejecutables HULK. El compilador lo enlaza como biblioteca
+//! estática con cada programa, y el código generado solo declara las
+//! funciones `hulk_*` que usa: imprimir, operar con strings y enteros,
+//! reservar memoria del recolector y cortar la ejecución con un error.
+//!
+//! Las funciones exportadas son envoltorios finos de funciones de Rust
+//! comunes, que son las que concentran el comportamiento.
+
+pub mod error;
+pub mod gc;
+pub mod math;
+pub mod print;
+pub mod strings;
+pub mod tags;
+
+pub use error::RuntimeError;
+pub use gc::{Frame, Heap, Stats};
+pub use tags::TypeTag;
+
+use std::cell::Cell;
+
+thread_local! {
+    /// El programa imprime al terminar las estadísticas del recolector
+    static SHOW_STATS: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Primera llamada de `main`. `stats` distinto de cero activa las
+/// estadísticas del recolector de `--gc-stats`.
+#[no_mangle]
+pub extern "C" fn hulk_runtime_init(stats: i32) {
+    SHOW_STATS.with(|show| show.set(stats != 0));
+}
+
+/// Última llamada de `main`: vacía la salida y, si se pidieron, imprime las
+/// estadísticas del recolector en la salida de errores.
+#[no_mangle]
+pub extern "C" fn hulk_runtime_finish() {
+    print::flush();
+    if SHOW_STATS.with(Cell::get) {
+        eprintln!("{}", gc::stats());
+    }
+}
@@ -0,0 +1,54 @@
+//! Aritmética entera que puede fallar.
+
+use crate::error::{self, RuntimeError};
+
+/// Cociente truncado; `i32::MIN / -1` da la vuelta en lugar de abortar.
+pub fn div(lhs: i32, rhs: i32) -> Result<i32, RuntimeError> {
+    match rhs {
+        0 => Err(RuntimeError::DivisionByZero),
+        _ => Ok(lhs.wrapping_div(rhs)),
+    }
+}
+
+/// Resto con el signo del dividendo, como el `%` de C.
+pub fn rem(lhs: i32, rhs: i32) -> Result<i32, RuntimeError> {
+    match rhs {
+        0 => Err(RuntimeError::DivisionByZero),
+        _ => Ok(lhs.wrapping_rem(rhs)),
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn hulk_div(lhs: i32, rhs: i32) -> i32 {
+    div(lhs, rhs).unwrap_or_else(|err| error::fail(err))
+}
+
+#[no_mangle]
+pub extern "C" fn hulk_rem(lhs: i32, rhs: i32) -> i32 {
+    rem(lhs, rhs).unwrap_or_else(|err| error::fail(err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zero_divisor_is_an_error() {
+        assert_eq!(div(7, 0), Err(RuntimeError::DivisionByZero));
+        assert_eq!(rem(7, 0), Err(RuntimeError::DivisionByZero));
+        assert_eq!(div(0, 0), Err(RuntimeError::DivisionByZero));
+    }
+
+    #[test]
+    fn min_over_minus_one_wraps() {
+        assert_eq!(div(i32::MIN, -1), Ok(i32::MIN));
+        assert_eq!(rem(i32::MIN, -1), Ok(0));
+    }
+
+    #[test]
+    fn truncates_toward_zero() {
+        assert_eq!(div(-7, 2), Ok(-3));
+        assert_eq!(rem(-7, 2), Ok(-1));
+        assert_eq!(rem(7, -2), Ok(1));
+    }
+}
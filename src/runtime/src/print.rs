@@ -0,0 +1,51 @@
+//! Salida de `print`. Se acumula en un buffer que se vacía al terminar el
+//! programa o antes de informar un error.
+
+use std::cell::RefCell;
+use std::ffi::{c_char, CStr};
+use std::io::{self, BufWriter, Stdout, Write};
+
+thread_local! {
+    static OUTPUT: RefCell<BufWriter<Stdout>> = RefCell::new(BufWriter::new(io::stdout()));
+}
+
+/// Escribe `text` y un salto de línea. Los errores de escritura se ignoran,
+/// como con `printf`.
+pub fn print_line(text: &[u8]) {
+    OUTPUT.with(|output| {
+        let mut output = output.borrow_mut();
+        let _ = output.write_all(text);
+        let _ = output.write_all(b"\n");
+    });
+}
+
+pub fn flush() {
+    OUTPUT.with(|output| {
+        let _ = output.borrow_mut().flush();
+    });
+}
+
+pub fn bool_text(value: bool) -> &'static str {
+    if value {
+        "true"
+    } else {
+        "false"
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn hulk_print_int(value: i32) {
+    print_line(value.to_string().as_bytes());
+}
+
+#[no_mangle]
+pub extern "C" fn hulk_print_bool(value: bool) {
+    print_line(bool_text(value).as_bytes());
+}
+
+/// # Safety
+/// `text` apunta a un string terminado en `\0`.
+#[no_mangle]
+pub unsafe extern "C" fn hulk_print_str(text: *const c_char) {
+    print_line(CStr::from_ptr(text).to_bytes());
+}
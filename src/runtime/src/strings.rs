@@ -0,0 +1,57 @@
+//! Operaciones con strings. Un string es un puntero a bytes terminados en
+//! `\0`. Todos son constantes globales del programa: el lenguaje todavía no
+//! crea strings en tiempo de ejecución.
+
+use std::cmp::Ordering;
+use std::ffi::{c_char, CStr};
+
+/// Contenido de `text`, sin el `\0`.
+///
+/// # Safety
+/// `text` apunta a un string terminado en `\0` que sigue vivo mientras se
+/// use el resultado.
+pub unsafe fn bytes<'a>(text: *const c_char) -> &'a [u8] {
+    CStr::from_ptr(text).to_bytes()
+}
+
+/// Compara por contenido y devuelve -1, 0 o 1, como un `strcmp` normalizado.
+///
+/// # Safety
+/// Los dos argumentos son strings terminados en `\0`.
+#[no_mangle]
+pub unsafe extern "C" fn hulk_str_cmp(lhs: *const c_char, rhs: *const c_char) -> i32 {
+    match bytes(lhs).cmp(bytes(rhs)) {
+        Ordering::Less => -1,
+        Ordering::Equal => 0,
+        Ordering::Greater => 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cmp(lhs: &std::ffi::CStr, rhs: &std::ffi::CStr) -> i32 {
+        // SAFETY: los dos son strings de C vivos durante la llamada
+        unsafe { hulk_str_cmp(lhs.as_ptr(), rhs.as_ptr()) }
+    }
+
+    #[test]
+    fn compares_by_content() {
+        assert_eq!(cmp(c"hola", c"hola"), 0);
+        assert_eq!(cmp(c"abc", c"abd"), -1);
+        assert_eq!(cmp(c"abd", c"abc"), 1);
+    }
+
+    #[test]
+    fn prefix_is_smaller() {
+        assert_eq!(cmp(c"", c"a"), -1);
+        assert_eq!(cmp(c"abc", c"ab"), 1);
+    }
+
+    #[test]
+    fn compares_bytes_not_pointers() {
+        let copy = std::ffi::CString::new("mundo").unwrap();
+        assert_eq!(cmp(&copy, c"mundo"), 0);
+    }
+}
@@ -0,0 +1,19 @@
+//! Etiquetas de tipo de los objetos del recolector.
+
+/// Qué es un objeto del heap. El código generado pasa el valor numérico a
+/// `hulk_gc_alloc`; el 0 no es la etiqueta de ningún objeto.
+#[repr(i32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TypeTag {
+    /// Struct de una clausura: la función y sus capturas
+    Closure = 1,
+}
+
+impl TypeTag {
+    pub fn from_raw(raw: i32) -> Option<TypeTag> {
+        match raw {
+            1 => Some(TypeTag::Closure),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,170 @@
+use parser::Diagnostic;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+/// Bibliotecas del sistema que necesita la biblioteca estándar de Rust dentro
+/// del runtime, según `rustc --print native-static-libs` en `build.rs`.
+fn runtime_native_libs() -> impl Iterator<Item = &'static str> {
+    env!("HULK_RUNTIME_NATIVE_LIBS").split_whitespace()
+}
+
+/// Artefacto nativo que se le pide al toolchain.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NativeOutput {
+    Asm,
+    Obj,
+    Exe,
+}
+
+/// Herramientas del sistema que convierten LLVM IR en código nativo.
+#[derive(Debug, Clone)]
+pub enum Toolchain {
+    /// `clang` compila el `.ll` y enlaza en un solo paso
+    Clang(PathBuf),
+    /// `llc` genera el objeto y un compilador de C del sistema lo enlaza
+    Llc { llc: PathBuf, linker: PathBuf },
+}
+
+/// Busca `program` en los directorios del `PATH`.
+fn find_in_path(program: &str) -> Option<PathBuf> {
+    let paths = env::var_os("PATH")?;
+    env::split_paths(&paths)
+        .map(|dir| dir.join(program).with_extension(env::consts::EXE_EXTENSION))
+        .find(|candidate| candidate.is_file())
+}
+
+/// Herramienta indicada en la variable de entorno `var`, o la primera de
+/// `candidates` que aparezca en el `PATH`.
+fn locate(var: &str, candidates: &[&str]) -> Option<PathBuf> {
+    if let Some(path) = env::var_os(var) {
+        return Some(PathBuf::from(path));
+    }
+    candidates.iter().find_map(|name| find_in_path(name))
+}
+
+impl Toolchain {
+    /// Localiza `clang` o, en su defecto, `llc` más un enlazador.
+    /// `HULK_CLANG`, `HULK_LLC` y `CC` permiten fijar las rutas a mano.
+    pub fn detect() -> Result<Toolchain, Diagnostic> {
+        if let Some(clang) = locate("HULK_CLANG", &["clang"]) {
+            return Ok(Toolchain::Clang(clang));
+        }
+        match (
+            locate("HULK_LLC", &["llc"]),
+            locate("CC", &["cc", "gcc", "clang"]),
+        ) {
+            (Some(llc), Some(linker)) => Ok(Toolchain::Llc { llc, linker }),
+            (Some(_), None) => Err(Diagnostic::error(
+                "Se encontró `llc` pero ningún enlazador (cc, gcc o clang)",
+            )
+            .with_note("indique el enlazador con la variable de entorno CC")),
+            (None, _) => Err(Diagnostic::error(
+                "No se encontró `clang` ni `llc` para generar código nativo",
+            )
+            .with_note("instale LLVM o indique las rutas con HULK_CLANG o HULK_LLC")
+            .with_note("use --emit=ir para obtener solo el LLVM IR")),
+        }
+    }
+
+    /// Compila el LLVM IR de `ir_path` al artefacto `kind` en `output`. Los
+    /// ejecutables se enlazan con el runtime; los objetos y el ensamblador
+    /// quedan con sus símbolos sin resolver.
+    pub fn compile(
+        &self,
+        ir_path: &Path,
+        output: &Path,
+        kind: NativeOutput,
+        opt_level: u8,
+    ) -> Result<(), Diagnostic> {
+        let runtime = match kind {
+            NativeOutput::Exe => Some(runtime_library()?),
+            _ => None,
+        };
+        match self {
+            Toolchain::Clang(clang) => {
+                let mut command = process::Command::new(clang);
+                command.arg(format!("-O{}", opt_level));
+                match kind {
+                    NativeOutput::Asm => command.arg("-S"),
+                    NativeOutput::Obj => command.arg("-c"),
+                    NativeOutput::Exe => &mut command,
+                };
+                command.arg("-x").arg("ir").arg(ir_path);
+                if let Some(runtime) = &runtime {
+                    command.arg("-x").arg("none").arg(runtime);
+                    command.args(runtime_native_libs());
+                }
+                command.arg("-o").arg(output);
+                run_tool(&mut command)
+            }
+            Toolchain::Llc { llc, linker } => {
+                let llc_output = match kind {
+                    NativeOutput::Exe => output.with_extension("o"),
+                    _ => output.to_path_buf(),
+                };
+                let mut command = process::Command::new(llc);
+                command
+                    .arg(format!("-O={}", opt_level))
+                    // Código reubicable para poder enlazar ejecutables PIE
+                    .arg("-relocation-model=pic")
+                    .arg(match kind {
+                        NativeOutput::Asm => "-filetype=asm",
+                        _ => "-filetype=obj",
+                    })
+                    .arg(ir_path)
+                    .arg("-o")
+                    .arg(&llc_output);
+                run_tool(&mut command)?;
+
+                if kind == NativeOutput::Exe {
+                    let mut command = process::Command::new(linker);
+                    command.arg(&llc_output);
+                    if let Some(runtime) = &runtime {
+                        command.arg(runtime).args(runtime_native_libs());
+                    }
+                    command.arg("-o").arg(output);
+                    let result = run_tool(&mut command);
+                    let _ = fs::remove_file(&llc_output);
+                    result?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Biblioteca estática del runtime: la de `HULK_RUNTIME` o la que compiló
+/// `build.rs` junto con el compilador.
+fn runtime_library() -> Result<PathBuf, Diagnostic> {
+    let path = env::var_os("HULK_RUNTIME")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(env!("HULK_RUNTIME_LIBRARY")));
+    if path.is_file() {
+        return Ok(path);
+    }
+    Err(
+        Diagnostic::error(format!("No se encontró el runtime '{}'", path.display())).with_note(
+            "indique la ruta de libhulk_runtime.a con la variable de entorno HULK_RUNTIME",
+        ),
+    )
+}
+
+/// Ejecuta una herramienta externa y convierte su fallo en un diagnóstico.
+fn run_tool(command: &mut process::Command) -> Result<(), Diagnostic> {
+    let tool = command.get_program().to_string_lossy().into_owned();
+    let result = command.output().map_err(|err| {
+        Diagnostic::error(format!("No se pudo ejecutar `{}`", tool)).with_note(err.to_string())
+    })?;
+    if result.status.success() {
+        return Ok(());
+    }
+    let mut diagnostic =
+        Diagnostic::error(format!("`{}` terminó con error ({})", tool, result.status));
+    let stderr = String::from_utf8_lossy(&result.stderr);
+    if !stderr.trim().is_empty() {
+        diagnostic = diagnostic.with_note(stderr.trim().to_string());
+    }
+    Err(diagnostic)
+}